@@ -0,0 +1,407 @@
+//! Command-line access to a vault without starting the app, for scripts,
+//! pre-commit hooks and CI
+//!
+//! ```text
+//! slate-cli scan <vault> [--json]
+//! slate-cli search <vault> <query> [--limit N] [--json]
+//! slate-cli backlinks <vault> <note> [--json]
+//! slate-cli tasks <vault> [--todo | --done] [--tag TAG] [--folder DIR]
+//!                 [--due-before DATE] [--due-after DATE]
+//!                 [--sort path|due|priority] [--limit N] [--json]
+//! slate-cli check-links <vault> [--json]
+//! slate-cli export <vault> [--format json|graphml|dot]
+//! slate-cli health <vault> [--json]
+//! ```
+//!
+//! Indexes are built in memory from the notes on disk; nothing is written
+//! to the vault. Exits with 2 on errors, and `check-links` and `health`
+//! exit with 1 when they find problems.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use slate_lib::error::SlateError;
+use slate_lib::vault::backlinks::{self, NoteMentions};
+use slate_lib::vault::graph::{self, GraphFilter, GraphFormat};
+use slate_lib::vault::health::{self, HealthReport, LinkProblem};
+use slate_lib::vault::links::LinkIndex;
+use slate_lib::vault::search::{self, query, SearchIndex};
+use slate_lib::vault::tasks::{Task, TaskIndex, TaskQuery};
+use slate_lib::vault::{Vault, VaultHandle};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: slate-cli <command> <vault> [args] [--json]
+
+Commands:
+  scan <vault>                 List the vault's notes
+  search <vault> <query>       Search note contents (--limit N)
+  backlinks <vault> <note>     Notes linking to a note, by path or name
+  tasks <vault>                List tasks (--todo, --done, --tag, --folder,
+                               --due-before, --due-after, --sort, --limit)
+  check-links <vault>          Report broken and ambiguous links
+  export <vault>               Export the link graph (--format json|graphml|dot)
+  health <vault>               Report orphans, dead ends and link problems";
+
+/// Flags that take a value.
+const VALUE_FLAGS: &[&str] = &[
+    "limit",
+    "tag",
+    "folder",
+    "due-before",
+    "due-after",
+    "sort",
+    "format",
+];
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => return usage_error(&e),
+    };
+
+    let result = match args.positional.as_slice() {
+        [command, vault, rest @ ..] => {
+            match VaultHandle::resolve(vault, dirs::home_dir().as_deref()) {
+                Ok(vault) => run(command, &vault, rest, &args),
+                Err(e) => Err(e),
+            }
+        }
+        _ => return usage_error("Missing command or vault"),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            if args.json {
+                print_json(&e);
+            } else {
+                eprintln!("{}", e);
+            }
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("{}\n\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+fn run(
+    command: &str,
+    vault: &VaultHandle,
+    rest: &[String],
+    args: &Args,
+) -> Result<ExitCode, SlateError> {
+    match (command, rest) {
+        ("scan", []) => scan(vault, args),
+        ("search", [query]) => search(vault, query, args),
+        ("backlinks", [note]) => backlinks(vault, note, args),
+        ("tasks", []) => tasks(vault, args),
+        ("check-links", []) => check_links(vault, args),
+        ("export", []) => export(vault, args),
+        ("health", []) => health(vault, args),
+        _ => Err(SlateError::invalid_input(format!(
+            "Unknown command or wrong arguments: {}\n\n{}",
+            command, USAGE
+        ))),
+    }
+}
+
+/// Command line split into positional arguments and `--flag [value]`s.
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<(String, Option<String>)>,
+    json: bool,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut flags = Vec::new();
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                positional.push(arg);
+                continue;
+            };
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None if VALUE_FLAGS.contains(&flag) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--{} needs a value", flag))?;
+                    (flag.to_string(), Some(value))
+                }
+                None => (flag.to_string(), None),
+            };
+            flags.push((name, value));
+        }
+        let json = flags.iter().any(|(name, _)| name == "json");
+        Ok(Args {
+            positional,
+            flags,
+            json,
+        })
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.flags.iter().any(|(flag, _)| flag == name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(flag, _)| flag == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    fn values(&self, name: &str) -> Vec<String> {
+        self.flags
+            .iter()
+            .filter(|(flag, _)| flag == name)
+            .filter_map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn limit(&self) -> Result<Option<usize>, SlateError> {
+        self.value("limit")
+            .map(|limit| {
+                limit.parse().map_err(|_| {
+                    SlateError::invalid_input(format!("--limit needs a number, not {}", limit))
+                })
+            })
+            .transpose()
+    }
+
+    /// A flag whose values are named like the frontend names them, such as
+    /// `--sort due`.
+    fn choice<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>, SlateError> {
+        self.value(name)
+            .map(|value| {
+                serde_json::from_value(serde_json::Value::String(value.to_string())).map_err(|_| {
+                    SlateError::invalid_input(format!("Invalid --{}: {}", name, value))
+                })
+            })
+            .transpose()
+    }
+}
+
+/// Lists the vault's notes, sorted by path.
+fn scan(vault: &VaultHandle, args: &Args) -> Result<ExitCode, SlateError> {
+    let files = Vault::on_disk(vault.root()).scan();
+
+    if args.json {
+        print_json(&files);
+    } else {
+        for file in &files {
+            println!("{}", file.relative_path);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn search(vault: &VaultHandle, text: &str, args: &Args) -> Result<ExitCode, SlateError> {
+    let query = query::parse(text)?;
+    let index = SearchIndex::build(vault.root());
+    let hits = search::search(&index, vault, &query, args.limit()?);
+
+    if args.json {
+        print_json(&hits);
+    } else {
+        for hit in &hits {
+            println!("{}", hit.file.relative_path);
+            for snippet in &hit.snippets {
+                println!("  {}: {}", snippet.line, snippet.text.trim());
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Notes linking to `note`, given by relative path or as a wikilink
+/// target.
+fn backlinks(vault: &VaultHandle, note: &str, args: &Args) -> Result<ExitCode, SlateError> {
+    let index = LinkIndex::build(vault.root());
+    let path = match index.links(note) {
+        Some(_) => note.to_string(),
+        None => index
+            .resolver()
+            .resolve_note(note)
+            .cloned()
+            .ok_or_else(|| SlateError::not_found(note, format!("No such note: {}", note)))?,
+    };
+    let found = backlinks::backlinks(&index, vault, &path);
+
+    if args.json {
+        print_json(&found);
+    } else {
+        print_mentions(&found);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn tasks(vault: &VaultHandle, args: &Args) -> Result<ExitCode, SlateError> {
+    let completed = match (args.has("todo"), args.has("done")) {
+        (true, true) => return Err(SlateError::invalid_input("Use --todo or --done, not both")),
+        (true, false) => Some(false),
+        (false, true) => Some(true),
+        (false, false) => None,
+    };
+    let query = TaskQuery {
+        completed,
+        folder: args.value("folder").map(str::to_string),
+        tags: args
+            .values("tag")
+            .into_iter()
+            .map(|tag| tag.trim_start_matches('#').to_string())
+            .collect(),
+        due_before: args.value("due-before").map(str::to_string),
+        due_after: args.value("due-after").map(str::to_string),
+        sort: args.choice("sort")?.unwrap_or_default(),
+        limit: args.limit()?,
+        ..TaskQuery::default()
+    };
+    let index = TaskIndex::build(vault.root());
+    let found = query.run(index.iter());
+
+    if args.json {
+        print_json(&found);
+    } else {
+        print_tasks(&found);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The link problems in a health report.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LinkCheck {
+    broken_links: Vec<LinkProblem>,
+    ambiguous_links: Vec<LinkProblem>,
+    missing_embeds: Vec<LinkProblem>,
+}
+
+/// Reports broken and ambiguous links and missing embeds. Exits with 1 if
+/// there are any, so it can gate commits.
+fn check_links(vault: &VaultHandle, args: &Args) -> Result<ExitCode, SlateError> {
+    let index = LinkIndex::build(vault.root());
+    let report = health::check(&index, vault.root());
+    let check = LinkCheck {
+        broken_links: report.broken_links,
+        ambiguous_links: report.ambiguous_links,
+        missing_embeds: report.missing_embeds,
+    };
+    let clean = check.broken_links.is_empty()
+        && check.ambiguous_links.is_empty()
+        && check.missing_embeds.is_empty();
+
+    if args.json {
+        print_json(&check);
+    } else {
+        print_links("Broken links", &check.broken_links);
+        print_links("Ambiguous links", &check.ambiguous_links);
+        print_links("Missing embeds", &check.missing_embeds);
+        if clean {
+            println!("No problems found");
+        }
+    }
+    Ok(if clean {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Prints the whole link graph in `--format` (JSON by default).
+fn export(vault: &VaultHandle, args: &Args) -> Result<ExitCode, SlateError> {
+    let format = args.choice("format")?.unwrap_or(GraphFormat::Json);
+    let index = LinkIndex::build(vault.root());
+    let graph = graph::build(&index, &GraphFilter::default())?;
+    println!("{}", graph::export(&graph, format)?);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints the vault's health report. Exits with 1 if anything is wrong, so
+/// it can gate scripts and CI.
+fn health(vault: &VaultHandle, args: &Args) -> Result<ExitCode, SlateError> {
+    let index = LinkIndex::build(vault.root());
+    let report = health::check(&index, vault.root());
+
+    if args.json {
+        print_json(&report);
+    } else {
+        print_report(&report);
+    }
+    Ok(if report.is_healthy() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn print_json(value: &impl Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(text) => println!("{}", text),
+        Err(e) => eprintln!("Failed to serialize output: {}", e),
+    }
+}
+
+fn print_mentions(notes: &[NoteMentions]) {
+    for note in notes {
+        println!("{}", note.file.relative_path);
+        for mention in &note.mentions {
+            println!("  {}: {}", mention.line, mention.text.trim());
+        }
+    }
+}
+
+fn print_tasks(tasks: &[Task]) {
+    for task in tasks {
+        print!(
+            "{}:{}  [{}] {}",
+            task.path, task.line, task.status, task.text
+        );
+        match &task.due {
+            Some(due) => println!("  (due {})", due),
+            None => println!(),
+        }
+    }
+}
+
+fn print_report(report: &HealthReport) {
+    print_notes("Orphans", &report.orphans);
+    print_notes("Dead ends", &report.dead_ends);
+    print_links("Broken links", &report.broken_links);
+    print_links("Ambiguous links", &report.ambiguous_links);
+    print_links("Missing embeds", &report.missing_embeds);
+    if report.is_healthy() {
+        println!("No problems found");
+    }
+}
+
+fn print_notes(title: &str, notes: &[String]) {
+    if notes.is_empty() {
+        return;
+    }
+    println!("{} ({})", title, notes.len());
+    for note in notes {
+        println!("  {}", note);
+    }
+}
+
+fn print_links(title: &str, problems: &[LinkProblem]) {
+    if problems.is_empty() {
+        return;
+    }
+    println!("{} ({})", title, problems.len());
+    for problem in problems {
+        print!(
+            "  {}:{}:{}  {}",
+            problem.file, problem.line, problem.column, problem.target
+        );
+        match problem.candidates.split_first() {
+            Some((chosen, others)) => println!(" -> {} (also {})", chosen, others.join(", ")),
+            None => println!(),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Errors returned by backend commands
+//!
+//! Every command fails with a [`SlateError`]. It reaches the frontend as an
+//! object tagged with a stable `code`, so the UI can tell a missing vault
+//! from a permission problem without parsing `message`.
+
+use crate::vault::note::NoteContent;
+use crate::vault::search::query::QueryError;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Why a command failed. Every variant carries a human readable `message`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(
+    tag = "code",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SlateError {
+    /// The vault root does not exist.
+    VaultNotFound { path: String, message: String },
+    /// The vault root exists but is not a directory.
+    NotADirectory { path: String, message: String },
+    /// The vault is not open in this app.
+    VaultNotOpen { path: String, message: String },
+    /// An index the command needs is still being built; retry later.
+    IndexNotReady { message: String },
+    /// A note or file does not exist.
+    NotFound { path: String, message: String },
+    /// Something already exists where a file was to be created.
+    AlreadyExists { path: String, message: String },
+    /// The OS refused access to a file or directory.
+    PermissionDenied { path: String, message: String },
+    /// A path is malformed, or points outside the vault.
+    InvalidPath { path: String, message: String },
+    /// A note is not valid UTF-8.
+    InvalidEncoding { path: String, message: String },
+    /// An argument was rejected, e.g. an invalid tag or property name.
+    InvalidInput { message: String },
+    /// A search or query did not parse; `start..end` is the offending part
+    /// of it, in UTF-16 code units.
+    Query {
+        message: String,
+        start: usize,
+        end: usize,
+    },
+    /// The note changed on disk since the editor loaded it. Nothing was
+    /// written; both versions are returned so the user can reconcile them.
+    Conflict {
+        path: String,
+        /// The text the editor tried to save.
+        ours: String,
+        /// What is on disk now, or None if the note was deleted.
+        theirs: Option<NoteContent>,
+        message: String,
+    },
+    /// Any other I/O failure.
+    Io {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        #[serde(serialize_with = "serialize_kind")]
+        io_kind: io::ErrorKind,
+        message: String,
+    },
+    /// A failure that is not the caller's doing.
+    Internal { message: String },
+}
+
+impl SlateError {
+    /// Wraps an I/O error on `path`, as in `io("Failed to read", path, e)`.
+    /// Missing files, permission problems and existing files get their own
+    /// variants; everything else becomes [`SlateError::Io`].
+    pub fn io(context: &str, path: &Path, e: io::Error) -> Self {
+        let message = format!("{} {:?}: {}", context, path, e);
+        let path = display(path);
+        match e.kind() {
+            io::ErrorKind::NotFound => SlateError::NotFound { path, message },
+            io::ErrorKind::PermissionDenied => SlateError::PermissionDenied { path, message },
+            io::ErrorKind::AlreadyExists => SlateError::AlreadyExists { path, message },
+            io_kind => SlateError::Io {
+                path: Some(path),
+                io_kind,
+                message,
+            },
+        }
+    }
+
+    /// An index of an open vault that has not been built yet, e.g. `"Link index"`.
+    pub fn not_ready(index: &str) -> Self {
+        SlateError::IndexNotReady {
+            message: format!("{} is not ready yet", index),
+        }
+    }
+
+    pub fn vault_not_open(root: &Path) -> Self {
+        SlateError::VaultNotOpen {
+            path: display(root),
+            message: format!("Vault is not open: {:?}", root),
+        }
+    }
+
+    pub fn not_found(path: &str, message: impl Into<String>) -> Self {
+        SlateError::NotFound {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_path(path: &str, message: impl Into<String>) -> Self {
+        SlateError::InvalidPath {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        SlateError::InvalidInput {
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        SlateError::Internal {
+            message: message.into(),
+        }
+    }
+
+    /// The stable code the error is tagged with on the frontend.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SlateError::VaultNotFound { .. } => "vaultNotFound",
+            SlateError::NotADirectory { .. } => "notADirectory",
+            SlateError::VaultNotOpen { .. } => "vaultNotOpen",
+            SlateError::IndexNotReady { .. } => "indexNotReady",
+            SlateError::NotFound { .. } => "notFound",
+            SlateError::AlreadyExists { .. } => "alreadyExists",
+            SlateError::PermissionDenied { .. } => "permissionDenied",
+            SlateError::InvalidPath { .. } => "invalidPath",
+            SlateError::InvalidEncoding { .. } => "invalidEncoding",
+            SlateError::InvalidInput { .. } => "invalidInput",
+            SlateError::Query { .. } => "query",
+            SlateError::Conflict { .. } => "conflict",
+            SlateError::Io { .. } => "io",
+            SlateError::Internal { .. } => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            SlateError::VaultNotFound { message, .. }
+            | SlateError::NotADirectory { message, .. }
+            | SlateError::VaultNotOpen { message, .. }
+            | SlateError::IndexNotReady { message }
+            | SlateError::NotFound { message, .. }
+            | SlateError::AlreadyExists { message, .. }
+            | SlateError::PermissionDenied { message, .. }
+            | SlateError::InvalidPath { message, .. }
+            | SlateError::InvalidEncoding { message, .. }
+            | SlateError::InvalidInput { message }
+            | SlateError::Query { message, .. }
+            | SlateError::Conflict { message, .. }
+            | SlateError::Io { message, .. }
+            | SlateError::Internal { message } => message,
+        }
+    }
+}
+
+impl fmt::Display for SlateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for SlateError {}
+
+impl From<QueryError> for SlateError {
+    fn from(e: QueryError) -> Self {
+        SlateError::Query {
+            message: e.message,
+            start: e.start,
+            end: e.end,
+        }
+    }
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+/// `io::ErrorKind` has no serde support; its variant name is stable enough.
+fn serialize_kind<S: Serializer>(kind: &io::ErrorKind, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(&format_args!("{:?}", kind))
+}
@@ -1,6 +1,25 @@
-mod vault;
+pub mod error;
+pub mod vault;
 
-use vault::scan_vault;
+use vault::backlinks::{get_backlinks, get_unlinked_mentions};
+use vault::conflicts::resolve_conflict;
+use vault::fuzzy::fuzzy_find;
+use vault::graph::{export_graph, get_graph};
+use vault::health::vault_health;
+use vault::links::{get_outgoing_links, resolve_link};
+use vault::merge::merge_note;
+use vault::note::{read_note, write_note};
+use vault::properties::{get_properties, set_property};
+use vault::query::run_query;
+use vault::registry::{
+    cancel_scan, close_vault, forget_vault, get_last_vault, list_vaults, open_vault,
+};
+use vault::rename::rename_note;
+use vault::scan::scan_vault_streaming;
+use vault::search::search_vault;
+use vault::tags::{list_tags, notes_with_tag, rename_tag};
+use vault::tasks::{query_tasks, toggle_task};
+use vault::{resolve_vault, scan_vault, VaultRegistry};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -16,7 +35,40 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![scan_vault])
+        .manage(VaultRegistry::load())
+        .invoke_handler(tauri::generate_handler![
+            scan_vault,
+            scan_vault_streaming,
+            cancel_scan,
+            resolve_vault,
+            read_note,
+            write_note,
+            merge_note,
+            resolve_conflict,
+            search_vault,
+            fuzzy_find,
+            resolve_link,
+            get_outgoing_links,
+            get_backlinks,
+            get_unlinked_mentions,
+            rename_note,
+            get_graph,
+            export_graph,
+            vault_health,
+            get_properties,
+            set_property,
+            list_tags,
+            notes_with_tag,
+            rename_tag,
+            query_tasks,
+            toggle_task,
+            run_query,
+            list_vaults,
+            open_vault,
+            close_vault,
+            forget_vault,
+            get_last_vault
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
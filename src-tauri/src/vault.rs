@@ -1,66 +1,92 @@
 //! Vault operations - file scanning, searching, indexing
 
-use serde::{Deserialize, Serialize};
+pub mod backlinks;
+pub mod conflicts;
+pub mod echo;
+pub mod fields;
+pub mod files;
+pub mod frontmatter;
+pub mod fs;
+pub mod fuzzy;
+pub mod graph;
+pub mod health;
+pub mod index;
+pub mod links;
+mod location;
+mod markdown;
+pub mod merge;
+pub mod note;
+pub mod properties;
+pub mod query;
+pub mod registry;
+pub mod rename;
+pub mod scan;
+pub mod search;
+pub mod tags;
+pub mod tasks;
+pub mod watcher;
+
+pub use conflicts::ConflictCopy;
+pub(crate) use files::{is_hidden_path, is_markdown_file, markdown_files};
+pub use files::{FileEntry, Vault};
+pub use index::{IndexDelta, VaultIndex};
+pub use location::{expand_path, VaultConfig, VaultHandle};
+pub use registry::VaultRegistry;
+
+use crate::error::SlateError;
+use serde::Serialize;
+use std::path::PathBuf;
 use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State};
 use tracing::{info, instrument, warn};
-use walkdir::WalkDir;
-
-/// A file entry in the vault.
-#[derive(Serialize, Deserialize)]
-pub struct FileEntry {
-    pub name: String,
-    pub path: String,
-    #[serde(rename = "relativePath")]
-    pub relative_path: String,
+
+/// Payload of the `vault://index-delta` event.
+#[derive(Clone, Serialize)]
+struct IndexDeltaEvent {
+    vault: VaultHandle,
+    #[serde(flatten)]
+    delta: IndexDelta,
 }
 
-/// Recursively scans a vault directory for markdown files.
-/// Skips hidden directories and returns sorted results.
+/// Lists the markdown files in a vault.
+///
+/// Answers straight from the persistent index when there is one, then
+/// reconciles it against the disk in the background and emits any changes as
+/// a `vault://index-delta` event. Without an index, the vault is walked once
+/// to build it. Results are sorted by relative path.
 #[tauri::command]
-#[instrument(skip_all, fields(vault = %vault_relative_path))]
-pub fn scan_vault(vault_relative_path: String) -> Result<Vec<FileEntry>, String> {
+#[instrument(skip_all, fields(vault = %vault.root().display()))]
+pub fn scan_vault(
+    app: AppHandle,
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+) -> Result<Vec<FileEntry>, SlateError> {
     let start = Instant::now();
     info!("Starting vault scan");
 
-    let home = dirs::home_dir().ok_or("Could not determine home directory")?;
-    let vault_root = home.join(&vault_relative_path);
+    let vault_root = vault.root();
 
-    if !vault_root.exists() {
-        warn!(path = ?vault_root, "Vault path does not exist");
-        return Err(format!("Vault path does not exist: {:?}", vault_root));
-    }
-
-    let mut results: Vec<FileEntry> = Vec::new();
-
-    for entry in WalkDir::new(&vault_root)
-        .into_iter()
-        .filter_entry(|e| !is_hidden(e))
-        .filter_map(Result::ok)
-        .filter(|e| is_markdown_file(e.path()))
-    {
-        let path = entry.path();
-        let name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("")
-            .to_string();
-
-        let full_path = path.to_string_lossy().to_string();
-
-        let relative_path = path
-            .strip_prefix(&vault_root)
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|_| name.clone());
-
-        results.push(FileEntry {
-            name,
-            path: full_path,
-            relative_path,
-        });
-    }
-
-    // Sort by relative path for consistent ordering
-    results.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    let results = match cached_index(&registry, &vault) {
+        Some(index) => {
+            let results = index.entries(vault_root);
+            spawn_reconcile(app, vault.clone(), index);
+            results
+        }
+        None => {
+            let mut index = VaultIndex::default();
+            index.reconcile(vault_root);
+            if let Err(e) = index.save(vault_root) {
+                warn!(error = %e, "Failed to persist vault index");
+            }
+            let results = index.entries(vault_root);
+            let deferred = store_index(&registry, &vault, index);
+            let vault = vault.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                replay_or_refresh(&app, &vault, &deferred)
+            });
+            results
+        }
+    };
 
     let elapsed = start.elapsed();
     info!(
@@ -72,16 +98,93 @@ pub fn scan_vault(vault_relative_path: String) -> Result<Vec<FileEntry>, String>
     Ok(results)
 }
 
-/// Returns true if the entry is a hidden file/directory (starts with '.')
-fn is_hidden(entry: &walkdir::DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| s.starts_with('.'))
-        .unwrap_or(false)
+/// The vault's index: the in-memory copy if the vault is open, otherwise
+/// the one on disk.
+pub(crate) fn cached_index(registry: &VaultRegistry, vault: &VaultHandle) -> Option<VaultIndex> {
+    registry
+        .with_open(vault, |open| open.index.clone())
+        .flatten()
+        .or_else(|| VaultIndex::load(vault.root()))
+}
+
+/// Reconciles `index` against the disk off the command thread.
+fn spawn_reconcile(app: AppHandle, vault: VaultHandle, index: VaultIndex) {
+    tauri::async_runtime::spawn_blocking(move || reconcile_index(&app, &vault, index, true));
+}
+
+/// Reconciles `index` against the disk, then commits it; see
+/// [`commit_index`].
+pub(crate) fn reconcile_index(
+    app: &AppHandle,
+    vault: &VaultHandle,
+    mut index: VaultIndex,
+    announce: bool,
+) {
+    let delta = index.reconcile(vault.root());
+    commit_index(app, vault, index, delta, announce);
 }
 
-/// Returns true if the path is a markdown file
-fn is_markdown_file(path: &std::path::Path) -> bool {
-    path.is_file() && path.extension().map(|e| e == "md").unwrap_or(false)
+/// Persists a just reconciled `index` if anything changed and stores it on
+/// the open vault, then brings the derived indexes up to date. With
+/// `announce`, changes are emitted as a `vault://index-delta` event.
+pub(crate) fn commit_index(
+    app: &AppHandle,
+    vault: &VaultHandle,
+    index: VaultIndex,
+    delta: IndexDelta,
+    announce: bool,
+) {
+    if !delta.is_empty() {
+        if let Err(e) = index.save(vault.root()) {
+            warn!(error = %e, "Failed to persist vault index");
+        }
+        if announce {
+            let event = IndexDeltaEvent {
+                vault: vault.clone(),
+                delta,
+            };
+            if let Err(e) = app.emit("vault://index-delta", event) {
+                warn!(error = %e, "Failed to emit index delta");
+            }
+        }
+    }
+    let deferred = store_index(&app.state::<VaultRegistry>(), vault, index);
+    replay_or_refresh(app, vault, &deferred);
+}
+
+/// Stores `index` on the open vault, returning the paths the watcher saw
+/// change while it had none.
+fn store_index(registry: &VaultRegistry, vault: &VaultHandle, index: VaultIndex) -> Vec<PathBuf> {
+    registry
+        .with_open(vault, |open| {
+            open.index = Some(index);
+            std::mem::take(&mut open.deferred)
+        })
+        .unwrap_or_default()
+}
+
+/// Replays deferred watcher changes, which also refreshes the derived
+/// indexes; with none, just refreshes them.
+fn replay_or_refresh(app: &AppHandle, vault: &VaultHandle, deferred: &[PathBuf]) {
+    if deferred.is_empty() {
+        refresh_derived(&app.state::<VaultRegistry>(), vault);
+    } else {
+        watcher::process_burst(app, vault, deferred);
+    }
+}
+
+/// Brings the indexes built from note contents (search, links) in line with
+/// an open vault's index.
+pub(crate) fn refresh_derived(registry: &VaultRegistry, vault: &VaultHandle) {
+    search::refresh(registry, vault);
+    links::refresh(registry, vault);
+    properties::refresh(registry, vault);
+    tasks::refresh(registry, vault);
+}
+
+/// Resolves a user-supplied vault path (absolute, `~`, `$VAR`, or
+/// home-relative) into a canonical vault handle.
+#[tauri::command]
+pub fn resolve_vault(path: String) -> Result<VaultHandle, SlateError> {
+    VaultHandle::resolve(&path, dirs::home_dir().as_deref())
 }
@@ -0,0 +1,323 @@
+//! Backlinks - the notes linking to a note, and the notes mentioning it
+//! without a link, each with the paragraph around the reference
+
+use super::links::{self, Link, LinkIndex};
+use super::markdown;
+use super::search::{self, tokenize, SearchIndex, Snippet, TextTerm};
+use super::{FileEntry, VaultHandle, VaultRegistry};
+use crate::error::SlateError;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use tauri::State;
+use tracing::{info, instrument};
+
+/// Paragraphs longer than this (in bytes) are cut down to the text around
+/// their first reference.
+const MAX_PARAGRAPH: usize = 400;
+
+/// A note referring to another, with the paragraphs it does so in.
+#[derive(Clone, Debug, Serialize)]
+pub struct NoteMentions {
+    pub file: FileEntry,
+    /// One per paragraph, in note order; `highlights` marks the references.
+    pub mentions: Vec<Snippet>,
+}
+
+/// Notes linking to the note at `path`, sorted by path.
+///
+/// The linking notes come straight from the link index; only they are read,
+/// to show the paragraph around each link.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn get_backlinks(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    path: String,
+) -> Result<Vec<NoteMentions>, SlateError> {
+    let start = Instant::now();
+    let sources = registry
+        .with_open(&vault, |open| {
+            open.links.as_ref().map(|index| linking(index, &path))
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Link index"))?;
+
+    // Notes are read outside the registry lock
+    let backlinks = mentions(&vault, sources);
+    info!(
+        notes = backlinks.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Found backlinks"
+    );
+    Ok(backlinks)
+}
+
+/// Notes linking to the note at `path` in `index`, sorted by path, for use
+/// without an open vault.
+pub fn backlinks(index: &LinkIndex, vault: &VaultHandle, path: &str) -> Vec<NoteMentions> {
+    mentions(vault, linking(index, path))
+}
+
+/// The notes linking to `path`, each with the links that do.
+fn linking(index: &LinkIndex, path: &str) -> Vec<(String, Vec<Link>)> {
+    index
+        .backlinks(path)
+        .map(|source| {
+            let links = index
+                .links(source)
+                .unwrap_or_default()
+                .iter()
+                .filter(|link| index.resolver().resolve_to_note(source, link) == Some(path))
+                .cloned()
+                .collect();
+            (source.clone(), links)
+        })
+        .collect()
+}
+
+/// Reads each linking note for the paragraphs around its links.
+fn mentions(vault: &VaultHandle, sources: Vec<(String, Vec<Link>)>) -> Vec<NoteMentions> {
+    sources
+        .into_iter()
+        .filter_map(|(source, links)| {
+            let text = read(vault, &source)?;
+            let ranges = links.iter().map(|link| (link.start, link.end)).collect();
+            note_mentions(vault, &source, &text, ranges)
+        })
+        .collect()
+}
+
+/// Notes mentioning the note at `path` by name in plain text, outside
+/// links and code, sorted by path.
+///
+/// Candidates come from the search index, so only notes containing the
+/// name's words are read.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn get_unlinked_mentions(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    path: String,
+) -> Result<Vec<NoteMentions>, SlateError> {
+    let start = Instant::now();
+    let (names, candidates) = registry
+        .with_open(&vault, |open| {
+            let search = open.search.as_ref()?;
+            let aliases = open
+                .properties
+                .as_ref()
+                .map_or(&[][..], |p| p.aliases(&path));
+            Some(mentioning(search, &path, aliases))
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Search index"))?;
+
+    // Notes are read outside the registry lock
+    let mentions = unlinked_in(&vault, &names, candidates);
+    info!(
+        notes = mentions.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Found unlinked mentions"
+    );
+    Ok(mentions)
+}
+
+/// Notes mentioning the note at `path`, by name or by one of `aliases`,
+/// outside links and code, sorted by path, for use without an open vault.
+pub fn unlinked_mentions(
+    search: &SearchIndex,
+    vault: &VaultHandle,
+    path: &str,
+    aliases: &[String],
+) -> Vec<NoteMentions> {
+    let (names, candidates) = mentioning(search, path, aliases);
+    unlinked_in(vault, &names, candidates)
+}
+
+/// The names of the note at `path` as lowercased words, and the other notes
+/// that hold all the words of one of them.
+fn mentioning(
+    search: &SearchIndex,
+    path: &str,
+    aliases: &[String],
+) -> (Vec<Vec<String>>, Vec<String>) {
+    let names = names(path, aliases);
+    let mut candidates: Vec<String> = names
+        .iter()
+        .filter_map(|name| TextTerm::from_text(name, false))
+        .flat_map(|term| search.matching(&term).cloned().collect::<Vec<_>>())
+        .filter(|candidate| candidate != path)
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    let names = names
+        .iter()
+        .map(|name| tokenize(name).into_iter().map(|token| token.word).collect())
+        .filter(|words: &Vec<String>| !words.is_empty())
+        .collect();
+    (names, candidates)
+}
+
+/// Reads each candidate for the paragraphs where one of `names` appears
+/// unlinked.
+fn unlinked_in(
+    vault: &VaultHandle,
+    names: &[Vec<String>],
+    candidates: Vec<String>,
+) -> Vec<NoteMentions> {
+    candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let text = read(vault, &candidate)?;
+            let ranges = unlinked(&text, names);
+            note_mentions(vault, &candidate, &text, ranges)
+        })
+        .collect()
+}
+
+/// What a note goes by in plain text: its file name and its aliases.
+fn names(path: &str, aliases: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = Path::new(path)
+        .file_stem()
+        .map(|stem| vec![stem.to_string_lossy().into_owned()])
+        .unwrap_or_default();
+    names.extend(aliases.iter().cloned());
+    names
+}
+
+/// Byte ranges where any of `names` (as lowercased words) appears in
+/// `text`, leaving out links and code.
+fn unlinked(text: &str, names: &[Vec<String>]) -> Vec<(usize, usize)> {
+    let mut excluded = links::code_ranges(text);
+    excluded.extend(links::parse(text).iter().map(|link| (link.start, link.end)));
+    let overlaps = |start: usize, end: usize| excluded.iter().any(|&(s, e)| start < e && s < end);
+
+    let tokens = tokenize(text);
+    let mut ranges = Vec::new();
+    for i in 0..tokens.len() {
+        for words in names {
+            let Some(run) = tokens.get(i..i + words.len()) else {
+                continue;
+            };
+            if run
+                .iter()
+                .zip(words)
+                .all(|(token, word)| token.word == *word)
+            {
+                let (start, end) = (run[0].start, run[run.len() - 1].end);
+                if !overlaps(start, end) {
+                    ranges.push((start, end));
+                }
+            }
+        }
+    }
+    search::merge_ranges(ranges)
+}
+
+/// Reads a note as the link index does, so link offsets line up.
+fn read(vault: &VaultHandle, path: &str) -> Option<String> {
+    let bytes = fs::read(vault.join(path).ok()?).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn note_mentions(
+    vault: &VaultHandle,
+    path: &str,
+    text: &str,
+    ranges: Vec<(usize, usize)>,
+) -> Option<NoteMentions> {
+    let mentions = paragraphs(text, &search::merge_ranges(ranges));
+    if mentions.is_empty() {
+        return None;
+    }
+    Some(NoteMentions {
+        file: FileEntry::new(vault.root(), &vault.join(path).ok()?),
+        mentions,
+    })
+}
+
+/// A snippet for each paragraph of `text` holding any of `ranges` (sorted
+/// byte ranges). Ranges that no longer fit the text are skipped.
+fn paragraphs(text: &str, ranges: &[(usize, usize)]) -> Vec<Snippet> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push((offset, line.trim_end_matches(['\n', '\r'])));
+        offset += line.len();
+    }
+
+    let mut snippets: Vec<Snippet> = Vec::new();
+    let mut last: Option<(usize, usize)> = None;
+    for &(start, end) in ranges {
+        if text.get(start..end).is_none() {
+            continue;
+        }
+        let i = lines.partition_point(|&(line_start, _)| line_start <= start) - 1;
+        let (first, end_line) = paragraph(&lines, i);
+        let from = lines[first].0;
+        let to = lines[end_line].0 + lines[end_line].1.len();
+        let range = (start - from, end.min(to) - from);
+
+        match (last, snippets.last_mut()) {
+            (Some(bounds), Some(snippet)) if bounds == (first, end_line) => {
+                // Converted to UTF-16 below, once the paragraph is complete
+                snippet.highlights.push(range);
+            }
+            _ => {
+                snippets.push(Snippet {
+                    line: i + 1,
+                    text: text[from..to].to_string(),
+                    highlights: vec![range],
+                });
+                last = Some((first, end_line));
+            }
+        }
+    }
+
+    snippets
+        .into_iter()
+        .map(|snippet| {
+            if snippet.text.len() > MAX_PARAGRAPH {
+                return search::snippet(&snippet.text, snippet.line, &snippet.highlights);
+            }
+            let utf16 = |byte: usize| snippet.text[..byte].encode_utf16().count();
+            let highlights = snippet
+                .highlights
+                .iter()
+                .map(|&(start, end)| (utf16(start), utf16(end)))
+                .collect();
+            Snippet {
+                highlights,
+                ..snippet
+            }
+        })
+        .collect()
+}
+
+/// First and last line of the paragraph around line `i`: the block of
+/// lines up to a blank line, heading or code fence. Headings and list items
+/// stand on their own.
+fn paragraph(lines: &[(usize, &str)], i: usize) -> (usize, usize) {
+    let is_heading = |line: &str| markdown::heading(line).is_some();
+    let is_list_item = |line: &str| markdown::list_item(line).is_some();
+    let is_break = |line: &str| {
+        line.trim().is_empty() || is_heading(line) || links::fence_marker(line).is_some()
+    };
+    if is_heading(lines[i].1) {
+        return (i, i);
+    }
+
+    let mut first = i;
+    while first > 0 && !is_list_item(lines[first].1) && !is_break(lines[first - 1].1) {
+        first -= 1;
+    }
+    let mut last = i;
+    while last + 1 < lines.len() && !is_break(lines[last + 1].1) && !is_list_item(lines[last + 1].1)
+    {
+        last += 1;
+    }
+    (first, last)
+}
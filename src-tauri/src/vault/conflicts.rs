@@ -0,0 +1,190 @@
+//! Sync conflicts - recognises the "conflicted copy" files that Dropbox,
+//! Syncthing and iCloud leave next to a note, and folds them back in
+
+use super::merge;
+use super::note::{self, write_atomic, NoteVersion};
+use super::{FileEntry, VaultHandle, VaultRegistry};
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tauri::State;
+use tracing::{info, instrument};
+
+/// The sync client that created a conflicted copy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncService {
+    /// `Note (Alice's conflicted copy 2026-10-01).md`
+    Dropbox,
+    /// `Note.sync-conflict-20261001-120000-ABCDEFG.md`
+    Syncthing,
+    /// `Note 2.md`, only when `Note.md` exists alongside it. That is also an
+    /// ordinary way to name notes, so these are a guess and are only
+    /// resolved once the user confirms.
+    Icloud,
+}
+
+/// Marks a file as a conflicted copy of another note.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConflictCopy {
+    pub service: SyncService,
+    /// The note this is a copy of. It may no longer exist.
+    pub original: Box<FileEntry>,
+}
+
+/// Recognises a conflicted copy by its name, given its path relative to the
+/// vault root. `is_note` tells whether another relative path is a note in
+/// the vault; it is only asked about the originals of iCloud-style names.
+pub fn detect(
+    vault_root: &Path,
+    relative_path: &str,
+    is_note: &dyn Fn(&str) -> bool,
+) -> Option<ConflictCopy> {
+    let path = Path::new(relative_path);
+    let (service, original_name) = original_name(path.file_name()?.to_str()?)?;
+    let original = path.with_file_name(original_name);
+    let original = original.to_string_lossy();
+
+    // "Name 2" is also an ordinary way to name a note; only suspect it when
+    // there is a "Name" for it to be a copy of
+    if service == SyncService::Icloud && !is_note(&original) {
+        return None;
+    }
+
+    Some(ConflictCopy {
+        service,
+        original: Box::new(FileEntry::with_lookup(
+            vault_root,
+            &vault_root.join(&*original),
+            is_note,
+        )),
+    })
+}
+
+/// The file name a conflicted copy was made from.
+fn original_name(file_name: &str) -> Option<(SyncService, String)> {
+    let (stem, ext) = match file_name.rfind('.') {
+        Some(i) if i > 0 => file_name.split_at(i),
+        _ => (file_name, ""),
+    };
+    let original = |stem: &str| format!("{}{}", stem, ext);
+
+    if let Some(i) = stem.find(".sync-conflict-") {
+        return (i > 0).then(|| (SyncService::Syncthing, original(&stem[..i])));
+    }
+
+    // The parenthetical may itself end in a counter: "(... conflicted copy 2026-10-01 (1))"
+    if stem.ends_with(')') {
+        if let Some((i, _)) = stem
+            .rmatch_indices(" (")
+            .find(|(i, _)| stem[*i..].contains("conflicted copy"))
+        {
+            return Some((SyncService::Dropbox, original(&stem[..i])));
+        }
+    }
+
+    let (name, counter) = stem.rsplit_once(' ')?;
+    let is_counter = !counter.starts_with('0') && counter.parse::<u32>().is_ok_and(|n| n >= 2);
+    (!name.is_empty() && is_counter).then(|| (SyncService::Icloud, original(name)))
+}
+
+/// How to settle a conflicted copy.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Resolution {
+    /// Merge the copy into the original. Changes that overlap are kept with
+    /// conflict markers.
+    Merge,
+    /// Discard the copy.
+    KeepOriginal,
+    /// Replace the original with the copy.
+    KeepCopy,
+}
+
+/// The original note after a conflict was resolved.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictResolved {
+    pub original: FileEntry,
+    pub version: NoteVersion,
+    /// False if the merge left conflict markers in the original.
+    pub clean: bool,
+}
+
+/// Settles a conflicted copy, given its path relative to the vault root, and
+/// deletes it. iCloud-style copies are refused unless `confirmed`, since
+/// "Chapter 2" next to "Chapter" may be an ordinary note.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn resolve_conflict(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    path: String,
+    resolution: Resolution,
+    confirmed: Option<bool>,
+) -> Result<ConflictResolved, SlateError> {
+    let copy_path = vault.join(&path)?;
+    let is_note = |rel: &str| vault.root().join(rel).is_file();
+    let copy = detect(vault.root(), &path, &is_note).ok_or_else(|| {
+        SlateError::invalid_path(&path, format!("Not a conflicted copy: {}", path))
+    })?;
+    if copy.service == SyncService::Icloud && confirmed != Some(true) {
+        return Err(SlateError::invalid_input(format!(
+            "{} may be an ordinary note; confirm it is an iCloud conflicted copy first",
+            path
+        )));
+    }
+    let original_rel = copy.original.relative_path.clone();
+    let original_path = vault.join(&original_rel)?;
+
+    let theirs = note::read(&copy_path)?;
+    let ours = if original_path.exists() {
+        Some(note::read(&original_path)?)
+    } else {
+        None
+    };
+
+    let save = |content: &str| -> Result<NoteVersion, SlateError> {
+        let entry = write_atomic(&original_path, content, |entry| {
+            registry.record_write(&vault, &original_rel, entry)
+        })?;
+        Ok(NoteVersion {
+            mtime: entry.mtime,
+            hash: entry.hash,
+        })
+    };
+
+    let (version, clean) = match (resolution, ours) {
+        (Resolution::KeepOriginal, None) => {
+            return Err(SlateError::not_found(
+                &original_rel,
+                format!(
+                    "Original of {} no longer exists; keep the copy instead",
+                    path
+                ),
+            ))
+        }
+        (Resolution::KeepOriginal, Some(ours)) => (
+            NoteVersion {
+                mtime: ours.mtime,
+                hash: ours.hash,
+            },
+            true,
+        ),
+        (Resolution::KeepCopy, _) | (Resolution::Merge, None) => (save(&theirs.content)?, true),
+        (Resolution::Merge, Some(ours)) => {
+            let outcome = merge::merge_unrelated(&ours.content, &theirs.content);
+            (save(outcome.content())?, outcome.is_clean())
+        }
+    };
+
+    fs::remove_file(&copy_path).map_err(|e| SlateError::io("Failed to delete", &copy_path, e))?;
+    info!(original = %original_rel, clean, "Resolved conflicted copy");
+
+    Ok(ConflictResolved {
+        original: FileEntry::new(vault.root(), &original_path),
+        version,
+        clean,
+    })
+}
@@ -0,0 +1,70 @@
+//! Write log - remembers Slate's own recent writes so the watcher can drop
+//! the change events they cause
+
+use super::index::IndexEntry;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long a write is remembered. Watcher bursts are processed well within
+/// this; anything older is assumed to have been seen already.
+const ECHO_TTL: Duration = Duration::from_secs(30);
+
+struct RecordedWrite {
+    hash: String,
+    mtime: u64,
+    at: Instant,
+}
+
+/// Recent writes by Slate, per relative path, oldest first.
+#[derive(Default)]
+pub struct WriteLog {
+    writes: HashMap<String, Vec<RecordedWrite>>,
+}
+
+impl WriteLog {
+    /// Records that Slate just wrote `entry` (the file's state right after
+    /// the write) to `relative_path`.
+    pub fn record(&mut self, relative_path: &str, entry: &IndexEntry) {
+        self.expire();
+        self.writes
+            .entry(relative_path.to_string())
+            .or_default()
+            .push(RecordedWrite {
+                hash: entry.hash.clone(),
+                mtime: entry.mtime,
+                at: Instant::now(),
+            });
+    }
+
+    /// Returns true if the file's current state is one Slate wrote itself.
+    ///
+    /// A match consumes that write and every earlier one for the path: once
+    /// the disk shows a later save, earlier saves can no longer be observed.
+    /// Later writes stay recorded, so a burst of rapid saves is suppressed
+    /// no matter which intermediate states the watcher happens to see.
+    pub fn take_echo(&mut self, relative_path: &str, current: &IndexEntry) -> bool {
+        self.expire();
+        let Some(writes) = self.writes.get_mut(relative_path) else {
+            return false;
+        };
+        let Some(i) = writes
+            .iter()
+            .rposition(|w| w.hash == current.hash && w.mtime == current.mtime)
+        else {
+            return false;
+        };
+
+        writes.drain(..=i);
+        if writes.is_empty() {
+            self.writes.remove(relative_path);
+        }
+        true
+    }
+
+    fn expire(&mut self) {
+        self.writes.retain(|_, writes| {
+            writes.retain(|w| w.at.elapsed() < ECHO_TTL);
+            !writes.is_empty()
+        });
+    }
+}
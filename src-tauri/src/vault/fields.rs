@@ -0,0 +1,117 @@
+//! Inline fields - finds `key:: value` fields in note text, written on a
+//! line of their own or inside `[key:: value]` or `(key:: value)`
+
+use super::frontmatter::{self, Property, PropertyValue};
+use super::links;
+
+/// Finds every inline field in a note, outside code and frontmatter, in the
+/// order they are written.
+pub fn parse(text: &str) -> Vec<Property> {
+    let code = links::code_ranges(text);
+    let skip_to = frontmatter::range(text).map_or(0, |range| range.end);
+
+    let mut fields = Vec::new();
+    let mut offset = 0;
+    for raw in text.split_inclusive('\n') {
+        let start = offset;
+        offset += raw.len();
+        if start < skip_to || code.iter().any(|&(s, e)| s <= start && start < e) {
+            continue;
+        }
+        let line = raw.trim_end_matches(['\n', '\r']);
+
+        let content = line_content(line);
+        if let Some((key, value)) = split_field(content) {
+            fields.push(field(key, value));
+            continue;
+        }
+        fields.extend(bracketed(line).map(|(key, value)| field(key, value)));
+    }
+    fields
+}
+
+/// A line without its list marker, checkbox or quote marker.
+fn line_content(line: &str) -> &str {
+    let mut rest = line.trim_start();
+    while let Some(after) = rest.strip_prefix('>') {
+        rest = after.trim_start();
+    }
+    if let Some(after) = rest.strip_prefix(['-', '*', '+']) {
+        if after.starts_with(' ') {
+            rest = after.trim_start();
+            if let Some(after) = rest
+                .strip_prefix('[')
+                .and_then(|r| r.get(r.chars().next()?.len_utf8()..))
+                .and_then(|r| r.strip_prefix(']'))
+            {
+                rest = after.trim_start();
+            }
+        }
+    }
+    rest
+}
+
+/// `key:: value`, where the key is made of letters, digits, spaces, `_`
+/// and `-`.
+fn split_field(s: &str) -> Option<(&str, &str)> {
+    let (key, value) = s.split_once("::")?;
+    let key = key.trim();
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '_' | '-'));
+    valid.then_some((key, value.trim()))
+}
+
+/// `[key:: value]` and `(key:: value)` fields anywhere in a line.
+fn bracketed(line: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut fields = Vec::new();
+    let mut rest = line;
+    while let Some(open) = rest.find(['[', '(']) {
+        let close = if rest[open..].starts_with('[') {
+            ']'
+        } else {
+            ')'
+        };
+        let inner = &rest[open + 1..];
+        let Some(end) = inner.find(close) else {
+            break;
+        };
+        match split_field(&inner[..end]) {
+            Some(field) => {
+                fields.push(field);
+                rest = &inner[end + 1..];
+            }
+            None => rest = inner,
+        }
+    }
+    fields.into_iter()
+}
+
+fn field(key: &str, value: &str) -> Property {
+    Property {
+        key: key.to_string(),
+        value: frontmatter::scalar(value),
+    }
+}
+
+/// True if a property key and a key written in a query are the same field:
+/// case and the difference between spaces and `-` are ignored.
+pub fn same_key(a: &str, b: &str) -> bool {
+    let normalize = |key: &str| key.trim().to_lowercase().replace(' ', "-");
+    normalize(a) == normalize(b)
+}
+
+/// The value of the first field or frontmatter property named `key`,
+/// frontmatter first.
+pub fn lookup<'a>(
+    frontmatter: &'a [Property],
+    fields: &'a [Property],
+    key: &str,
+) -> Option<&'a PropertyValue> {
+    frontmatter
+        .iter()
+        .chain(fields)
+        .find(|property| same_key(&property.key, key))
+        .map(|property| &property.value)
+}
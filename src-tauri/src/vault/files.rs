@@ -0,0 +1,207 @@
+//! Vault files - walking a vault root for its notes, over any [`VaultFs`]
+
+use super::conflicts::{self, ConflictCopy};
+use super::fs::{DiskFs, VaultFs};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// A file entry in the vault.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub name: String,
+    pub path: String,
+    #[serde(rename = "relativePath")]
+    pub relative_path: String,
+    /// Set if this file is a sync client's conflicted copy of another note.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<ConflictCopy>,
+}
+
+impl FileEntry {
+    /// Builds an entry for `path`, a file somewhere under `vault_root` on
+    /// disk.
+    pub fn new(vault_root: &Path, path: &Path) -> Self {
+        Self::with_lookup(vault_root, path, &|rel| vault_root.join(rel).is_file())
+    }
+
+    /// Like [`new`](Self::new), but asks `is_note` whether another relative
+    /// path is a note instead of checking the disk, so a scan can answer
+    /// from the listing it already has.
+    pub fn with_lookup(vault_root: &Path, path: &Path, is_note: &dyn Fn(&str) -> bool) -> Self {
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("")
+            .to_string();
+
+        let full_path = path.to_string_lossy().to_string();
+
+        let relative_path = path
+            .strip_prefix(vault_root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| name.clone());
+
+        let conflict = conflicts::detect(vault_root, &relative_path, is_note);
+
+        FileEntry {
+            name,
+            path: full_path,
+            relative_path,
+            conflict,
+        }
+    }
+}
+
+/// A vault root and the filesystem it lives on.
+///
+/// Knows nothing of the app: the root is taken as given, so it can point at
+/// a temporary directory, or at a [`MemoryFs`](super::fs::MemoryFs) tree.
+#[derive(Debug)]
+pub struct Vault<F = DiskFs> {
+    root: PathBuf,
+    fs: F,
+}
+
+impl Vault<DiskFs> {
+    pub fn on_disk(root: impl Into<PathBuf>) -> Self {
+        Vault::new(root, DiskFs)
+    }
+}
+
+impl<F: VaultFs> Vault<F> {
+    pub fn new(root: impl Into<PathBuf>, fs: F) -> Self {
+        Vault {
+            root: root.into(),
+            fs,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn fs(&self) -> &F {
+        &self.fs
+    }
+
+    /// Walks the vault for markdown files, skipping hidden files and
+    /// directories.
+    pub fn markdown_files(&self) -> MarkdownFiles<'_, F> {
+        MarkdownFiles::new(&self.fs, &self.root)
+    }
+
+    /// Every note in the vault, sorted by relative path.
+    pub fn scan(&self) -> Vec<FileEntry> {
+        let paths: Vec<PathBuf> = self.markdown_files().collect();
+        let notes: HashSet<&Path> = paths
+            .iter()
+            .filter_map(|path| path.strip_prefix(&self.root).ok())
+            .collect();
+        let is_note = |rel: &str| notes.contains(Path::new(rel));
+        let mut entries: Vec<FileEntry> = paths
+            .iter()
+            .map(|path| FileEntry::with_lookup(&self.root, path, &is_note))
+            .collect();
+        entries.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        entries
+    }
+
+    /// Builds an entry for `path`, a file in the vault, looking up the
+    /// original of a conflicted copy on this vault's filesystem.
+    pub fn entry(&self, path: &Path) -> FileEntry {
+        FileEntry::with_lookup(&self.root, path, &|rel| {
+            self.is_markdown_file(&self.root.join(rel))
+        })
+    }
+
+    /// Returns true if any component of `path` below the root is hidden.
+    /// Paths outside the vault count as hidden.
+    pub fn is_hidden(&self, path: &Path) -> bool {
+        path.strip_prefix(&self.root)
+            .map(|rel| rel.components().any(|c| is_hidden_name(c.as_os_str())))
+            .unwrap_or(true)
+    }
+
+    /// Returns true if `path` is a markdown file.
+    pub fn is_markdown_file(&self, path: &Path) -> bool {
+        is_markdown(&self.fs, path)
+    }
+}
+
+/// Iterator over the markdown files below a directory; see
+/// [`Vault::markdown_files`]. Directories are read lazily, so a scan can
+/// stop part way.
+pub struct MarkdownFiles<'a, F> {
+    fs: &'a F,
+    /// Directories still to read, the next one last.
+    dirs: Vec<PathBuf>,
+    /// Markdown files found in the last directory read, the next one last.
+    files: Vec<PathBuf>,
+}
+
+impl<'a, F: VaultFs> MarkdownFiles<'a, F> {
+    /// Walks `dir`. It is never treated as hidden itself, so vaults like
+    /// `~/.notes` work.
+    pub fn new(fs: &'a F, dir: &Path) -> Self {
+        MarkdownFiles {
+            fs,
+            dirs: vec![dir.to_path_buf()],
+            files: Vec::new(),
+        }
+    }
+}
+
+impl<F: VaultFs> Iterator for MarkdownFiles<'_, F> {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        loop {
+            if let Some(file) = self.files.pop() {
+                return Some(file);
+            }
+            let dir = self.dirs.pop()?;
+            // Unreadable directories are skipped, like unreadable files
+            let Ok(mut entries) = self.fs.read_dir(&dir) else {
+                continue;
+            };
+            entries.sort_by(|a, b| b.path.cmp(&a.path));
+            for entry in entries {
+                let hidden = entry.path.file_name().is_none_or(is_hidden_name);
+                if hidden {
+                    continue;
+                }
+                if entry.is_dir {
+                    self.dirs.push(entry.path);
+                } else if is_markdown(self.fs, &entry.path) {
+                    self.files.push(entry.path);
+                }
+            }
+        }
+    }
+}
+
+/// Walks a vault on disk for markdown files.
+pub(crate) fn markdown_files(root: &Path) -> MarkdownFiles<'static, DiskFs> {
+    MarkdownFiles::new(&DiskFs, root)
+}
+
+/// Returns true if any component of `path` below `root` is hidden
+pub(crate) fn is_hidden_path(root: &Path, path: &Path) -> bool {
+    Vault::on_disk(root).is_hidden(path)
+}
+
+/// Returns true if the path is a markdown file on disk
+pub(crate) fn is_markdown_file(path: &Path) -> bool {
+    is_markdown(&DiskFs, path)
+}
+
+/// Hidden files and directories start with '.'
+fn is_hidden_name(name: &std::ffi::OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+fn is_markdown(fs: &impl VaultFs, path: &Path) -> bool {
+    path.extension().map(|e| e == "md").unwrap_or(false)
+        && fs.metadata(path).is_ok_and(|meta| meta.is_file)
+}
@@ -0,0 +1,635 @@
+//! Frontmatter - YAML (`---`) and TOML (`+++`) blocks at the top of a note,
+//! read as typed properties and edited in place
+//!
+//! YAML is read line by line rather than through a full YAML parser: notes
+//! use a small part of the language (scalars and lists of scalars), and
+//! editing by line is what keeps comments and the formatting of other keys
+//! untouched. Anything richer, like a nested map, is kept as text.
+
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use toml_edit::{Array, DocumentMut, Item, Value};
+
+/// The value of a property.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PropertyValue {
+    Text(String),
+    Number(f64),
+    Boolean(bool),
+    /// `YYYY-MM-DD`, optionally followed by a time, as written.
+    Date(String),
+    List(Vec<PropertyValue>),
+    /// A key with no value.
+    Null,
+}
+
+/// A frontmatter key and its value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Property {
+    pub key: String,
+    pub value: PropertyValue,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Yaml,
+    Toml,
+}
+
+/// Where a note's frontmatter is.
+struct Block {
+    format: Format,
+    /// Byte range of the text between the fences.
+    body: Range<usize>,
+    /// Where the note's body starts, after the closing fence.
+    end: usize,
+}
+
+/// Finds the frontmatter block at the very start of `text`, if it has one
+/// that is closed.
+fn find(text: &str) -> Option<Block> {
+    let (format, fence) = if text.starts_with("---") {
+        (Format::Yaml, "---")
+    } else if text.starts_with("+++") {
+        (Format::Toml, "+++")
+    } else {
+        return None;
+    };
+    let first = text.find('\n')?;
+    if text[fence.len()..first].trim() != "" {
+        return None;
+    }
+
+    let body_start = first + 1;
+    let mut offset = body_start;
+    for line in text[body_start..].split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == fence || (format == Format::Yaml && trimmed == "...") {
+            return Some(Block {
+                format,
+                body: body_start..offset,
+                end: offset + line.len(),
+            });
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Byte range of a note's frontmatter, fences included, if it has any.
+pub fn range(text: &str) -> Option<Range<usize>> {
+    find(text).map(|block| 0..block.end)
+}
+
+/// Reads the properties in a note's frontmatter, in the order they are
+/// written. A note without frontmatter, or with frontmatter that cannot be
+/// read, has none.
+pub fn parse(text: &str) -> Vec<Property> {
+    let Some(block) = find(text) else {
+        return Vec::new();
+    };
+    let body = &text[block.body];
+    match block.format {
+        Format::Yaml => yaml_entries(body)
+            .into_iter()
+            .map(|entry| Property {
+                key: entry.key,
+                value: entry.value,
+            })
+            .collect(),
+        Format::Toml => match body.parse::<DocumentMut>() {
+            Ok(doc) => doc
+                .iter()
+                .map(|(key, item)| Property {
+                    key: key.to_string(),
+                    value: from_toml_item(item),
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+    }
+}
+
+/// `text` with `key` set to `value`, or removed if `value` is None. Only the
+/// lines of that key change; other keys, comments and blank lines are left
+/// as they are. A new key goes after the others, and a note without
+/// frontmatter gets a YAML block.
+pub fn set(text: &str, key: &str, value: Option<&PropertyValue>) -> Result<String, SlateError> {
+    if key.trim().is_empty() || key.contains('\n') {
+        return Err(SlateError::invalid_input(format!(
+            "Invalid property name: {:?}",
+            key
+        )));
+    }
+    let Some(block) = find(text) else {
+        return Ok(match value {
+            Some(value) => format!("---\n{}---\n{}", yaml_entry(key, value, "  "), text),
+            None => text.to_string(),
+        });
+    };
+
+    let body = &text[block.body.clone()];
+    let edited = match block.format {
+        Format::Yaml => set_yaml(body, key, value),
+        Format::Toml => set_toml(body, key, value)?,
+    };
+    Ok(format!(
+        "{}{}{}",
+        &text[..block.body.start],
+        edited,
+        &text[block.body.end..]
+    ))
+}
+
+/// A top-level YAML key and the lines it spans.
+struct Entry {
+    key: String,
+    lines: Range<usize>,
+    value: PropertyValue,
+    /// True for a list written inline, as `[a, b]`.
+    flow: bool,
+}
+
+fn yaml_entries(body: &str) -> Vec<Entry> {
+    let lines: Vec<&str> = body.lines().collect();
+    let mut entries = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let Some((key, rest)) = yaml_key(line) else {
+            i += 1;
+            continue;
+        };
+
+        // Indented lines and block list items belong to the key; blank
+        // lines and comments only if more of it follows
+        let mut end = i + 1;
+        for (j, next) in lines.iter().enumerate().skip(i + 1) {
+            if next.trim().is_empty() || next.starts_with('#') {
+                continue;
+            }
+            if next.starts_with([' ', '\t']) || next.starts_with("- ") || next.trim_end() == "-" {
+                end = j + 1;
+            } else {
+                break;
+            }
+        }
+
+        entries.push(Entry {
+            key,
+            lines: i..end,
+            value: yaml_value(rest, &lines[i + 1..end]),
+            flow: strip_comment(rest).trim().starts_with('['),
+        });
+        i = end;
+    }
+    entries
+}
+
+/// Splits a top-level `key: value` line.
+fn yaml_key(line: &str) -> Option<(String, &str)> {
+    if line.starts_with([' ', '\t', '#', '-']) || line.trim().is_empty() {
+        return None;
+    }
+    if let Some(quote @ ('"' | '\'')) = line.chars().next() {
+        let close = line[1..].find(quote)? + 1;
+        let rest = line[close + 1..].trim_start().strip_prefix(':')?;
+        return Some((line[1..close].to_string(), rest));
+    }
+    let colon = line
+        .match_indices(':')
+        .map(|(i, _)| i)
+        .find(|&i| line[i + 1..].is_empty() || line[i + 1..].starts_with([' ', '\t']))?;
+    Some((line[..colon].trim_end().to_string(), &line[colon + 1..]))
+}
+
+/// The value written after a key's colon, and the lines under it.
+fn yaml_value(rest: &str, block: &[&str]) -> PropertyValue {
+    let rest = strip_comment(rest).trim();
+
+    if rest.starts_with(['|', '>']) {
+        let folded = rest.starts_with('>');
+        let text = dedent(block);
+        return PropertyValue::Text(if folded {
+            text.split('\n')
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join(" ")
+                .trim()
+                .to_string()
+        } else {
+            text
+        });
+    }
+    if rest.starts_with('[') {
+        let mut flow = rest.to_string();
+        for line in block {
+            flow.push(' ');
+            flow.push_str(strip_comment(line).trim());
+        }
+        if let Some(inner) = flow
+            .strip_prefix('[')
+            .and_then(|flow| flow.trim_end().strip_suffix(']'))
+        {
+            return PropertyValue::List(
+                split_flow(inner)
+                    .into_iter()
+                    .map(|item| yaml_scalar(item.trim()))
+                    .collect(),
+            );
+        }
+        return PropertyValue::Text(flow);
+    }
+    if !rest.is_empty() {
+        if block.is_empty() {
+            return yaml_scalar(rest);
+        }
+        // A plain scalar carried over several lines
+        let mut text = rest.to_string();
+        for line in block {
+            text.push(' ');
+            text.push_str(line.trim());
+        }
+        return PropertyValue::Text(text);
+    }
+
+    let items: Vec<&str> = block
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    if items.is_empty() {
+        return PropertyValue::Null;
+    }
+    if items
+        .iter()
+        .all(|item| *item == "-" || item.starts_with("- "))
+    {
+        return PropertyValue::List(
+            items
+                .iter()
+                .map(|item| yaml_scalar(item[1..].trim()))
+                .collect(),
+        );
+    }
+    PropertyValue::Text(dedent(block))
+}
+
+/// Reads a value written on its own, outside frontmatter, the way a YAML
+/// scalar would be.
+pub fn scalar(s: &str) -> PropertyValue {
+    yaml_scalar(s.trim())
+}
+
+/// Reads a single YAML scalar: quoted text, a boolean, a number, a date,
+/// nothing, or plain text.
+fn yaml_scalar(s: &str) -> PropertyValue {
+    if let Some(inner) = s.strip_prefix('"') {
+        let end = closing_quote(inner, '"').unwrap_or(inner.len());
+        return PropertyValue::Text(unescape(&inner[..end]));
+    }
+    if let Some(inner) = s.strip_prefix('\'') {
+        let end = closing_quote(inner, '\'').unwrap_or(inner.len());
+        return PropertyValue::Text(inner[..end].replace("''", "'"));
+    }
+
+    let s = strip_comment(s).trim();
+    match s {
+        "" | "~" | "null" | "Null" | "NULL" => PropertyValue::Null,
+        "true" | "True" | "TRUE" => PropertyValue::Boolean(true),
+        "false" | "False" | "FALSE" => PropertyValue::Boolean(false),
+        _ if is_number(s) => PropertyValue::Number(s.parse().unwrap_or_default()),
+        _ if is_date(s) => PropertyValue::Date(s.to_string()),
+        _ => PropertyValue::Text(s.to_string()),
+    }
+}
+
+/// Index of the quote closing a quoted scalar whose opening quote has been
+/// stripped. In single quotes, `''` is an escaped quote.
+fn closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            c if c == quote => {
+                if quote == '\'' && chars.peek().is_some_and(|&(_, next)| next == '\'') {
+                    chars.next();
+                } else {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Cuts a trailing ` # comment` off a line, outside quotes.
+fn strip_comment(s: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if prev.is_whitespace() || matches!(prev, '[' | ',') => {
+                quote = Some(c)
+            }
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if prev.is_whitespace() => return &s[..i],
+            _ => {}
+        }
+        prev = c;
+    }
+    s
+}
+
+/// Splits the inside of a flow list on commas outside quotes.
+fn split_flow(s: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ',') => {
+                items.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&s[start..]);
+    items.retain(|item| !item.trim().is_empty());
+    items
+}
+
+/// Joins lines with their common indent taken off.
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let text: Vec<&str> = lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect();
+    text.join("\n").trim_end().to_string()
+}
+
+fn is_number(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '-' | '+'))
+        && s.parse::<f64>().is_ok()
+}
+
+/// `YYYY-MM-DD`, then optionally a time after `T` or a space.
+fn is_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    let date = b.len() >= 10
+        && b[..4].iter().all(u8::is_ascii_digit)
+        && b[4] == b'-'
+        && b[5..7].iter().all(u8::is_ascii_digit)
+        && b[7] == b'-'
+        && b[8..10].iter().all(u8::is_ascii_digit);
+    date && (b.len() == 10
+        || (matches!(b[10], b'T' | b' ')
+            && b.len() >= 16
+            && b[11..13].iter().all(u8::is_ascii_digit)
+            && b[13] == b':'))
+}
+
+fn set_yaml(body: &str, key: &str, value: Option<&PropertyValue>) -> String {
+    let lines: Vec<&str> = body.split_inclusive('\n').collect();
+    let entries = yaml_entries(body);
+    let existing = entries.iter().find(|entry| entry.key == key);
+
+    // New list items are indented like the list being replaced, or the
+    // first block list in the frontmatter
+    let indent = existing
+        .into_iter()
+        .chain(&entries)
+        .filter_map(|entry| {
+            let item = lines.get(entry.lines.start + 1)?;
+            let trimmed = item.trim_start();
+            trimmed
+                .starts_with('-')
+                .then(|| &item[..item.len() - trimmed.len()])
+        })
+        .next()
+        .unwrap_or("  ");
+    let written = value.map(|value| match value {
+        // A list written inline stays inline
+        PropertyValue::List(_) if existing.is_some_and(|entry| entry.flow) => {
+            format!("{}: {}\n", yaml_key_text(key), yaml_inline(value))
+        }
+        value => yaml_entry(key, value, indent),
+    });
+
+    let mut out = String::with_capacity(body.len());
+    match existing {
+        Some(entry) => {
+            lines[..entry.lines.start]
+                .iter()
+                .for_each(|l| out.push_str(l));
+            out.extend(written);
+            lines[entry.lines.end..]
+                .iter()
+                .for_each(|l| out.push_str(l));
+        }
+        None => {
+            out.push_str(body);
+            if !out.is_empty() && !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out.extend(written);
+        }
+    }
+    out
+}
+
+/// A key and its value as YAML lines, each ending in a line break.
+fn yaml_entry(key: &str, value: &PropertyValue, indent: &str) -> String {
+    let key = yaml_key_text(key);
+    match value {
+        PropertyValue::List(items) if !items.is_empty() => {
+            let mut out = format!("{}:\n", key);
+            for item in items {
+                out.push_str(&format!("{}- {}\n", indent, yaml_inline(item)));
+            }
+            out
+        }
+        PropertyValue::Null => format!("{}:\n", key),
+        PropertyValue::Text(text) if text.contains('\n') => {
+            let mut out = format!("{}: |\n", key);
+            for line in text.lines() {
+                out.push_str(&format!("{}{}\n", indent, line));
+            }
+            out
+        }
+        value => format!("{}: {}\n", key, yaml_inline(value)),
+    }
+}
+
+fn yaml_key_text(key: &str) -> String {
+    if needs_quotes(key) || key.contains(':') {
+        quote(key)
+    } else {
+        key.to_string()
+    }
+}
+
+/// A value written on one line.
+fn yaml_inline(value: &PropertyValue) -> String {
+    match value {
+        PropertyValue::Text(text) if needs_quotes(text) => quote(text),
+        PropertyValue::Text(text) => text.clone(),
+        PropertyValue::Number(n) => format_number(*n),
+        PropertyValue::Boolean(b) => b.to_string(),
+        PropertyValue::Date(date) => date.clone(),
+        PropertyValue::List(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| match item {
+                    PropertyValue::Text(text) if text.contains([',', '[', ']', '{', '}']) => {
+                        quote(text)
+                    }
+                    item => yaml_inline(item),
+                })
+                .collect();
+            format!("[{}]", items.join(", "))
+        }
+        PropertyValue::Null => "null".to_string(),
+    }
+}
+
+/// True if `text`, written plainly, would read back as something else.
+fn needs_quotes(text: &str) -> bool {
+    text.is_empty()
+        || text != text.trim()
+        || text.contains(['\n', '\t'])
+        || text.contains(": ")
+        || text.contains(" #")
+        || text.ends_with(':')
+        || text.starts_with([
+            '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%',
+            '@', '`',
+        ])
+        || yaml_scalar(text) != PropertyValue::Text(text.to_string())
+}
+
+fn quote(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+fn format_number(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        n.to_string()
+    }
+}
+
+fn set_toml(body: &str, key: &str, value: Option<&PropertyValue>) -> Result<String, SlateError> {
+    let mut doc = body
+        .parse::<DocumentMut>()
+        .map_err(|e| SlateError::invalid_input(format!("Invalid TOML frontmatter: {}", e)))?;
+    match value {
+        Some(value) => {
+            let value = to_toml_value(value)?;
+            match doc.get_mut(key).and_then(Item::as_value_mut) {
+                // Keeps the comments and spacing around the old value
+                Some(old) => {
+                    let decor = old.decor().clone();
+                    *old = value;
+                    *old.decor_mut() = decor;
+                }
+                None => {
+                    doc.insert(key, Item::Value(value));
+                }
+            }
+        }
+        None => {
+            doc.remove(key);
+        }
+    }
+    Ok(doc.to_string())
+}
+
+fn to_toml_value(value: &PropertyValue) -> Result<Value, SlateError> {
+    Ok(match value {
+        PropertyValue::Text(text) => Value::from(text.as_str()),
+        PropertyValue::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => Value::from(*n as i64),
+        PropertyValue::Number(n) => Value::from(*n),
+        PropertyValue::Boolean(b) => Value::from(*b),
+        PropertyValue::Date(date) => {
+            match date.replacen(' ', "T", 1).parse::<toml_edit::Datetime>() {
+                Ok(datetime) => Value::from(datetime),
+                Err(_) => return Err(SlateError::invalid_input(format!("Invalid date: {}", date))),
+            }
+        }
+        PropertyValue::List(items) => {
+            let items = items
+                .iter()
+                .map(to_toml_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            Value::Array(items.into_iter().collect::<Array>())
+        }
+        PropertyValue::Null => {
+            return Err(SlateError::invalid_input(
+                "TOML frontmatter cannot hold an empty value",
+            ))
+        }
+    })
+}
+
+fn from_toml_item(item: &Item) -> PropertyValue {
+    match item {
+        Item::Value(value) => from_toml_value(value),
+        Item::None => PropertyValue::Null,
+        other => PropertyValue::Text(other.to_string().trim().to_string()),
+    }
+}
+
+fn from_toml_value(value: &Value) -> PropertyValue {
+    match value {
+        Value::String(s) => PropertyValue::Text(s.value().clone()),
+        Value::Integer(i) => PropertyValue::Number(*i.value() as f64),
+        Value::Float(f) => PropertyValue::Number(*f.value()),
+        Value::Boolean(b) => PropertyValue::Boolean(*b.value()),
+        Value::Datetime(dt) => PropertyValue::Date(dt.value().to_string()),
+        Value::Array(items) => PropertyValue::List(items.iter().map(from_toml_value).collect()),
+        Value::InlineTable(table) => PropertyValue::Text(table.to_string().trim().to_string()),
+    }
+}
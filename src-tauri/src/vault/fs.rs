@@ -0,0 +1,171 @@
+//! Filesystem access for the vault core - the real disk, or an in-memory
+//! tree so scanning can be exercised without touching one
+
+use super::index::mtime_ms;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use tracing::warn;
+
+/// An entry returned by [`VaultFs::read_dir`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub path: PathBuf,
+    /// True for directories. Symlinks to directories are not followed.
+    pub is_dir: bool,
+}
+
+/// What the vault needs to know about a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileMeta {
+    pub is_file: bool,
+    /// Modification time in milliseconds since the Unix epoch (0 if unknown).
+    pub mtime: u64,
+    pub size: u64,
+}
+
+/// The filesystem operations scanning and indexing are built on.
+pub trait VaultFs: Send + Sync {
+    /// The entries of `dir`, in no particular order.
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<DirEntry>>;
+    /// Metadata of `path`, following symlinks.
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta>;
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+}
+
+/// The real filesystem.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiskFs;
+
+impl VaultFs for DiskFs {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<DirEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry.and_then(|entry| {
+                Ok(DirEntry {
+                    is_dir: entry.file_type()?.is_dir(),
+                    path: entry.path(),
+                })
+            });
+            // One bad entry does not hide the rest of the directory
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!(dir = %dir.display(), error = %e, "Skipping unreadable entry"),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+        let meta = fs::metadata(path)?;
+        Ok(FileMeta {
+            is_file: meta.is_file(),
+            mtime: mtime_ms(&meta),
+            size: meta.len(),
+        })
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+}
+
+/// A filesystem held in memory. Directories exist implicitly above every
+/// file; each write moves the file's mtime on by one.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    files: Mutex<MemoryFiles>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryFiles {
+    files: BTreeMap<PathBuf, (Vec<u8>, u64)>,
+    clock: u64,
+}
+
+impl MemoryFs {
+    /// A filesystem holding `files`, given as absolute paths and contents.
+    pub fn with_files<P: AsRef<Path>>(files: &[(P, &str)]) -> Self {
+        let memory = MemoryFs::default();
+        for (path, text) in files {
+            memory.write(path.as_ref(), text.as_bytes());
+        }
+        memory
+    }
+
+    /// Creates or replaces the file at `path`.
+    pub fn write(&self, path: &Path, bytes: &[u8]) {
+        let mut state = self.lock();
+        state.clock += 1;
+        let mtime = state.clock;
+        state
+            .files
+            .insert(path.to_path_buf(), (bytes.to_vec(), mtime));
+    }
+
+    /// Deletes the file at `path`, returning whether it existed.
+    pub fn remove(&self, path: &Path) -> bool {
+        self.lock().files.remove(path).is_some()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MemoryFiles> {
+        self.files.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl VaultFs for MemoryFs {
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<DirEntry>> {
+        let state = self.lock();
+        if state.files.contains_key(dir) {
+            return Err(io::Error::other("Not a directory"));
+        }
+        let mut entries: BTreeMap<PathBuf, bool> = BTreeMap::new();
+        for path in state.files.keys() {
+            let Ok(rest) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let mut components = rest.components();
+            let Some(first) = components.next() else {
+                continue;
+            };
+            let is_dir = components.next().is_some();
+            *entries.entry(dir.join(first)).or_default() |= is_dir;
+        }
+        if entries.is_empty() {
+            return Err(io::ErrorKind::NotFound.into());
+        }
+        Ok(entries
+            .into_iter()
+            .map(|(path, is_dir)| DirEntry { path, is_dir })
+            .collect())
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+        let state = self.lock();
+        if let Some((bytes, mtime)) = state.files.get(path) {
+            return Ok(FileMeta {
+                is_file: true,
+                mtime: *mtime,
+                size: bytes.len() as u64,
+            });
+        }
+        if state.files.keys().any(|file| file.starts_with(path)) {
+            return Ok(FileMeta {
+                is_file: false,
+                mtime: 0,
+                size: 0,
+            });
+        }
+        Err(io::ErrorKind::NotFound.into())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.lock()
+            .files
+            .get(path)
+            .map(|(bytes, _)| bytes.clone())
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
@@ -0,0 +1,489 @@
+//! Fuzzy file finder - scores every note's path (and aliases) against a
+//! quick-switcher query and returns the best few with match positions
+
+use super::{FileEntry, VaultHandle, VaultRegistry};
+use crate::error::SlateError;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::State;
+use tracing::{debug, instrument};
+
+const DEFAULT_LIMIT: usize = 50;
+
+const MATCH: i32 = 16;
+/// First character of a path segment (after `/`).
+const BONUS_SEGMENT: i32 = 10;
+/// First character of a word: after a space, `-`, `_` or `.`, or a
+/// camelCase hump.
+const BONUS_WORD: i32 = 8;
+/// Character directly after the previous match.
+const BONUS_CONSECUTIVE: i32 = 6;
+/// Any match inside the file name rather than a folder.
+const BONUS_FILE_NAME: i32 = 4;
+/// The first query character's position bonus counts this many times, so
+/// queries that start at a word beat ones that start mid-word.
+const FIRST_CHAR_MULTIPLIER: i32 = 2;
+const GAP_START: i32 = 3;
+const GAP_EXTEND: i32 = 1;
+
+/// Boost for the most recently opened note; the n-th most recent gets
+/// `BOOST_OPENED / n`.
+const BOOST_OPENED: i32 = 40;
+/// Boost for a note modified just now, halving every `HALF_LIFE_DAYS`.
+const BOOST_MODIFIED: f64 = 16.0;
+const HALF_LIFE_DAYS: f64 = 7.0;
+
+const NONE: i32 = i32::MIN / 2;
+
+/// A compiled quick-switcher query.
+///
+/// Matching is smart-case: case-insensitive unless the query has an
+/// uppercase letter. A space in the query matches any separator (space,
+/// `-`, `_`, `.` or `/`), so "meet notes" finds "meeting-notes.md".
+pub struct Matcher {
+    query: Vec<char>,
+    /// The query as bytes (lowercased unless case-sensitive) if it is
+    /// ASCII, for the byte-level prefilter.
+    ascii: Option<Vec<u8>>,
+    case_sensitive: bool,
+    // Reused between candidates so scoring does not allocate
+    target: Vec<char>,
+    earliest: Vec<usize>,
+    scores: Vec<i32>,
+    from: Vec<u32>,
+}
+
+/// A scored match: higher is better. `indices` are the matched positions
+/// as UTF-16 offsets into the target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Scored {
+    pub score: i32,
+    pub indices: Vec<usize>,
+}
+
+impl Matcher {
+    pub fn new(query: &str) -> Self {
+        let query: Vec<char> = query.trim().chars().collect();
+        let case_sensitive = query.iter().any(|c| c.is_uppercase());
+        let ascii = query.iter().all(char::is_ascii).then(|| {
+            query
+                .iter()
+                .map(|&c| fold_if(c, !case_sensitive) as u8)
+                .collect()
+        });
+        Matcher {
+            query: if case_sensitive {
+                query
+            } else {
+                query.into_iter().map(|c| fold_if(c, true)).collect()
+            },
+            ascii,
+            case_sensitive,
+            target: Vec::new(),
+            earliest: Vec::new(),
+            scores: Vec::new(),
+            from: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.query.is_empty()
+    }
+
+    /// Cheap check that every query character appears in `target`, in
+    /// order. Most candidates stop here.
+    fn is_subsequence(&self, target: &str) -> bool {
+        if let Some(query) = &self.ascii {
+            let mut i = 0;
+            for &b in target.as_bytes() {
+                let t = if self.case_sensitive {
+                    b
+                } else {
+                    b.to_ascii_lowercase()
+                };
+                let q = query[i];
+                if q == t || (q == b' ' && is_separator(t as char)) {
+                    i += 1;
+                    if i == query.len() {
+                        return true;
+                    }
+                }
+            }
+            return false;
+        }
+
+        let mut query = self.query.iter();
+        let mut next = query.next();
+        for t in target.chars() {
+            match next {
+                Some(&q) if chars_match(q, t, self.case_sensitive) => next = query.next(),
+                Some(_) => {}
+                None => break,
+            }
+        }
+        next.is_none()
+    }
+
+    /// Scores `target`, a relative path, or None if it does not match.
+    pub fn score_path(&mut self, target: &str) -> Option<i32> {
+        self.align(target, true, false)
+    }
+
+    /// Scores `target`, an alias, or None if it does not match.
+    pub fn score_alias(&mut self, target: &str) -> Option<i32> {
+        self.align(target, false, false)
+    }
+
+    /// Like [`Matcher::score_path`]/[`Matcher::score_alias`], with the
+    /// positions that produced the score.
+    pub fn indices(&mut self, target: &str, is_path: bool) -> Option<Scored> {
+        let score = self.align(target, is_path, true)?;
+        let m = self.query.len();
+        let n = self.target.len();
+        if m == 0 {
+            return Some(Scored {
+                score,
+                indices: Vec::new(),
+            });
+        }
+
+        // Walk back from the best end position
+        let last = &self.scores[(m - 1) * n..m * n];
+        let mut j = (0..n).max_by_key(|&j| (last[j], Reverse(j)))?;
+        let mut positions = vec![0; m];
+        for i in (0..m).rev() {
+            positions[i] = j;
+            if i > 0 {
+                j = self.from[i * n + j] as usize;
+            }
+        }
+
+        let mut utf16 = Vec::with_capacity(n);
+        let mut offset = 0;
+        for c in &self.target {
+            utf16.push(offset);
+            offset += c.len_utf16();
+        }
+        Some(Scored {
+            score,
+            indices: positions.into_iter().map(|j| utf16[j]).collect(),
+        })
+    }
+
+    /// Best alignment of the query against `target`: a match scores
+    /// `MATCH` plus its position bonus, and gaps between matches cost
+    /// `GAP_START` plus `GAP_EXTEND` per extra skipped character.
+    ///
+    /// Keeps every row of the score table when `trace` is set, so
+    /// [`Matcher::indices`] can walk it back; otherwise only two.
+    fn align(&mut self, target: &str, is_path: bool, trace: bool) -> Option<i32> {
+        if self.query.is_empty() {
+            return Some(0);
+        }
+        if !self.is_subsequence(target) {
+            return None;
+        }
+
+        self.target.clear();
+        self.target.extend(target.chars());
+        let n = self.target.len();
+        let m = self.query.len();
+        let file_name_start = if is_path {
+            self.target
+                .iter()
+                .rposition(|&c| c == '/' || c == '\\')
+                .map_or(0, |i| i + 1)
+        } else {
+            n
+        };
+        let bonus = |target: &[char], j: usize| {
+            let position = position_bonus(j.checked_sub(1).map(|p| target[p]), target[j]);
+            if j >= file_name_start {
+                position + BONUS_FILE_NAME
+            } else {
+                position
+            }
+        };
+
+        // Where each query character can first match; nothing before it can
+        self.earliest.clear();
+        let mut j = 0;
+        for &q in &self.query {
+            while !chars_match(q, self.target[j], self.case_sensitive) {
+                j += 1;
+            }
+            self.earliest.push(j);
+            j += 1;
+        }
+
+        let rows = if trace { m } else { 2 };
+        self.scores.clear();
+        self.scores.resize(rows * n, NONE);
+        if trace {
+            self.from.clear();
+            self.from.resize(m * n, 0);
+        }
+        let row_start = |i: usize| if trace { i * n } else { (i % 2) * n };
+
+        for j in self.earliest[0]..n {
+            if chars_match(self.query[0], self.target[j], self.case_sensitive) {
+                self.scores[j] = MATCH + bonus(&self.target, j) * FIRST_CHAR_MULTIPLIER;
+            }
+        }
+        for i in 1..m {
+            let (prev_start, row_start) = (row_start(i - 1), row_start(i));
+            let (prev, row) = if prev_start < row_start {
+                let (a, b) = self.scores.split_at_mut(row_start);
+                (&a[prev_start..prev_start + n], &mut b[..n])
+            } else {
+                let (a, b) = self.scores.split_at_mut(prev_start);
+                (&b[..n], &mut a[row_start..row_start + n])
+            };
+            row.fill(NONE);
+
+            // Best previous match at least two characters back, less the gap.
+            // Starts from the previous row's first match so the gap sees it
+            let mut gap = NONE;
+            let mut gap_from = 0;
+            for j in self.earliest[i - 1] + 1..n {
+                if j >= 2 {
+                    let opened = prev[j - 2] - GAP_START;
+                    if opened >= gap - GAP_EXTEND {
+                        gap = opened;
+                        gap_from = j - 2;
+                    } else {
+                        gap -= GAP_EXTEND;
+                    }
+                }
+                if !chars_match(self.query[i], self.target[j], self.case_sensitive) {
+                    continue;
+                }
+
+                let consecutive = prev[j - 1] + BONUS_CONSECUTIVE;
+                let (best, best_from) = if consecutive >= gap {
+                    (consecutive, j - 1)
+                } else {
+                    (gap, gap_from)
+                };
+                if best > NONE / 2 {
+                    row[j] = best + MATCH + bonus(&self.target, j);
+                    if trace {
+                        self.from[i * n + j] = best_from as u32;
+                    }
+                }
+            }
+        }
+
+        let last = row_start(m - 1);
+        let best = *self.scores[last..last + n].iter().max()?;
+        (best > NONE / 2).then_some(best)
+    }
+}
+
+/// Bonus for a match at a character given the one before it.
+fn position_bonus(prev: Option<char>, c: char) -> i32 {
+    match prev {
+        None | Some('/') | Some('\\') => BONUS_SEGMENT,
+        Some(p) if is_separator(p) => BONUS_WORD,
+        Some(p) if p.is_lowercase() && c.is_uppercase() => BONUS_WORD,
+        Some(p) if !p.is_alphanumeric() && c.is_alphanumeric() => BONUS_WORD,
+        _ => 0,
+    }
+}
+
+fn chars_match(q: char, t: char, case_sensitive: bool) -> bool {
+    if q == ' ' {
+        is_separator(t)
+    } else {
+        q == fold_if(t, !case_sensitive)
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, ' ' | '-' | '_' | '.' | '/' | '\\')
+}
+
+/// Lowercases `c` if `fold` is set. Queries are folded once up front, so
+/// only target characters go through here.
+fn fold_if(c: char, fold: bool) -> char {
+    if !fold {
+        c
+    } else if c.is_ascii() {
+        c.to_ascii_lowercase()
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// How much to favour a note the user has been working on.
+pub struct Recency {
+    /// Milliseconds since the Unix epoch.
+    now: u64,
+    /// Rank of recently opened notes by relative path, most recent first.
+    opened: HashMap<String, usize>,
+}
+
+impl Recency {
+    /// `opened` lists recently opened notes, most recent first.
+    pub fn new(opened: Vec<String>) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let mut ranks = HashMap::with_capacity(opened.len());
+        for (rank, path) in opened.into_iter().enumerate() {
+            ranks.entry(path).or_insert(rank);
+        }
+        Recency { now, opened: ranks }
+    }
+
+    /// Boost for a note last modified at `mtime` (ms since the Unix epoch).
+    pub fn boost(&self, relative_path: &str, mtime: u64) -> i32 {
+        let opened = self
+            .opened
+            .get(relative_path)
+            .map_or(0, |&rank| BOOST_OPENED / (rank as i32 + 1));
+        let age_days = self.now.saturating_sub(mtime) as f64 / 86_400_000.0;
+        let modified = BOOST_MODIFIED * 0.5f64.powf(age_days / HALF_LIFE_DAYS);
+        opened + modified.round() as i32
+    }
+}
+
+/// Scores a note by its path and each of its aliases, keeping the best.
+/// Returns the score and the alias it came from, if any.
+pub fn best_match<'a>(
+    matcher: &mut Matcher,
+    path: &str,
+    aliases: &'a [String],
+) -> Option<(i32, Option<&'a String>)> {
+    let by_path = matcher.score_path(path).map(|score| (score, None));
+    let by_alias = aliases
+        .iter()
+        .filter_map(|alias| Some((matcher.score_alias(alias)?, Some(alias))))
+        .max_by_key(|(score, _)| *score);
+    by_path
+        .into_iter()
+        .chain(by_alias)
+        .max_by_key(|(score, _)| *score)
+}
+
+/// A note the quick switcher can offer, copied out of the vault's indexes
+/// so matching does not hold the registry lock.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    /// Relative path.
+    pub path: String,
+    /// Last modified, in ms since the Unix epoch.
+    pub mtime: u64,
+    pub aliases: Vec<String>,
+}
+
+/// Scores every candidate and returns the best `limit`, best first, each
+/// with its score (recency boost included) and the alias it matched by.
+/// Among equal scores, shorter paths come first, then alphabetical.
+pub fn rank<'a>(
+    matcher: &mut Matcher,
+    recency: &Recency,
+    candidates: &'a [Candidate],
+    limit: usize,
+) -> Vec<(i32, &'a Candidate, Option<&'a String>)> {
+    let mut matched = Vec::new();
+    for candidate in candidates {
+        if let Some((score, alias)) = best_match(matcher, &candidate.path, &candidate.aliases) {
+            let boost = recency.boost(&candidate.path, candidate.mtime);
+            matched.push((score + boost, candidate, alias));
+        }
+    }
+
+    let order = |a: &(i32, &Candidate, Option<&String>), b: &(i32, &Candidate, Option<&String>)| {
+        b.0.cmp(&a.0)
+            .then_with(|| a.1.path.len().cmp(&b.1.path.len()))
+            .then_with(|| a.1.path.cmp(&b.1.path))
+    };
+    if matched.len() > limit && limit > 0 {
+        matched.select_nth_unstable_by(limit - 1, order);
+    }
+    matched.truncate(limit);
+    matched.sort_unstable_by(order);
+    matched
+}
+
+/// A note matching a quick-switcher query.
+#[derive(Clone, Debug, Serialize)]
+pub struct FuzzyMatch {
+    pub file: FileEntry,
+    pub score: i32,
+    /// The alias that matched, if the note matched by alias rather than
+    /// path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Matched positions in `file.relativePath`, or in `alias` if set, as
+    /// UTF-16 offsets.
+    pub indices: Vec<usize>,
+}
+
+/// Fuzzy-matches notes of an open vault by path and alias, best first.
+/// With an empty query, returns the most recent notes.
+///
+/// `recent` lists recently opened notes (relative paths, most recent
+/// first); they and recently modified notes rank higher.
+#[tauri::command]
+#[instrument(skip(registry, vault, recent))]
+pub fn fuzzy_find(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    query: String,
+    limit: Option<usize>,
+    recent: Option<Vec<String>>,
+) -> Result<Vec<FuzzyMatch>, SlateError> {
+    let start = Instant::now();
+    let mut matcher = Matcher::new(&query);
+    let recency = Recency::new(recent.unwrap_or_default());
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+
+    let candidates: Vec<Candidate> = registry
+        .with_open(&vault, |open| {
+            let index = open.index.as_ref()?;
+            let properties = open.properties.as_ref();
+            Some(
+                index
+                    .iter()
+                    .map(|(path, entry)| Candidate {
+                        path: path.clone(),
+                        mtime: entry.mtime,
+                        aliases: properties.map_or_else(Vec::new, |p| p.aliases(path).to_vec()),
+                    })
+                    .collect(),
+            )
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Vault index"))?;
+
+    let matches: Vec<FuzzyMatch> = rank(&mut matcher, &recency, &candidates, limit)
+        .into_iter()
+        .filter_map(|(score, candidate, alias)| {
+            let full_path = vault.join(&candidate.path).ok()?;
+            let indices = match alias {
+                Some(alias) => matcher.indices(alias, false),
+                None => matcher.indices(&candidate.path, true),
+            }
+            .map(|scored| scored.indices)
+            .unwrap_or_default();
+            Some(FuzzyMatch {
+                file: FileEntry::new(vault.root(), &full_path),
+                score,
+                alias: alias.cloned(),
+                indices,
+            })
+        })
+        .collect();
+
+    debug!(
+        matches = matches.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Fuzzy find complete"
+    );
+    Ok(matches)
+}
@@ -0,0 +1,397 @@
+//! Graph - the vault's notes and the links between them, taken from the
+//! link index, filtered and exported as JSON, GraphML or DOT
+
+use super::links::{LinkIndex, LinkSyntax};
+use super::tags;
+use super::{VaultHandle, VaultRegistry};
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::time::Instant;
+use tauri::State;
+use tracing::{info, instrument};
+
+/// Links followed out from the center of a local graph when no depth is
+/// given.
+const DEFAULT_DEPTH: usize = 1;
+
+/// A note in the graph, or a link target that does not exist.
+#[derive(Clone, Debug, Serialize)]
+pub struct GraphNode {
+    /// Relative path of the note; for a missing note, the link target as
+    /// written.
+    pub id: String,
+    /// File name without `.md`.
+    pub name: String,
+    /// Folder holding the note, relative to the vault root; empty at the
+    /// root.
+    pub folder: String,
+    pub tags: Vec<String>,
+    /// False for a link target that does not exist.
+    pub exists: bool,
+}
+
+/// The links from one note to another.
+#[derive(Clone, Debug, Serialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    /// How many links `source` has to `target`.
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Graph {
+    /// Sorted by id.
+    pub nodes: Vec<GraphNode>,
+    /// Sorted by source, then target.
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Which part of the graph to return. Everything by default.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GraphFilter {
+    /// Only notes in these folders, or folders inside them. Empty entries
+    /// are ignored.
+    pub folders: Vec<String>,
+    /// Leave out notes in these folders. Empty entries are ignored.
+    pub exclude_folders: Vec<String>,
+    /// Only notes with one of these tags, or a tag nested under one.
+    pub tags: Vec<String>,
+    /// Leave out links that embed their target.
+    pub hide_embeds: bool,
+    /// Include link targets that do not exist, as nodes with `exists` false.
+    pub missing: bool,
+    /// Leave out notes with no links to or from the rest of the graph.
+    pub hide_orphans: bool,
+    /// Only notes within `depth` links (in either direction) of this one.
+    pub center: Option<String>,
+    pub depth: Option<usize>,
+}
+
+/// Output format of [`export_graph`].
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GraphFormat {
+    Json,
+    Graphml,
+    Dot,
+}
+
+/// Builds the graph of notes in `index` that pass `filter`.
+pub fn build(index: &LinkIndex, filter: &GraphFilter) -> Result<Graph, SlateError> {
+    // An empty folder (or just `/`) is the whole vault, which would make
+    // an exclusion leave out everything, so such entries are ignored
+    let folders = |list: &[String]| -> Vec<String> {
+        list.iter()
+            .map(|folder| folder.trim_matches('/'))
+            .filter(|folder| !folder.is_empty())
+            .map(|folder| format!("{}/", folder))
+            .collect()
+    };
+    let (only, exclude) = (folders(&filter.folders), folders(&filter.exclude_folders));
+    let included = |path: &str| {
+        let tags = index.tags(path);
+        (only.is_empty() || only.iter().any(|f| path.starts_with(f.as_str())))
+            && !exclude.iter().any(|f| path.starts_with(f.as_str()))
+            && (filter.tags.is_empty()
+                || tags
+                    .iter()
+                    .any(|tag| filter.tags.iter().any(|parent| tags::is_under(tag, parent))))
+    };
+
+    let mut nodes: BTreeMap<String, GraphNode> = index
+        .iter()
+        .filter(|(path, _)| included(path))
+        .map(|(path, _)| (path.clone(), node(path, index.tags(path).to_vec(), true)))
+        .collect();
+
+    let mut counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+    let mut missing: BTreeMap<String, GraphNode> = BTreeMap::new();
+    for (source, links) in index.iter() {
+        if !nodes.contains_key(source) {
+            continue;
+        }
+        for link in links {
+            if link.target.is_empty() || (link.embed && filter.hide_embeds) {
+                continue;
+            }
+            let target = match index.resolver().resolve_to_note(source, link) {
+                Some(target) if target == source.as_str() => continue,
+                Some(target) if nodes.contains_key(target) => target.to_string(),
+                Some(_) => continue,
+                // Only links meant for a note; a missing image is not a node
+                None if filter.missing && is_note_target(&link.target) => {
+                    let id = match link.syntax {
+                        LinkSyntax::Wikilink => link.target.clone(),
+                        LinkSyntax::Markdown => link.target.trim_end_matches(".md").to_string(),
+                    };
+                    missing
+                        .entry(id.clone())
+                        .or_insert_with(|| node(&id, Vec::new(), false));
+                    id
+                }
+                None => continue,
+            };
+            *counts.entry((source.clone(), target)).or_default() += 1;
+        }
+    }
+    // A missing note named like an existing one would clash with it
+    missing.retain(|id, _| !nodes.contains_key(id));
+    nodes.append(&mut missing);
+
+    let mut edges: Vec<GraphEdge> = counts
+        .into_iter()
+        .filter(|((_, target), _)| nodes.contains_key(target))
+        .map(|((source, target), count)| GraphEdge {
+            source,
+            target,
+            count,
+        })
+        .collect();
+
+    if let Some(center) = &filter.center {
+        if !nodes.contains_key(center) {
+            return Err(SlateError::not_found(
+                center,
+                format!("Not in the graph: {}", center),
+            ));
+        }
+        let near = neighborhood(&edges, center, filter.depth.unwrap_or(DEFAULT_DEPTH));
+        nodes.retain(|id, _| near.contains(id));
+        edges.retain(|edge| near.contains(&edge.source) && near.contains(&edge.target));
+    }
+    if filter.hide_orphans {
+        let linked: HashSet<&str> = edges
+            .iter()
+            .flat_map(|edge| [edge.source.as_str(), edge.target.as_str()])
+            .collect();
+        nodes.retain(|id, _| linked.contains(id.as_str()));
+    }
+
+    Ok(Graph {
+        nodes: nodes.into_values().collect(),
+        edges,
+    })
+}
+
+fn node(id: &str, tags: Vec<String>, exists: bool) -> GraphNode {
+    let path = Path::new(id);
+    let name = match path.extension() {
+        Some(ext) if ext == "md" => path.file_stem(),
+        _ => path.file_name(),
+    };
+    GraphNode {
+        id: id.to_string(),
+        name: name
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        folder: id
+            .rsplit_once('/')
+            .map_or("", |(folder, _)| folder)
+            .to_string(),
+        tags,
+        exists,
+    }
+}
+
+/// True if a link target names a note rather than some other file.
+fn is_note_target(target: &str) -> bool {
+    match Path::new(target).extension() {
+        Some(ext) => ext == "md",
+        None => true,
+    }
+}
+
+/// Nodes within `depth` edges of `center`, following edges either way.
+fn neighborhood(edges: &[GraphEdge], center: &str, depth: usize) -> HashSet<String> {
+    let mut adjacent: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in edges {
+        adjacent.entry(&edge.source).or_default().push(&edge.target);
+        adjacent.entry(&edge.target).or_default().push(&edge.source);
+    }
+
+    let mut seen = HashSet::from([center]);
+    let mut queue = VecDeque::from([(center, 0)]);
+    while let Some((id, distance)) = queue.pop_front() {
+        if distance == depth {
+            continue;
+        }
+        for &next in adjacent.get(id).into_iter().flatten() {
+            if seen.insert(next) {
+                queue.push_back((next, distance + 1));
+            }
+        }
+    }
+    seen.into_iter().map(str::to_string).collect()
+}
+
+/// The graph as GraphML, with the node and edge fields as attributes.
+pub fn to_graphml(graph: &Graph) -> Result<String, fmt::Error> {
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    for (id, kind, ty) in [
+        ("name", "node", "string"),
+        ("folder", "node", "string"),
+        ("tags", "node", "string"),
+        ("exists", "node", "boolean"),
+        ("count", "edge", "int"),
+    ] {
+        writeln!(
+            out,
+            r#"  <key id="{id}" for="{kind}" attr.name="{id}" attr.type="{ty}"/>"#
+        )?;
+    }
+    writeln!(out, r#"  <graph id="vault" edgedefault="directed">"#)?;
+    for node in &graph.nodes {
+        writeln!(out, r#"    <node id="{}">"#, xml_escape(&node.id))?;
+        writeln!(
+            out,
+            r#"      <data key="name">{}</data>"#,
+            xml_escape(&node.name)
+        )?;
+        writeln!(
+            out,
+            r#"      <data key="folder">{}</data>"#,
+            xml_escape(&node.folder)
+        )?;
+        writeln!(
+            out,
+            r#"      <data key="tags">{}</data>"#,
+            xml_escape(&node.tags.join(","))
+        )?;
+        writeln!(out, r#"      <data key="exists">{}</data>"#, node.exists)?;
+        writeln!(out, "    </node>")?;
+    }
+    for edge in &graph.edges {
+        writeln!(
+            out,
+            r#"    <edge source="{}" target="{}">"#,
+            xml_escape(&edge.source),
+            xml_escape(&edge.target)
+        )?;
+        writeln!(out, r#"      <data key="count">{}</data>"#, edge.count)?;
+        writeln!(out, "    </edge>")?;
+    }
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+    Ok(out)
+}
+
+/// The graph in Graphviz DOT. Missing notes are drawn dashed; edges are
+/// weighted by their link count.
+pub fn to_dot(graph: &Graph) -> Result<String, fmt::Error> {
+    let mut out = String::new();
+    writeln!(out, "digraph vault {{")?;
+    for node in &graph.nodes {
+        write!(
+            out,
+            "  {} [label={}, folder={}, tags={}",
+            dot_quote(&node.id),
+            dot_quote(&node.name),
+            dot_quote(&node.folder),
+            dot_quote(&node.tags.join(","))
+        )?;
+        if !node.exists {
+            write!(out, ", style=dashed")?;
+        }
+        writeln!(out, "];")?;
+    }
+    for edge in &graph.edges {
+        writeln!(
+            out,
+            "  {} -> {} [weight={}];",
+            dot_quote(&edge.source),
+            dot_quote(&edge.target),
+            edge.count
+        )?;
+    }
+    writeln!(out, "}}")?;
+    Ok(out)
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn dot_quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+fn graph_of(
+    registry: &VaultRegistry,
+    vault: &VaultHandle,
+    filter: &GraphFilter,
+) -> Result<Graph, SlateError> {
+    registry
+        .with_open(vault, |open| {
+            open.links.as_ref().map(|links| build(links, filter))
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Link index"))?
+}
+
+/// The link graph of an open vault, or the part of it `filter` asks for.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn get_graph(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    filter: Option<GraphFilter>,
+) -> Result<Graph, SlateError> {
+    let start = Instant::now();
+    let graph = graph_of(&registry, &vault, &filter.unwrap_or_default())?;
+    info!(
+        nodes = graph.nodes.len(),
+        edges = graph.edges.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Built graph"
+    );
+    Ok(graph)
+}
+
+/// The link graph as text in `format`, for visualising or analysing the
+/// vault in other tools.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn export_graph(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    format: GraphFormat,
+    filter: Option<GraphFilter>,
+) -> Result<String, SlateError> {
+    let graph = graph_of(&registry, &vault, &filter.unwrap_or_default())?;
+    export(&graph, format)
+}
+
+/// `graph` as text in `format`.
+pub fn export(graph: &Graph, format: GraphFormat) -> Result<String, SlateError> {
+    let text = match format {
+        GraphFormat::Json => serde_json::to_string_pretty(graph).map_err(|e| e.to_string()),
+        GraphFormat::Graphml => to_graphml(graph).map_err(|e| e.to_string()),
+        GraphFormat::Dot => to_dot(graph).map_err(|e| e.to_string()),
+    };
+    text.map_err(SlateError::internal)
+}
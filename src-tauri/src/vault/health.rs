@@ -0,0 +1,180 @@
+//! Vault health - orphans, dead ends, broken and ambiguous links and missing
+//! embeds, found from the link index
+
+use super::links::{self, Link, LinkIndex, LinkSyntax};
+use super::{VaultHandle, VaultRegistry};
+use crate::error::SlateError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
+use tauri::State;
+use tracing::{info, instrument};
+
+/// A link with something wrong with it.
+#[derive(Clone, Debug, Serialize)]
+pub struct LinkProblem {
+    /// Relative path of the note the link is in.
+    pub file: String,
+    /// 1-based line and column of the link.
+    pub line: usize,
+    pub column: usize,
+    /// The target as written.
+    pub target: String,
+    /// For an ambiguous link, every note it could mean, the one it leads to
+    /// first. Empty otherwise.
+    pub candidates: Vec<String>,
+}
+
+/// What is wrong in a vault. Every list is sorted by file, then position.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    /// Notes no other note links to.
+    pub orphans: Vec<String>,
+    /// Notes that link to no other note.
+    pub dead_ends: Vec<String>,
+    /// Links leading to no note or file. Embeds are in `missing_embeds`.
+    pub broken_links: Vec<LinkProblem>,
+    /// Wikilinks by name that more than one note has; they lead to the
+    /// first by path, which may not be the one meant.
+    pub ambiguous_links: Vec<LinkProblem>,
+    /// Embeds of a note or file that does not exist.
+    pub missing_embeds: Vec<LinkProblem>,
+}
+
+impl HealthReport {
+    /// True if nothing is wrong.
+    pub fn is_healthy(&self) -> bool {
+        self.orphans.is_empty()
+            && self.dead_ends.is_empty()
+            && self.broken_links.is_empty()
+            && self.ambiguous_links.is_empty()
+            && self.missing_embeds.is_empty()
+    }
+}
+
+/// The findings that need only the link index, and the links that lead to
+/// no note, which still have to be looked for on disk.
+struct Snapshot {
+    report: HealthReport,
+    unresolved: Vec<(String, Link)>,
+}
+
+fn snapshot(index: &LinkIndex) -> Snapshot {
+    // Notes by lowercased name, as wikilinks find them
+    let mut named: HashMap<String, Vec<&String>> = HashMap::new();
+    for (path, _) in index.iter() {
+        named
+            .entry(name(path).to_lowercase())
+            .or_default()
+            .push(path);
+    }
+
+    let mut report = HealthReport::default();
+    let mut unresolved = Vec::new();
+    for (source, links) in index.iter() {
+        if index.backlinks(source).next().is_none() {
+            report.orphans.push(source.clone());
+        }
+
+        let mut links_out = false;
+        for link in links {
+            let Some(target) = index.resolver().resolve_to_note(source, link) else {
+                unresolved.push((source.clone(), link.clone()));
+                continue;
+            };
+            links_out |= target != source.as_str();
+
+            let by_name = link.syntax == LinkSyntax::Wikilink && !link.target.contains('/');
+            let same_name = named.get(&name(target).to_lowercase());
+            if let Some(notes) = same_name.filter(|notes| by_name && notes.len() > 1) {
+                let mut candidates = vec![target.to_string()];
+                candidates.extend(
+                    notes
+                        .iter()
+                        .filter(|&&note| note != target)
+                        .map(|note| note.to_string()),
+                );
+                report
+                    .ambiguous_links
+                    .push(problem(source, link, candidates));
+            }
+        }
+        if !links_out {
+            report.dead_ends.push(source.clone());
+        }
+    }
+
+    Snapshot { report, unresolved }
+}
+
+/// Looks for the links that lead to no note on disk, where they may still
+/// lead to an attachment.
+fn finish(snapshot: Snapshot, root: &Path) -> HealthReport {
+    let Snapshot {
+        mut report,
+        unresolved,
+    } = snapshot;
+    for (source, link) in unresolved {
+        if link.target.is_empty() || links::resolve_file(root, &source, &link).is_some() {
+            continue;
+        }
+        let problem = problem(&source, &link, Vec::new());
+        if link.embed {
+            report.missing_embeds.push(problem);
+        } else {
+            report.broken_links.push(problem);
+        }
+    }
+    report
+}
+
+/// Checks a vault's health from its link index.
+pub fn check(index: &LinkIndex, root: &Path) -> HealthReport {
+    finish(snapshot(index), root)
+}
+
+/// File name without `.md`.
+fn name(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_suffix(".md").unwrap_or(name)
+}
+
+fn problem(source: &str, link: &Link, candidates: Vec<String>) -> LinkProblem {
+    LinkProblem {
+        file: source.to_string(),
+        line: link.line,
+        column: link.column,
+        target: link.target.clone(),
+        candidates,
+    }
+}
+
+/// Reports orphans, dead ends, broken and ambiguous links and missing
+/// embeds in an open vault.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn vault_health(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+) -> Result<HealthReport, SlateError> {
+    let start = Instant::now();
+    let snapshot = registry
+        .with_open(&vault, |open| open.links.as_ref().map(snapshot))
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Link index"))?;
+    // Attachments are looked for without holding the registry lock
+    let report = finish(snapshot, vault.root());
+
+    info!(
+        orphans = report.orphans.len(),
+        dead_ends = report.dead_ends.len(),
+        broken = report.broken_links.len(),
+        ambiguous = report.ambiguous_links.len(),
+        missing_embeds = report.missing_embeds.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Checked vault health"
+    );
+    Ok(report)
+}
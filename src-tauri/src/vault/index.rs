@@ -0,0 +1,292 @@
+//! Persistent vault index - per-file mtime, size and content hash stored in
+//! `.slate/` so startup can skip unchanged files
+
+use super::fs::{DiskFs, VaultFs};
+use super::{FileEntry, Vault};
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+/// Directory inside the vault holding Slate's own data. Hidden, so the
+/// scanner never lists it.
+pub const SLATE_DIR: &str = ".slate";
+const INDEX_FILE: &str = "index.bin";
+/// Bump whenever the on-disk layout changes; older indexes are rebuilt.
+const INDEX_VERSION: u32 = 1;
+
+/// What the index knows about a single file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Modification time in milliseconds since the Unix epoch.
+    pub mtime: u64,
+    pub size: u64,
+    /// Hex-encoded BLAKE3 hash of the file contents.
+    pub hash: String,
+}
+
+impl IndexEntry {
+    /// Stats and hashes the file at `path`.
+    pub fn read(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(path).ok()?;
+        Some(IndexEntry {
+            mtime: mtime_ms(&meta),
+            size: meta.len(),
+            hash: hash_file(&DiskFs, path)?,
+        })
+    }
+}
+
+/// Files that changed between the stored index and the disk.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexDelta {
+    pub added: Vec<FileEntry>,
+    pub modified: Vec<FileEntry>,
+    /// Relative paths of files that no longer exist.
+    pub removed: Vec<String>,
+}
+
+impl IndexDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Notes whose entry in an index derived from their contents (search,
+/// links) is out of date with the vault index.
+#[derive(Debug, Default)]
+pub struct Stale {
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Stale {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Writes `bytes` to `file_name` inside `.slate/`, replacing the old file
+/// atomically.
+pub fn write_slate_file(root: &Path, file_name: &str, bytes: &[u8]) -> Result<(), SlateError> {
+    let dir = root.join(SLATE_DIR);
+    fs::create_dir_all(&dir).map_err(|e| SlateError::io("Failed to create", &dir, e))?;
+
+    let path = dir.join(file_name);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).map_err(|e| SlateError::io("Failed to write", &path, e))?;
+    fs::rename(&tmp, &path).map_err(|e| SlateError::io("Failed to replace", &path, e))?;
+    Ok(())
+}
+
+/// Index of every markdown file in a vault, keyed by relative path.
+#[derive(Clone, Debug, Default)]
+pub struct VaultIndex {
+    files: BTreeMap<String, IndexEntry>,
+}
+
+impl VaultIndex {
+    fn file_path(root: &Path) -> PathBuf {
+        root.join(SLATE_DIR).join(INDEX_FILE)
+    }
+
+    /// Loads the stored index. Returns None if there is none or it cannot be
+    /// used (corrupt, or written by a different index version).
+    pub fn load(root: &Path) -> Option<Self> {
+        let path = Self::file_path(root);
+        let bytes = fs::read(&path).ok()?;
+        match bincode::deserialize::<(u32, BTreeMap<String, IndexEntry>)>(&bytes) {
+            Ok((INDEX_VERSION, files)) => {
+                debug!(file_count = files.len(), "Loaded vault index");
+                Some(VaultIndex { files })
+            }
+            Ok((version, _)) => {
+                info!(version, "Discarding vault index from another version");
+                None
+            }
+            Err(e) => {
+                warn!(path = ?path, error = %e, "Discarding unreadable vault index");
+                None
+            }
+        }
+    }
+
+    /// Writes the index to `.slate/index.bin`, replacing the old one atomically.
+    pub fn save(&self, root: &Path) -> Result<(), SlateError> {
+        let bytes = bincode::serialize(&(INDEX_VERSION, &self.files))
+            .map_err(|e| SlateError::internal(format!("Failed to serialize vault index: {}", e)))?;
+        write_slate_file(root, INDEX_FILE, &bytes)
+    }
+
+    /// Indexed files and their entries, sorted by relative path.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &IndexEntry)> {
+        self.files.iter()
+    }
+
+    /// Compares a derived index with this one: `indexed` are the notes it
+    /// has, and `hash_of` the content hash it has for a note.
+    pub fn stale<'a>(
+        &self,
+        indexed: impl Iterator<Item = &'a String>,
+        hash_of: impl Fn(&str) -> Option<&'a str>,
+    ) -> Stale {
+        let removed = indexed
+            .filter(|path| !self.files.contains_key(*path))
+            .cloned()
+            .collect();
+        let changed = self
+            .files
+            .iter()
+            .filter(|(path, entry)| hash_of(path).is_none_or(|hash| hash != entry.hash))
+            .map(|(path, _)| path.clone())
+            .collect();
+        Stale { removed, changed }
+    }
+
+    pub fn get(&self, relative_path: &str) -> Option<&IndexEntry> {
+        self.files.get(relative_path)
+    }
+
+    pub fn insert(&mut self, relative_path: String, entry: IndexEntry) {
+        self.files.insert(relative_path, entry);
+    }
+
+    pub fn remove(&mut self, relative_path: &str) -> Option<IndexEntry> {
+        self.files.remove(relative_path)
+    }
+
+    /// Relative paths of indexed files inside the directory `relative_dir`.
+    pub fn paths_under(&self, relative_dir: &str) -> Vec<String> {
+        let dir = Path::new(relative_dir);
+        self.files
+            .keys()
+            .filter(|rel| Path::new(rel).starts_with(dir) && rel.as_str() != relative_dir)
+            .cloned()
+            .collect()
+    }
+
+    /// All indexed files, sorted by relative path.
+    pub fn entries(&self, root: &Path) -> Vec<FileEntry> {
+        let is_note = |rel: &str| self.files.contains_key(rel);
+        self.files
+            .keys()
+            .map(|rel| FileEntry::with_lookup(root, &root.join(rel), &is_note))
+            .collect()
+    }
+
+    /// Brings the index in line with the disk. Files whose mtime and size
+    /// match the index are not read; everything else is rehashed, and only
+    /// files whose hash actually changed are reported as modified.
+    pub fn reconcile(&mut self, root: &Path) -> IndexDelta {
+        self.reconcile_with(&Vault::on_disk(root))
+    }
+
+    /// [`reconcile`](Self::reconcile) against any filesystem.
+    pub fn reconcile_with<F: VaultFs>(&mut self, vault: &Vault<F>) -> IndexDelta {
+        let never = AtomicBool::new(false);
+        self.reconcile_paths(vault, vault.markdown_files(), &never)
+            .unwrap_or_default()
+    }
+
+    /// [`reconcile_with`](Self::reconcile_with) for `paths`, the vault's
+    /// markdown files as already walked, so a scan need not walk twice.
+    /// Returns None, leaving the index part way, if `cancel` is set before
+    /// it finishes.
+    pub fn reconcile_paths<F: VaultFs>(
+        &mut self,
+        vault: &Vault<F>,
+        paths: impl IntoIterator<Item = PathBuf>,
+        cancel: &AtomicBool,
+    ) -> Option<IndexDelta> {
+        let start = Instant::now();
+        let mut delta = IndexDelta::default();
+        let mut seen = HashSet::with_capacity(self.files.len());
+        let mut hashed = 0usize;
+
+        for path in paths {
+            if cancel.load(Ordering::Relaxed) {
+                info!(hashed, "Index reconcile cancelled");
+                return None;
+            }
+            let file = vault.entry(&path);
+            seen.insert(file.relative_path.clone());
+
+            let Ok(meta) = vault.fs().metadata(&path) else {
+                continue;
+            };
+            let (mtime, size) = (meta.mtime, meta.size);
+
+            if let Some(existing) = self.files.get(&file.relative_path) {
+                if existing.mtime == mtime && existing.size == size {
+                    continue;
+                }
+            }
+
+            let Some(hash) = hash_file(vault.fs(), &path) else {
+                continue;
+            };
+            hashed += 1;
+
+            let is_new = !self.files.contains_key(&file.relative_path);
+            let content_changed = self
+                .files
+                .get(&file.relative_path)
+                .is_some_and(|old| old.hash != hash);
+            self.files
+                .insert(file.relative_path.clone(), IndexEntry { mtime, size, hash });
+
+            // A touched file with identical content only moves the metadata on
+            if is_new {
+                delta.added.push(file);
+            } else if content_changed {
+                delta.modified.push(file);
+            }
+        }
+
+        self.files.retain(|rel, _| {
+            let keep = seen.contains(rel);
+            if !keep {
+                delta.removed.push(rel.clone());
+            }
+            keep
+        });
+
+        info!(
+            file_count = self.files.len(),
+            hashed,
+            added = delta.added.len(),
+            modified = delta.modified.len(),
+            removed = delta.removed.len(),
+            elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+            "Index reconciled"
+        );
+
+        Some(delta)
+    }
+}
+
+/// Modification time in milliseconds since the Unix epoch (0 if unknown).
+pub fn mtime_ms(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Hex-encoded BLAKE3 hash of `bytes`.
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex().to_string()
+}
+
+fn hash_file(fs: &impl VaultFs, path: &Path) -> Option<String> {
+    fs.read(path)
+        .map(|bytes| hash_bytes(&bytes))
+        .map_err(|e| warn!(path = ?path, error = %e, "Failed to read file for indexing"))
+        .ok()
+}
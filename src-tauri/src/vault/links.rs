@@ -0,0 +1,915 @@
+//! Links - parses wikilinks, markdown links and embeds out of notes,
+//! resolves them the way the editor does, and keeps every note's outgoing
+//! links (and tags, the other thing notes point at) in `.slate/`
+
+use super::index::{hash_bytes, write_slate_file, Stale, SLATE_DIR};
+use super::tags;
+use super::{FileEntry, VaultHandle, VaultIndex, VaultRegistry};
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use tauri::State;
+use tracing::{debug, info, instrument, warn};
+
+const LINKS_FILE: &str = "links.bin";
+/// Bump whenever the on-disk layout or the parser changes; older link
+/// indexes are rebuilt.
+const LINKS_VERSION: u32 = 4;
+
+/// How a link is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkSyntax {
+    /// `[[target#anchor|alias]]`
+    Wikilink,
+    /// `[alias](target#anchor)`
+    Markdown,
+}
+
+/// The part of a note a link points into.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Anchor {
+    /// `#Heading`
+    Heading(String),
+    /// `#^block-id`
+    Block(String),
+}
+
+/// A link found in a note.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Link {
+    pub syntax: LinkSyntax,
+    /// `![[...]]` or `![...](...)`: the target is shown inline.
+    pub embed: bool,
+    /// The note or file linked to as written, URL-decoded for markdown
+    /// links. Empty for a link within the same note, like `[[#Heading]]`.
+    pub target: String,
+    pub anchor: Option<Anchor>,
+    /// Display text: `[[target|alias]]` or `[alias](target)`.
+    pub alias: Option<String>,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column where the link starts, in characters.
+    pub column: usize,
+    /// Byte range of the whole link in the note.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Finds every link in a note. Links in fenced code blocks and inline code
+/// are not links; external URLs are left out.
+pub fn parse(text: &str) -> Vec<Link> {
+    let mut links = Vec::new();
+    let mut fence: Option<(u8, usize)> = None;
+    let mut offset = 0;
+
+    for (i, line) in text.split_inclusive('\n').enumerate() {
+        match (fence_marker(line), fence) {
+            (Some(marker), None) => fence = Some(marker),
+            (Some((c, len)), Some((open, open_len))) if c == open && len >= open_len => {
+                fence = None
+            }
+            (_, None) => parse_line(line, offset, i + 1, &mut links),
+            _ => {}
+        }
+        offset += line.len();
+    }
+    links
+}
+
+/// Byte ranges of the fenced code blocks and inline code spans in a note,
+/// where nothing is a link or a mention.
+pub(crate) fn code_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut fence: Option<(u8, usize, usize)> = None;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        match (fence_marker(line), fence) {
+            (Some((c, len)), None) => fence = Some((c, len, offset)),
+            (Some((c, len)), Some((open, open_len, start))) if c == open && len >= open_len => {
+                ranges.push((start, offset + line.len()));
+                fence = None;
+            }
+            (_, None) => ranges.extend(
+                code_spans(line)
+                    .into_iter()
+                    .map(|(start, end)| (offset + start, offset + end)),
+            ),
+            _ => {}
+        }
+        offset += line.len();
+    }
+    // An unclosed fence runs to the end of the note
+    if let Some((_, _, start)) = fence {
+        ranges.push((start, text.len()));
+    }
+    ranges
+}
+
+/// If `line` opens or closes a fenced code block, its fence character and
+/// length.
+pub(crate) fn fence_marker(line: &str) -> Option<(u8, usize)> {
+    let trimmed = line.trim_start();
+    let c = *trimmed.as_bytes().first()?;
+    if c != b'`' && c != b'~' {
+        return None;
+    }
+    let len = trimmed.bytes().take_while(|&b| b == c).count();
+    (len >= 3).then_some((c, len))
+}
+
+fn parse_line(line: &str, offset: usize, number: usize, links: &mut Vec<Link>) {
+    let code = code_spans(line);
+    let bytes = line.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(&(_, end)) = code.iter().find(|(start, end)| (*start..*end).contains(&i)) {
+            i = end;
+            continue;
+        }
+        let escaped = i > 0 && bytes[i - 1] == b'\\';
+        if bytes[i] != b'[' || escaped {
+            i += 1;
+            continue;
+        }
+
+        let parsed = if line[i..].starts_with("[[") {
+            wikilink(&line[i..])
+        } else {
+            markdown_link(&line[i..])
+        };
+        let Some((len, mut link)) = parsed else {
+            i += 1;
+            continue;
+        };
+        link.embed = i > 0 && bytes[i - 1] == b'!';
+        let start = if link.embed { i - 1 } else { i };
+        link.line = number;
+        link.column = line[..start].chars().count() + 1;
+        link.start = offset + start;
+        link.end = offset + i + len;
+        links.push(link);
+        i += len;
+    }
+}
+
+/// Byte ranges of the inline code spans in a line, backticks included.
+fn code_spans(line: &str) -> Vec<(usize, usize)> {
+    let bytes = line.as_bytes();
+    let run_at = |i: usize| bytes[i..].iter().take_while(|&&b| b == b'`').count();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        let run = run_at(i);
+        let mut j = i + run;
+        let mut close = None;
+        while j < bytes.len() {
+            if bytes[j] == b'`' {
+                let r = run_at(j);
+                if r == run {
+                    close = Some(j + r);
+                    break;
+                }
+                j += r;
+            } else {
+                j += 1;
+            }
+        }
+        match close {
+            Some(end) => {
+                spans.push((i, end));
+                i = end;
+            }
+            None => i += run,
+        }
+    }
+    spans
+}
+
+/// A link with only what is written inside it filled in.
+fn link(syntax: LinkSyntax, target: &str, alias: Option<&str>) -> Link {
+    let (target, anchor) = match target.split_once('#') {
+        Some((page, anchor)) => (page.trim(), parse_anchor(anchor.trim())),
+        None => (target.trim(), None),
+    };
+    Link {
+        syntax,
+        embed: false,
+        target: target.to_string(),
+        anchor,
+        alias: alias.map(str::trim).map(str::to_string),
+        line: 0,
+        column: 0,
+        start: 0,
+        end: 0,
+    }
+}
+
+fn parse_anchor(anchor: &str) -> Option<Anchor> {
+    match anchor.strip_prefix('^') {
+        _ if anchor.is_empty() => None,
+        Some(id) => Some(Anchor::Block(id.to_string())),
+        None => Some(Anchor::Heading(anchor.to_string())),
+    }
+}
+
+/// `[[target]]` or `[[target|alias]]` at the start of `s`, with the length
+/// it takes up. Follows the editor's syntax: neither part may contain `]`,
+/// the target may not contain `|`, and neither may be empty.
+fn wikilink(s: &str) -> Option<(usize, Link)> {
+    let inner_len = s[2..].find("]]")?;
+    let inner = &s[2..2 + inner_len];
+    if inner.contains([']', '\n']) {
+        return None;
+    }
+    let (target, alias) = match inner.split_once('|') {
+        Some((target, alias)) => (target, Some(alias)),
+        None => (inner, None),
+    };
+    if target.is_empty() || alias.is_some_and(str::is_empty) {
+        return None;
+    }
+    let link = link(LinkSyntax::Wikilink, target, alias);
+    if link.target.is_empty() && link.anchor.is_none() {
+        return None;
+    }
+    Some((2 + inner_len + 2, link))
+}
+
+/// `[text](destination)` or `[text](destination "title")` at the start of
+/// `s`, with the length it takes up. None for external URLs.
+fn markdown_link(s: &str) -> Option<(usize, Link)> {
+    let (text, (start, end), len) = markdown_parts(s)?;
+    let destination = &s[start..end];
+    if destination.is_empty() || is_external(destination) {
+        return None;
+    }
+    let destination = percent_decode(destination);
+    let alias = (!text.trim().is_empty()).then_some(text);
+    Some((len, link(LinkSyntax::Markdown, &destination, alias)))
+}
+
+/// The text, byte range of the destination (inside any angle brackets) and
+/// length of a markdown link at the start of `s`.
+fn markdown_parts(s: &str) -> Option<(&str, (usize, usize), usize)> {
+    // Link text may hold balanced brackets, e.g. an image
+    let mut depth = 0;
+    let close = s.char_indices().find_map(|(i, c)| match c {
+        '[' => {
+            depth += 1;
+            None
+        }
+        ']' if depth == 1 => Some(i),
+        ']' => {
+            depth -= 1;
+            None
+        }
+        '\n' => Some(0),
+        _ => None,
+    })?;
+    if close == 0 {
+        return None;
+    }
+    let text = &s[1..close];
+    let body = s[close + 1..].strip_prefix('(')?;
+    let body_start = close + 2;
+
+    let (destination, after) = match body.strip_prefix('<') {
+        Some(angled) => {
+            let end = angled.find(['>', '\n'])?;
+            ((body_start + 1, body_start + 1 + end), &angled[end + 1..])
+        }
+        None => {
+            let mut depth = 0;
+            let end = body.char_indices().find_map(|(i, c)| match c {
+                '(' => {
+                    depth += 1;
+                    None
+                }
+                ')' if depth == 0 => Some(i),
+                ')' => {
+                    depth -= 1;
+                    None
+                }
+                c if c.is_whitespace() => Some(i),
+                _ => None,
+            })?;
+            ((body_start, body_start + end), &body[end..])
+        }
+    };
+
+    // An optional title, then the closing parenthesis
+    let paren = after.find(')')?;
+    let title = after[..paren].trim();
+    if !(title.is_empty() || title.starts_with(['"', '\''])) {
+        return None;
+    }
+    Some((text, destination, s.len() - after.len() + paren + 1))
+}
+
+/// Byte range of the target as written in `raw`, the text of a parsed link
+/// (`[[target#anchor|alias]]`, `[alias](target#anchor)`), without its
+/// anchor. For markdown links it is still percent-encoded.
+pub(crate) fn target_range(raw: &str, syntax: LinkSyntax) -> Option<(usize, usize)> {
+    let open = raw.find('[')?;
+    let (start, end) = match syntax {
+        LinkSyntax::Wikilink => {
+            let start = open + 2;
+            let inner = start + raw.get(start..)?.find("]]")?;
+            (
+                start,
+                start + raw[start..inner].find(['#', '|']).unwrap_or(inner - start),
+            )
+        }
+        LinkSyntax::Markdown => {
+            let (_, (start, end), _) = markdown_parts(&raw[open..])?;
+            let end = raw[open + start..open + end]
+                .find('#')
+                .map_or(open + end, |anchor| open + start + anchor);
+            (open + start, end)
+        }
+    };
+    // Wikilink targets are trimmed when parsed
+    let target = &raw[start..end];
+    let leading = target.len() - target.trim_start().len();
+    Some((start + leading, start + target.trim_end().len()))
+}
+
+/// True for URLs with a scheme (`https:`, `mailto:`, `obsidian:`) and
+/// protocol-relative ones.
+fn is_external(destination: &str) -> bool {
+    if destination.starts_with("//") {
+        return true;
+    }
+    match destination.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// Decodes `%XX` escapes. Malformed escapes are kept as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Resolves link targets to notes.
+#[derive(Clone, Debug, Default)]
+pub struct Resolver {
+    /// File name without `.md` -> first note with that name, by path.
+    names: HashMap<String, String>,
+    /// The same, lowercased.
+    names_lower: HashMap<String, String>,
+    paths: HashSet<String>,
+    /// Lowercased path -> first note with it.
+    paths_lower: HashMap<String, String>,
+}
+
+impl Resolver {
+    /// Builds a resolver for `notes`, relative paths in sorted order, so
+    /// the first of several notes with the same name wins as in the editor.
+    pub fn new<'a>(notes: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut resolver = Resolver::default();
+        for path in notes {
+            let name = Path::new(path)
+                .file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_default();
+            let name = name.strip_suffix(".md").unwrap_or(&name);
+            resolver
+                .names
+                .entry(name.to_string())
+                .or_insert_with(|| path.clone());
+            resolver
+                .names_lower
+                .entry(name.to_lowercase())
+                .or_insert_with(|| path.clone());
+            resolver
+                .paths_lower
+                .entry(path.to_lowercase())
+                .or_insert_with(|| path.clone());
+            resolver.paths.insert(path.clone());
+        }
+        resolver
+    }
+
+    /// Resolves a wikilink target (without anchor) to a note, in the
+    /// editor's order:
+    ///
+    /// 1. Exact match on file name without `.md`
+    /// 2. Case-insensitive match on file name
+    /// 3. If the target has a `/`, match on relative path, exact and then
+    ///    case-insensitive
+    pub fn resolve_note(&self, target: &str) -> Option<&String> {
+        let target = target.trim();
+        if target.is_empty() {
+            return None;
+        }
+        if let Some(path) = self.names.get(target) {
+            return Some(path);
+        }
+        if let Some(path) = self.names_lower.get(&target.to_lowercase()) {
+            return Some(path);
+        }
+        if target.contains('/') {
+            let with_ext = if target.ends_with(".md") {
+                target.to_string()
+            } else {
+                format!("{}.md", target)
+            };
+            return self
+                .paths
+                .get(&with_ext)
+                .or_else(|| self.paths_lower.get(&with_ext.to_lowercase()));
+        }
+        None
+    }
+
+    /// Resolves `link`, found in the note `source`, to the relative path of
+    /// the note or other file it leads to. Returns None if it is broken.
+    ///
+    /// Wikilinks resolve by name anywhere in the vault ([`Self::resolve_note`]);
+    /// markdown links are paths relative to `source`, falling back to the
+    /// vault root. A target that is not a note (an image, a PDF) resolves if
+    /// the file exists, looked up like a markdown link.
+    pub fn resolve(&self, root: &Path, source: &str, link: &Link) -> Option<String> {
+        match self.resolve_to_note(source, link) {
+            Some(note) => Some(note.to_string()),
+            None => resolve_file(root, source, link),
+        }
+    }
+
+    /// Like [`Self::resolve`], but only to notes, so it never touches the
+    /// disk.
+    pub fn resolve_to_note<'a>(&'a self, source: &'a str, link: &Link) -> Option<&'a str> {
+        if link.target.is_empty() {
+            return (!source.is_empty()).then_some(source);
+        }
+        let note = match link.syntax {
+            LinkSyntax::Wikilink => self.resolve_note(&link.target),
+            LinkSyntax::Markdown => candidates(source, &link.target)
+                .into_iter()
+                .flat_map(|path| [path.clone(), format!("{}.md", path)])
+                .find_map(|path| {
+                    self.paths
+                        .get(&path)
+                        .or_else(|| self.paths_lower.get(&path.to_lowercase()))
+                }),
+        };
+        note.map(String::as_str)
+    }
+}
+
+/// Resolves `link`, found in the note `source`, to a file that is not a
+/// note (an image, a PDF) next to the source or from the root, if it exists.
+pub fn resolve_file(root: &Path, source: &str, link: &Link) -> Option<String> {
+    candidates(source, &link.target).into_iter().find(|path| {
+        !path.ends_with(".md")
+            && !super::is_hidden_path(root, &root.join(path))
+            && root.join(path).is_file()
+    })
+}
+
+/// Paths a link target may refer to, in order: relative to the note
+/// `source` (or to the vault root if it starts with `/`), then from the root,
+/// since vaults also write markdown links that way without the `/`.
+fn candidates(source: &str, target: &str) -> Vec<String> {
+    let relative = match target.strip_prefix('/') {
+        Some(from_root) => normalize(from_root),
+        None => {
+            let dir = Path::new(source)
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            normalize(&format!("{}/{}", dir, target))
+        }
+    };
+    let from_root = normalize(target).filter(|path| Some(path) != relative.as_ref());
+    relative.into_iter().chain(from_root).collect()
+}
+
+/// Resolves `.` and `..` in a `/`-separated relative path. None if it
+/// climbs out of the vault.
+pub(crate) fn normalize(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// A note's links and tags, with the content hash they were parsed from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct NoteLinks {
+    hash: String,
+    links: Vec<Link>,
+    /// Distinct tags, as first written.
+    tags: Vec<String>,
+}
+
+/// A note parsed for the link index, ready to be applied to it.
+pub struct ParsedNote {
+    pub path: String,
+    pub hash: String,
+    pub links: Vec<Link>,
+    pub tags: Vec<String>,
+}
+
+impl ParsedNote {
+    pub fn new(path: String, hash: String, text: &str) -> Self {
+        ParsedNote {
+            path,
+            hash,
+            links: parse(text),
+            tags: tags::note_tags(text),
+        }
+    }
+}
+
+/// Outgoing links of every note in a vault, and the backlinks they make.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LinkIndex {
+    notes: BTreeMap<String, NoteLinks>,
+    /// Rebuilt whenever notes come or go, since that can change where any
+    /// link leads.
+    #[serde(skip)]
+    resolver: Resolver,
+    /// Note -> notes linking to it. Derived from `notes` and kept up to date
+    /// with it, so it is not stored.
+    #[serde(skip)]
+    backlinks: HashMap<String, BTreeSet<String>>,
+}
+
+impl LinkIndex {
+    fn file_path(root: &Path) -> std::path::PathBuf {
+        root.join(SLATE_DIR).join(LINKS_FILE)
+    }
+
+    /// Loads the stored link index. Returns None if there is none or it
+    /// cannot be used.
+    pub fn load(root: &Path) -> Option<Self> {
+        let path = Self::file_path(root);
+        let bytes = fs::read(&path).ok()?;
+        match bincode::deserialize::<(u32, LinkIndex)>(&bytes) {
+            Ok((LINKS_VERSION, mut index)) => {
+                index.rebuild();
+                debug!(note_count = index.notes.len(), "Loaded link index");
+                Some(index)
+            }
+            Ok((version, _)) => {
+                info!(version, "Discarding link index from another version");
+                None
+            }
+            Err(e) => {
+                warn!(path = ?path, error = %e, "Discarding unreadable link index");
+                None
+            }
+        }
+    }
+
+    /// Writes the link index to `.slate/links.bin`.
+    pub fn save(&self, root: &Path) -> Result<(), SlateError> {
+        let bytes = bincode::serialize(&(LINKS_VERSION, self))
+            .map_err(|e| SlateError::internal(format!("Failed to serialize link index: {}", e)))?;
+        write_slate_file(root, LINKS_FILE, &bytes)
+    }
+
+    /// Builds a link index from scratch by reading every note in the vault,
+    /// for use without an open vault.
+    pub fn build(root: &Path) -> Self {
+        let parsed = super::markdown_files(root)
+            .filter_map(|file| {
+                let path = file.strip_prefix(root).ok()?;
+                let bytes = fs::read(&file).ok()?;
+                Some(ParsedNote::new(
+                    path.to_string_lossy().into_owned(),
+                    hash_bytes(&bytes),
+                    &String::from_utf8_lossy(&bytes),
+                ))
+            })
+            .collect();
+        let mut index = LinkIndex::default();
+        index.apply(&[], parsed);
+        index
+    }
+
+    /// Compares the parsed content hashes with the vault index.
+    pub fn stale(&self, index: &VaultIndex) -> Stale {
+        index.stale(self.notes.keys(), |path| {
+            self.notes.get(path).map(|note| note.hash.as_str())
+        })
+    }
+
+    /// Applies a batch of changes: `removed` notes are dropped and `parsed`
+    /// notes replace whatever was there.
+    pub fn apply(&mut self, removed: &[String], parsed: Vec<ParsedNote>) {
+        let paths_changed = removed.iter().any(|path| self.notes.contains_key(path))
+            || parsed
+                .iter()
+                .any(|note| !self.notes.contains_key(&note.path));
+        if paths_changed {
+            for path in removed {
+                self.notes.remove(path);
+            }
+            for note in parsed {
+                self.insert(note);
+            }
+            self.rebuild();
+            return;
+        }
+
+        // Same notes, so links elsewhere still lead where they did
+        for note in parsed {
+            let path = note.path.clone();
+            self.set_backlinks(&path, false);
+            self.insert(note);
+            self.set_backlinks(&path, true);
+        }
+    }
+
+    fn insert(&mut self, note: ParsedNote) {
+        let ParsedNote {
+            path,
+            hash,
+            links,
+            tags,
+        } = note;
+        self.notes.insert(path, NoteLinks { hash, links, tags });
+    }
+
+    fn rebuild(&mut self) {
+        self.resolver = Resolver::new(self.notes.keys());
+        self.backlinks.clear();
+        let sources: Vec<String> = self.notes.keys().cloned().collect();
+        for source in sources {
+            self.set_backlinks(&source, true);
+        }
+    }
+
+    /// Adds or takes out the backlinks made by `source`'s links.
+    fn set_backlinks(&mut self, source: &str, linked: bool) {
+        let Some(note) = self.notes.get(source) else {
+            return;
+        };
+        let targets: BTreeSet<&str> = note
+            .links
+            .iter()
+            .filter_map(|link| self.resolver.resolve_to_note(source, link))
+            .filter(|&target| target != source)
+            .collect();
+        for target in targets {
+            if linked {
+                self.backlinks
+                    .entry(target.to_string())
+                    .or_default()
+                    .insert(source.to_string());
+            } else if let Some(sources) = self.backlinks.get_mut(target) {
+                sources.remove(source);
+                if sources.is_empty() {
+                    self.backlinks.remove(target);
+                }
+            }
+        }
+    }
+
+    /// Notes linking to the note at `path`, by relative path.
+    pub fn backlinks(&self, path: &str) -> impl Iterator<Item = &String> {
+        self.backlinks.get(path).into_iter().flatten()
+    }
+
+    pub fn resolver(&self) -> &Resolver {
+        &self.resolver
+    }
+
+    /// Links of one note, in order.
+    pub fn links(&self, path: &str) -> Option<&[Link]> {
+        self.notes.get(path).map(|note| note.links.as_slice())
+    }
+
+    /// Distinct tags of one note, as first written.
+    pub fn tags(&self, path: &str) -> &[String] {
+        self.notes
+            .get(path)
+            .map(|note| note.tags.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Every note and its links, by relative path.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &[Link])> {
+        self.notes
+            .iter()
+            .map(|(path, note)| (path, note.links.as_slice()))
+    }
+}
+
+/// Brings an open vault's link index in line with its vault index, parsing
+/// only notes whose content hash changed, without holding the registry lock.
+pub(crate) fn refresh(registry: &VaultRegistry, vault: &VaultHandle) {
+    let root = vault.root();
+    if registry.with_open(vault, |open| open.links.is_none()) == Some(true) {
+        let loaded = LinkIndex::load(root).unwrap_or_default();
+        registry.with_open(vault, |open| {
+            open.links.get_or_insert(loaded);
+        });
+    }
+
+    let Some(mut stale) = registry
+        .with_open(vault, |open| {
+            Some(open.links.as_ref()?.stale(open.index.as_ref()?))
+        })
+        .flatten()
+    else {
+        return;
+    };
+    if stale.is_empty() {
+        return;
+    }
+
+    let start = Instant::now();
+    let mut parsed = Vec::with_capacity(stale.changed.len());
+    for path in std::mem::take(&mut stale.changed) {
+        match fs::read(root.join(&path)) {
+            Ok(bytes) => {
+                let hash = hash_bytes(&bytes);
+                parsed.push(ParsedNote::new(
+                    path,
+                    hash,
+                    &String::from_utf8_lossy(&bytes),
+                ));
+            }
+            // Gone since the vault index saw it; the next refresh drops it
+            Err(_) => stale.removed.push(path),
+        }
+    }
+
+    let count = parsed.len();
+    registry.with_open(vault, |open| {
+        let Some(links) = open.links.as_mut() else {
+            return;
+        };
+        links.apply(&stale.removed, parsed);
+        if let Err(e) = links.save(root) {
+            warn!(error = %e, "Failed to persist link index");
+        }
+    });
+
+    info!(
+        parsed = count,
+        removed = stale.removed.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Updated link index"
+    );
+}
+
+/// Where a wikilink target leads.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkResolution {
+    pub target: String,
+    pub file: Option<FileEntry>,
+    pub exists: bool,
+    pub anchor: Option<Anchor>,
+}
+
+/// Resolves what is inside a wikilink's brackets (`Page`, `folder/Page`,
+/// `Page#Heading`, `Page#^block|alias`), as the note `from` would.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn resolve_link(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    target: String,
+    from: Option<String>,
+) -> Result<LinkResolution, SlateError> {
+    let (page, alias) = match target.split_once('|') {
+        Some((page, alias)) => (page, Some(alias)),
+        None => (target.as_str(), None),
+    };
+    let link = link(LinkSyntax::Wikilink, page, alias);
+    let source = from.unwrap_or_default();
+
+    let resolved = registry
+        .with_open(&vault, |open| match (&open.links, &open.index) {
+            (Some(links), _) => Some(links.resolver().resolve(vault.root(), &source, &link)),
+            // Before the link index is built, resolve against the file list
+            (None, Some(index)) => Some(Resolver::new(index.iter().map(|(path, _)| path)).resolve(
+                vault.root(),
+                &source,
+                &link,
+            )),
+            (None, None) => None,
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Vault index"))?;
+
+    let file = resolved
+        .and_then(|path| vault.join(&path).ok())
+        .map(|path| FileEntry::new(vault.root(), &path));
+    Ok(LinkResolution {
+        target,
+        exists: file.is_some(),
+        file,
+        anchor: link.anchor,
+    })
+}
+
+/// A link and where it leads.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutgoingLink {
+    #[serde(flatten)]
+    pub link: Link,
+    /// Relative path of the note or file it leads to; None if it is broken.
+    pub resolved: Option<String>,
+}
+
+/// Outgoing links of one note, or of every note if `path` is None, keyed by
+/// relative path.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn get_outgoing_links(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    path: Option<String>,
+) -> Result<BTreeMap<String, Vec<OutgoingLink>>, SlateError> {
+    let mut outgoing: BTreeMap<String, Vec<OutgoingLink>> = registry
+        .with_open(&vault, |open| {
+            let index = open.links.as_ref()?;
+            let resolve = |source: &String, links: &[Link]| {
+                let resolved = links
+                    .iter()
+                    .map(|link| OutgoingLink {
+                        resolved: index
+                            .resolver()
+                            .resolve_to_note(source, link)
+                            .map(str::to_string),
+                        link: link.clone(),
+                    })
+                    .collect();
+                (source.clone(), resolved)
+            };
+            Some(match &path {
+                Some(path) => index
+                    .links(path)
+                    .map(|links| resolve(path, links))
+                    .into_iter()
+                    .collect(),
+                None => index
+                    .iter()
+                    .map(|(source, links)| resolve(source, links))
+                    .collect(),
+            })
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Link index"))?;
+
+    // Attachments are looked for without holding the registry lock
+    for (source, links) in &mut outgoing {
+        for link in links.iter_mut().filter(|link| link.resolved.is_none()) {
+            link.resolved = resolve_file(vault.root(), source, &link.link);
+        }
+    }
+    Ok(outgoing)
+}
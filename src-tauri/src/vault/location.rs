@@ -0,0 +1,236 @@
+//! Vault location - resolving, validating and persisting the vault root
+
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tracing::{info, warn};
+
+/// Directory (under the platform config dir) holding Slate's settings.
+/// Matches the Tauri app identifier so it lines up with `app_config_dir`.
+const CONFIG_DIR_NAME: &str = "dev.srid.slate";
+const CONFIG_FILE_NAME: &str = "vault.json";
+
+/// A resolved, canonical and validated vault root.
+///
+/// Commands take this instead of raw path strings. It crosses the IPC
+/// boundary as the canonical path string and is re-validated whenever one
+/// is deserialized, so a handle always refers to an existing directory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VaultHandle {
+    root: PathBuf,
+}
+
+impl VaultHandle {
+    /// Resolves user input into a vault handle.
+    ///
+    /// Accepts absolute paths, `~` and `$VAR`/`${VAR}` expansion. `~` and
+    /// relative paths are taken relative to `home`; the caller supplies it
+    /// (usually `dirs::home_dir()`), and without one they are rejected.
+    pub fn resolve(input: &str, home: Option<&Path>) -> Result<Self, SlateError> {
+        let expanded = expand_path(input, home)?;
+        Self::from_path(&expanded)
+    }
+
+    /// Canonicalizes and validates an already expanded path.
+    pub fn from_path(path: &Path) -> Result<Self, SlateError> {
+        let root = fs::canonicalize(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => SlateError::VaultNotFound {
+                path: path.to_string_lossy().to_string(),
+                message: format!("Vault path does not exist: {:?}", path),
+            },
+            _ => SlateError::io("Failed to resolve vault path", path, e),
+        })?;
+
+        if !root.is_dir() {
+            return Err(SlateError::NotADirectory {
+                path: root.to_string_lossy().to_string(),
+                message: format!("Vault path is not a directory: {:?}", root),
+            });
+        }
+        fs::read_dir(&root).map_err(|e| SlateError::io("Failed to read vault", &root, e))?;
+
+        Ok(VaultHandle { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Joins a vault-relative path onto the root. Only plain components are
+    /// accepted: absolute paths, `.` and `..` are rejected, which keeps the
+    /// result inside the vault and the relative path in canonical form.
+    pub fn join(&self, relative_path: &str) -> Result<PathBuf, SlateError> {
+        let relative = Path::new(relative_path);
+        let contained = !relative_path.is_empty()
+            && relative
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)));
+        if !contained {
+            return Err(SlateError::invalid_path(
+                relative_path,
+                format!("Path is outside the vault: {}", relative_path),
+            ));
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// Display name of the vault (its directory name).
+    pub fn name(&self) -> String {
+        self.root
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.root.to_string_lossy().to_string())
+    }
+}
+
+impl TryFrom<String> for VaultHandle {
+    type Error = SlateError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        // Handles cross IPC as canonical paths; there is nothing to expand
+        let path = Path::new(&value);
+        if !path.is_absolute() {
+            return Err(SlateError::invalid_path(
+                &value,
+                format!("Vault path is not absolute: {}", value),
+            ));
+        }
+        Self::from_path(path)
+    }
+}
+
+impl From<VaultHandle> for String {
+    fn from(handle: VaultHandle) -> Self {
+        handle.root.to_string_lossy().to_string()
+    }
+}
+
+/// Expands `~`, `$VAR` and `${VAR}` in a user-supplied path.
+/// Relative results are anchored at `home`.
+pub fn expand_path(input: &str, home: Option<&Path>) -> Result<PathBuf, SlateError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(SlateError::invalid_path(input, "Vault path is empty"));
+    }
+
+    let expanded = expand_env_vars(input)?;
+    let home_dir = || {
+        home.map(Path::to_path_buf).ok_or_else(|| {
+            SlateError::invalid_path(input, format!("No home directory to resolve {}", input))
+        })
+    };
+
+    let path = if expanded == "~" {
+        home_dir()?
+    } else if let Some(rest) = expanded
+        .strip_prefix("~/")
+        .or_else(|| expanded.strip_prefix("~\\"))
+    {
+        home_dir()?.join(rest)
+    } else {
+        PathBuf::from(&expanded)
+    };
+
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Ok(home_dir()?.join(path))
+    }
+}
+
+/// Substitutes `$VAR` and `${VAR}` references. Unset variables are an error
+/// rather than silently expanding to nothing.
+fn expand_env_vars(input: &str) -> Result<String, SlateError> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            let close = braced.find('}').ok_or_else(|| {
+                SlateError::invalid_path(input, format!("Unterminated variable in path: {}", input))
+            })?;
+            (&braced[..close], close + 2)
+        } else {
+            let len = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..len], len)
+        };
+
+        if name.is_empty() {
+            out.push('$');
+        } else {
+            let value = std::env::var(name).map_err(|_| {
+                SlateError::invalid_path(
+                    input,
+                    format!("Environment variable ${} is not set", name),
+                )
+            })?;
+            out.push_str(&value);
+        }
+        rest = &after[consumed..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Persisted vault selection, stored as JSON in the config directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VaultConfig {
+    /// Most recently opened vault in any window.
+    pub last_vault: Option<PathBuf>,
+    /// Every vault the user has opened and not forgotten, in insertion order.
+    pub known_vaults: Vec<PathBuf>,
+    /// Last vault opened per window label.
+    pub last_used: BTreeMap<String, PathBuf>,
+}
+
+impl VaultConfig {
+    /// Where the config lives: `vault.json` in Slate's config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join(CONFIG_DIR_NAME).join(CONFIG_FILE_NAME))
+    }
+
+    /// Loads the config at `path`, falling back to defaults if it is missing
+    /// or corrupt.
+    pub fn load_from(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                warn!(path = ?path, error = %e, "Ignoring unreadable vault config");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), SlateError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| SlateError::io("Failed to create config directory", parent, e))?;
+        }
+        let text = serde_json::to_string_pretty(self).map_err(|e| {
+            SlateError::internal(format!("Failed to serialize vault config: {}", e))
+        })?;
+        fs::write(path, text)
+            .map_err(|e| SlateError::io("Failed to write vault config", path, e))?;
+        info!(path = ?path, "Saved vault config");
+        Ok(())
+    }
+
+    /// The last used vault for `window` (falling back to the last vault used
+    /// anywhere), if it still resolves to a valid directory.
+    pub fn last_vault(&self, window: &str) -> Option<VaultHandle> {
+        let path = self.last_used.get(window).or(self.last_vault.as_ref())?;
+        VaultHandle::from_path(path)
+            .map_err(|e| warn!(error = %e, "Last used vault is no longer available"))
+            .ok()
+    }
+}
@@ -0,0 +1,85 @@
+//! Markdown lines - headings, list items and tasks, read the same way by
+//! tasks, search and backlinks. Lines are given without their line ending.
+//! Whether a line is inside a code block is left to the caller.
+
+/// An ATX heading's level and text, without any closing `#`s.
+pub(crate) fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// The indent of a list item: `- item`, `* item`, `+ item`, `1. item` or
+/// `1) item`, tasks included.
+pub(crate) fn list_item(line: &str) -> Option<usize> {
+    list_marker(line).map(|(indent, _)| indent)
+}
+
+/// A `- [ ] text` line.
+pub(crate) struct TaskItem<'a> {
+    pub indent: usize,
+    /// What is inside the brackets.
+    pub status: char,
+    pub text: &'a str,
+    /// Byte offset of the status character in the line.
+    pub status_at: usize,
+}
+
+impl TaskItem<'_> {
+    /// True for any status but a space.
+    pub fn checked(&self) -> bool {
+        self.status != ' '
+    }
+}
+
+/// Reads a `- [ ] text` line: any list marker, any single status character.
+pub(crate) fn task_item(line: &str) -> Option<TaskItem<'_>> {
+    let (indent, after_marker) = list_marker(line)?;
+    let checkbox = after_marker
+        .strip_prefix([' ', '\t'])?
+        .trim_start_matches(' ');
+    let inner = checkbox.strip_prefix('[')?;
+    let status = inner.chars().next()?;
+    let after = inner[status.len_utf8()..].strip_prefix(']')?;
+    if !after.is_empty() && !after.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    Some(TaskItem {
+        indent,
+        status,
+        text: after.trim(),
+        status_at: line.len() - inner.len(),
+    })
+}
+
+/// A list item's indent and what follows its marker, which is empty or
+/// starts with whitespace.
+fn list_marker(line: &str) -> Option<(usize, &str)> {
+    let rest = line.trim_start_matches([' ', '\t']);
+    let after = match rest.strip_prefix(['-', '*', '+']) {
+        Some(after) => after,
+        None => {
+            let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0 {
+                return None;
+            }
+            rest[digits..].strip_prefix(['.', ')'])?
+        }
+    };
+    if !after.is_empty() && !after.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some((indent_width(&line[..line.len() - rest.len()]), after))
+}
+
+/// Width of leading whitespace, tabs counting as four spaces.
+fn indent_width(indent: &str) -> usize {
+    indent.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
@@ -0,0 +1,307 @@
+//! Three-way merge - reconciles two edits of a note against the version they
+//! both started from, line by line and then word by word
+
+use super::note::{self, normalize_newlines, NoteVersion};
+use super::VaultHandle;
+use crate::error::SlateError;
+use serde::Serialize;
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use tracing::{info, instrument};
+
+/// A stretch of the merged text.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum MergeRegion {
+    /// Text both sides agree on, or that only one side changed.
+    Resolved { text: String },
+    /// Text both sides changed in different ways.
+    Conflict {
+        base: String,
+        ours: String,
+        theirs: String,
+    },
+}
+
+/// Result of a three-way merge.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(
+    tag = "status",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum MergeOutcome {
+    /// Every change merged without overlap.
+    Clean { content: String },
+    /// Some changes overlap. `content` is the merge with git-style conflict
+    /// markers around each conflicting region.
+    Conflicted {
+        content: String,
+        regions: Vec<MergeRegion>,
+    },
+}
+
+impl MergeOutcome {
+    pub fn is_clean(&self) -> bool {
+        matches!(self, MergeOutcome::Clean { .. })
+    }
+
+    /// The merged text, with conflict markers if the merge is not clean.
+    pub fn content(&self) -> &str {
+        match self {
+            MergeOutcome::Clean { content } | MergeOutcome::Conflicted { content, .. } => content,
+        }
+    }
+}
+
+/// Merges `ours` and `theirs`, two edits of `base`.
+///
+/// Lines changed on only one side are taken from that side. Where both
+/// sides changed the same lines, the lines are merged again at word level;
+/// only if that overlaps too is the region reported as a conflict.
+pub fn merge(base: &str, ours: &str, theirs: &str) -> MergeOutcome {
+    let mut regions: Vec<MergeRegion> = Vec::new();
+    for region in merge_tokens(&lines(base), &lines(ours), &lines(theirs)) {
+        let region = match region {
+            MergeRegion::Conflict { base, ours, theirs } => merge_words(&base, &ours, &theirs)
+                .map(|text| MergeRegion::Resolved { text })
+                .unwrap_or(MergeRegion::Conflict { base, ours, theirs }),
+            resolved => resolved,
+        };
+        // Coalesce so resolved text between conflicts comes out in one piece
+        match (regions.last_mut(), region) {
+            (Some(MergeRegion::Resolved { text }), MergeRegion::Resolved { text: more }) => {
+                text.push_str(&more)
+            }
+            (_, region) => regions.push(region),
+        }
+    }
+
+    let conflicted = regions
+        .iter()
+        .any(|r| matches!(r, MergeRegion::Conflict { .. }));
+    if conflicted {
+        MergeOutcome::Conflicted {
+            content: with_markers(&regions),
+            regions,
+        }
+    } else {
+        MergeOutcome::Clean {
+            content: with_markers(&regions),
+        }
+    }
+}
+
+/// Merges two versions of a note with no known common ancestor, such as a
+/// note and a sync client's conflicted copy of it.
+///
+/// The lines both versions share stand in for the base, so text only one
+/// side has is kept; only where both sides have different text in the same
+/// place is there a conflict.
+pub fn merge_unrelated(ours: &str, theirs: &str) -> MergeOutcome {
+    let (ours_lines, theirs_lines) = (lines(ours), lines(theirs));
+    let base: String = capture_diff_slices(Algorithm::Myers, &ours_lines, &theirs_lines)
+        .into_iter()
+        .filter_map(|op| match op {
+            DiffOp::Equal { old_index, len, .. } => {
+                Some(ours_lines[old_index..old_index + len].concat())
+            }
+            _ => None,
+        })
+        .collect();
+    merge(&base, ours, theirs)
+}
+
+/// Merges a region both sides changed at word granularity. None if the
+/// word-level changes overlap as well.
+fn merge_words(base: &str, ours: &str, theirs: &str) -> Option<String> {
+    let mut merged = String::new();
+    for region in merge_tokens(&words(base), &words(ours), &words(theirs)) {
+        match region {
+            MergeRegion::Resolved { text } => merged.push_str(&text),
+            MergeRegion::Conflict { .. } => return None,
+        }
+    }
+    Some(merged)
+}
+
+fn with_markers(regions: &[MergeRegion]) -> String {
+    let mut out = String::new();
+    for region in regions {
+        match region {
+            MergeRegion::Resolved { text } => out.push_str(text),
+            MergeRegion::Conflict { ours, theirs, .. } => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str("<<<<<<< ours\n");
+                push_line_block(&mut out, ours);
+                out.push_str("=======\n");
+                push_line_block(&mut out, theirs);
+                out.push_str(">>>>>>> theirs\n");
+            }
+        }
+    }
+    out
+}
+
+fn push_line_block(out: &mut String, text: &str) {
+    out.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Lines, each keeping its trailing newline.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Runs of word characters, runs of whitespace, and single other characters.
+fn words(text: &str) -> Vec<&str> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<Class> = None;
+    for (i, c) in text.char_indices() {
+        let current = class(c);
+        let split = match &prev {
+            Some(p) => *p != current || current == Class::Other,
+            None => false,
+        };
+        if split {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        prev = Some(current);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// diff3 over token sequences. Stable runs (unchanged on both sides) and
+/// regions changed on only one side come out resolved; regions both sides
+/// changed differently come out as conflicts.
+fn merge_tokens(base: &[&str], ours: &[&str], theirs: &[&str]) -> Vec<MergeRegion> {
+    let in_ours = matching(base, ours);
+    let in_theirs = matching(base, theirs);
+    let (mut b, mut o, mut t) = (0, 0, 0);
+    let mut regions = Vec::new();
+
+    loop {
+        let mut n = 0;
+        while b + n < base.len() && in_ours[b + n] == Some(o + n) && in_theirs[b + n] == Some(t + n)
+        {
+            n += 1;
+        }
+        if n > 0 {
+            regions.push(MergeRegion::Resolved {
+                text: base[b..b + n].concat(),
+            });
+            (b, o, t) = (b + n, o + n, t + n);
+            continue;
+        }
+
+        // Next base token both sides kept; everything before it changed
+        let (nb, no, nt) = (b..base.len())
+            .find_map(|i| Some((i, in_ours[i]?, in_theirs[i]?)))
+            .unwrap_or((base.len(), ours.len(), theirs.len()));
+        if (nb, no, nt) == (b, o, t) {
+            break;
+        }
+
+        let (base_part, ours_part, theirs_part) = (&base[b..nb], &ours[o..no], &theirs[t..nt]);
+        let text = if ours_part == base_part {
+            Some(theirs_part)
+        } else if theirs_part == base_part || ours_part == theirs_part {
+            Some(ours_part)
+        } else {
+            None
+        };
+        regions.push(match text {
+            Some(text) => MergeRegion::Resolved {
+                text: text.concat(),
+            },
+            None => MergeRegion::Conflict {
+                base: base_part.concat(),
+                ours: ours_part.concat(),
+                theirs: theirs_part.concat(),
+            },
+        });
+        (b, o, t) = (nb, no, nt);
+    }
+
+    regions
+}
+
+/// For each base token, the index of the token it is matched with in
+/// `other` by a minimal diff, if it survives there.
+fn matching(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut matched = vec![None; base.len()];
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = op
+        {
+            for k in 0..len {
+                matched[old_index + k] = Some(new_index + k);
+            }
+        }
+    }
+    matched
+}
+
+/// A merge of the editor's text with the note as it is now on disk.
+#[derive(Clone, Debug, Serialize)]
+pub struct NoteMerge {
+    #[serde(flatten)]
+    pub outcome: MergeOutcome,
+    /// The on-disk version merged against. Save the result with this as the
+    /// base, so a further change on disk is caught again.
+    pub theirs: NoteVersion,
+}
+
+/// Merges `ours` (the editor's text, loaded as `base`) with the current
+/// content of the note on disk.
+#[tauri::command]
+#[instrument(skip(vault, base, ours))]
+pub fn merge_note(
+    vault: VaultHandle,
+    path: String,
+    base: String,
+    ours: String,
+) -> Result<NoteMerge, SlateError> {
+    let theirs = note::read(&vault.join(&path)?)?;
+    let outcome = merge(
+        &normalize_newlines(&base),
+        &normalize_newlines(&ours),
+        &theirs.content,
+    );
+    info!(clean = outcome.is_clean(), "Merged note");
+
+    Ok(NoteMerge {
+        outcome,
+        theirs: NoteVersion {
+            mtime: theirs.mtime,
+            hash: theirs.hash,
+        },
+    })
+}
@@ -0,0 +1,245 @@
+//! Note I/O - reading notes and writing them atomically, so a crash or a
+//! sync client never sees a half-written file
+
+use super::index::{hash_bytes, mtime_ms, IndexEntry};
+use super::{VaultHandle, VaultRegistry};
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tauri::State;
+use tracing::{info, instrument, warn};
+
+/// A note's text together with the on-disk version it was read from.
+#[derive(Clone, Debug, Serialize)]
+pub struct NoteContent {
+    /// Text with line endings normalized to `\n`.
+    pub content: String,
+    /// Modification time in milliseconds since the Unix epoch.
+    pub mtime: u64,
+    /// Hex-encoded BLAKE3 hash of the bytes on disk.
+    pub hash: String,
+}
+
+/// The on-disk version of a note after a write.
+#[derive(Clone, Debug, Serialize)]
+pub struct NoteVersion {
+    pub mtime: u64,
+    pub hash: String,
+}
+
+/// The version of a note the editor loaded, sent back with a save so the
+/// write can be refused if the file changed underneath it. Either field may
+/// be omitted; when both are present the hash decides.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct BaseVersion {
+    pub hash: Option<String>,
+    pub mtime: Option<u64>,
+}
+
+impl BaseVersion {
+    fn matches(&self, current: &NoteContent) -> bool {
+        match (&self.hash, self.mtime) {
+            (Some(hash), _) => *hash == current.hash,
+            (None, Some(mtime)) => mtime == current.mtime,
+            (None, None) => true,
+        }
+    }
+}
+
+/// Checks that the note at `path` is still at `base`. On a mismatch, returns
+/// the current disk state as the inner error (None if the note is gone). A
+/// note that already holds exactly `ours` is never a conflict, since nothing
+/// would be lost.
+///
+/// Fails if the note is there but cannot be read, so that is never taken
+/// for a deleted note.
+pub fn check_base(
+    path: &Path,
+    base: &BaseVersion,
+    ours: &str,
+) -> Result<Result<(), Option<NoteContent>>, SlateError> {
+    let current = match read(path) {
+        Ok(current) => current,
+        Err(SlateError::NotFound { .. }) => return Ok(Err(None)),
+        Err(e) => return Err(e),
+    };
+
+    if base.matches(&current) || current.content == normalize_newlines(ours) {
+        Ok(Ok(()))
+    } else {
+        Ok(Err(Some(current)))
+    }
+}
+
+/// Line ending convention of a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// The dominant line ending of `text` (LF when there are no line breaks).
+    pub fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// Rewrites every line break in `text` to this convention.
+    pub fn apply(self, text: &str) -> String {
+        let normalized = normalize_newlines(text);
+        match self {
+            LineEnding::Lf => normalized,
+            LineEnding::CrLf => normalized.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Converts `\r\n` line breaks to `\n`.
+pub fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n")
+}
+
+/// Reads a note from disk.
+pub fn read(path: &Path) -> Result<NoteContent, SlateError> {
+    let bytes = fs::read(path).map_err(|e| SlateError::io("Failed to read", path, e))?;
+    let meta = fs::metadata(path).map_err(|e| SlateError::io("Failed to stat", path, e))?;
+    let hash = hash_bytes(&bytes);
+    let text = String::from_utf8(bytes).map_err(|_| SlateError::InvalidEncoding {
+        path: path.to_string_lossy().to_string(),
+        message: format!("Note is not valid UTF-8: {:?}", path),
+    })?;
+
+    Ok(NoteContent {
+        content: normalize_newlines(&text),
+        mtime: mtime_ms(&meta),
+        hash,
+    })
+}
+
+/// Writes `content` to `path` atomically.
+///
+/// The text goes to a temporary file in the same directory, which is synced
+/// and then renamed over the original. An existing file's permissions and
+/// line endings are kept. `before_commit` is called with the final state of
+/// the file just before the rename makes it visible.
+pub fn write_atomic(
+    path: &Path,
+    content: &str,
+    before_commit: impl FnOnce(&IndexEntry),
+) -> Result<IndexEntry, SlateError> {
+    let dir = path.parent().ok_or_else(|| {
+        SlateError::invalid_path(
+            &path.to_string_lossy(),
+            format!("Invalid note path: {:?}", path),
+        )
+    })?;
+    fs::create_dir_all(dir).map_err(|e| SlateError::io("Failed to create", dir, e))?;
+
+    let existing = fs::metadata(path).ok();
+    let line_ending = match existing {
+        Some(_) => fs::read_to_string(path)
+            .map(|text| LineEnding::detect(&text))
+            .unwrap_or(LineEnding::Lf),
+        None => LineEnding::Lf,
+    };
+    let bytes = line_ending.apply(content).into_bytes();
+
+    // Hidden temp name, so neither the scanner nor the watcher picks it up
+    let mut tmp = tempfile::Builder::new()
+        .prefix(".slate-")
+        .suffix(".tmp")
+        .tempfile_in(dir)
+        .map_err(|e| SlateError::io("Failed to create temp file in", dir, e))?;
+    tmp.write_all(&bytes)
+        .map_err(|e| SlateError::io("Failed to write", path, e))?;
+    if let Some(meta) = &existing {
+        fs::set_permissions(tmp.path(), meta.permissions())
+            .map_err(|e| SlateError::io("Failed to copy permissions to", path, e))?;
+    }
+    tmp.as_file()
+        .sync_all()
+        .map_err(|e| SlateError::io("Failed to sync", path, e))?;
+
+    // The rename keeps the temp file's mtime, so this is the final state
+    let meta = tmp
+        .as_file()
+        .metadata()
+        .map_err(|e| SlateError::io("Failed to stat", path, e))?;
+    let entry = IndexEntry {
+        mtime: mtime_ms(&meta),
+        size: meta.len(),
+        hash: hash_bytes(&bytes),
+    };
+    before_commit(&entry);
+
+    tmp.persist(path)
+        .map_err(|e| SlateError::io("Failed to replace", path, e.error))?;
+    sync_dir(dir);
+
+    Ok(entry)
+}
+
+/// Makes the rename durable. Not possible (or needed) on every platform.
+fn sync_dir(dir: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+/// Reads a note, given its path relative to the vault root.
+#[tauri::command]
+#[instrument(skip(vault))]
+pub fn read_note(vault: VaultHandle, path: String) -> Result<NoteContent, SlateError> {
+    read(&vault.join(&path)?)
+}
+
+/// Writes a note atomically, given its path relative to the vault root.
+/// Returns the note's new on-disk version.
+///
+/// With a `base`, the write only goes ahead if the note is still at that
+/// version; otherwise it fails with `SlateError::Conflict`. Without one
+/// the note is overwritten unconditionally (new notes, "keep mine").
+#[tauri::command]
+#[instrument(skip(registry, vault, content, base), fields(len = content.len()))]
+pub fn write_note(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    path: String,
+    content: String,
+    base: Option<BaseVersion>,
+) -> Result<NoteVersion, SlateError> {
+    let full_path = vault.join(&path)?;
+
+    if let Some(base) = &base {
+        if let Err(theirs) = check_base(&full_path, base, &content)? {
+            warn!("Note changed on disk since it was loaded");
+            return Err(SlateError::Conflict {
+                message: format!("Note changed on disk since it was loaded: {}", path),
+                path,
+                ours: content,
+                theirs,
+            });
+        }
+    }
+
+    let entry = write_atomic(&full_path, &content, |entry| {
+        registry.record_write(&vault, &path, entry)
+    })?;
+    info!(hash = %entry.hash, "Saved note");
+
+    Ok(NoteVersion {
+        mtime: entry.mtime,
+        hash: entry.hash,
+    })
+}
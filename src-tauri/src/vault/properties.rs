@@ -0,0 +1,302 @@
+//! Properties - every note's frontmatter and inline fields, read into typed
+//! properties and kept in `.slate/` so they can be listed and matched
+//! without opening notes
+
+use super::fields;
+use super::frontmatter::{self, Property, PropertyValue};
+use super::index::{hash_bytes, write_slate_file, Stale, SLATE_DIR};
+use super::note::{self, BaseVersion, NoteVersion};
+use super::watcher::{emit_change, VaultChange};
+use super::{is_markdown_file, FileEntry, VaultHandle, VaultIndex, VaultRegistry};
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use tauri::{AppHandle, State};
+use tracing::{debug, info, instrument, warn};
+
+const PROPERTIES_FILE: &str = "properties.bin";
+/// Bump whenever the on-disk layout or the parser changes; older property
+/// indexes are rebuilt.
+const PROPERTIES_VERSION: u32 = 2;
+
+/// Keys whose values are other names for a note.
+const ALIAS_KEYS: [&str; 2] = ["aliases", "alias"];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct NoteProperties {
+    /// Content hash of the note the properties were read from.
+    hash: String,
+    properties: Vec<Property>,
+    /// Inline `key:: value` fields in the note's text.
+    fields: Vec<Property>,
+    aliases: Vec<String>,
+}
+
+impl NoteProperties {
+    fn new(hash: String, text: &str) -> Self {
+        let properties = frontmatter::parse(text);
+        let mut aliases = Vec::new();
+        for property in &properties {
+            if !ALIAS_KEYS.contains(&property.key.to_lowercase().as_str()) {
+                continue;
+            }
+            let values = match &property.value {
+                PropertyValue::List(items) => items.as_slice(),
+                value => std::slice::from_ref(value),
+            };
+            for value in values {
+                if let PropertyValue::Text(alias) = value {
+                    if !alias.is_empty() && !aliases.contains(alias) {
+                        aliases.push(alias.clone());
+                    }
+                }
+            }
+        }
+        NoteProperties {
+            hash,
+            properties,
+            fields: fields::parse(text),
+            aliases,
+        }
+    }
+}
+
+/// Frontmatter properties and inline fields of every note in a vault.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PropertyIndex {
+    notes: BTreeMap<String, NoteProperties>,
+}
+
+impl PropertyIndex {
+    fn file_path(root: &Path) -> std::path::PathBuf {
+        root.join(SLATE_DIR).join(PROPERTIES_FILE)
+    }
+
+    /// Loads the stored property index. Returns None if there is none or it
+    /// cannot be used.
+    pub fn load(root: &Path) -> Option<Self> {
+        let path = Self::file_path(root);
+        let bytes = fs::read(&path).ok()?;
+        match bincode::deserialize::<(u32, PropertyIndex)>(&bytes) {
+            Ok((PROPERTIES_VERSION, index)) => {
+                debug!(note_count = index.notes.len(), "Loaded property index");
+                Some(index)
+            }
+            Ok((version, _)) => {
+                info!(version, "Discarding property index from another version");
+                None
+            }
+            Err(e) => {
+                warn!(path = ?path, error = %e, "Discarding unreadable property index");
+                None
+            }
+        }
+    }
+
+    /// Writes the property index to `.slate/properties.bin`.
+    pub fn save(&self, root: &Path) -> Result<(), SlateError> {
+        let bytes = bincode::serialize(&(PROPERTIES_VERSION, self)).map_err(|e| {
+            SlateError::internal(format!("Failed to serialize property index: {}", e))
+        })?;
+        write_slate_file(root, PROPERTIES_FILE, &bytes)
+    }
+
+    /// Compares the parsed content hashes with the vault index.
+    pub fn stale(&self, index: &VaultIndex) -> Stale {
+        index.stale(self.notes.keys(), |path| {
+            self.notes.get(path).map(|note| note.hash.as_str())
+        })
+    }
+
+    /// Reads the properties of the note at `path` from its `text`, replacing
+    /// what was there.
+    pub fn insert(&mut self, path: String, hash: String, text: &str) {
+        self.notes.insert(path, NoteProperties::new(hash, text));
+    }
+
+    pub fn remove(&mut self, path: &str) {
+        self.notes.remove(path);
+    }
+
+    /// Properties of a note, in the order its frontmatter has them.
+    pub fn properties(&self, path: &str) -> Option<&[Property]> {
+        self.notes.get(path).map(|note| note.properties.as_slice())
+    }
+
+    /// Inline `key:: value` fields of a note, in the order they are written.
+    pub fn fields(&self, path: &str) -> &[Property] {
+        self.notes.get(path).map_or(&[], |note| &note.fields)
+    }
+
+    /// Other names for a note, from its `aliases` (or `alias`) property.
+    pub fn aliases(&self, path: &str) -> &[String] {
+        self.notes.get(path).map_or(&[], |note| &note.aliases)
+    }
+
+    /// Every note with its properties, by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &[Property])> {
+        self.notes
+            .iter()
+            .map(|(path, note)| (path, note.properties.as_slice()))
+    }
+
+    /// True if the properties of `path` were read from the content with
+    /// this hash.
+    fn is_current(&self, path: &str, hash: &str) -> bool {
+        self.notes.get(path).is_some_and(|note| note.hash == hash)
+    }
+}
+
+/// Brings an open vault's property index in line with its vault index,
+/// reading only notes whose content hash changed, without holding the
+/// registry lock.
+pub(crate) fn refresh(registry: &VaultRegistry, vault: &VaultHandle) {
+    let root = vault.root();
+    if registry.with_open(vault, |open| open.properties.is_none()) == Some(true) {
+        let loaded = PropertyIndex::load(root).unwrap_or_default();
+        registry.with_open(vault, |open| {
+            open.properties.get_or_insert(loaded);
+        });
+    }
+
+    let Some(mut stale) = registry
+        .with_open(vault, |open| {
+            Some(open.properties.as_ref()?.stale(open.index.as_ref()?))
+        })
+        .flatten()
+    else {
+        return;
+    };
+    if stale.is_empty() {
+        return;
+    }
+
+    let start = Instant::now();
+    let mut parsed = Vec::with_capacity(stale.changed.len());
+    for path in std::mem::take(&mut stale.changed) {
+        match fs::read(root.join(&path)) {
+            Ok(bytes) => {
+                let hash = hash_bytes(&bytes);
+                let text = note::normalize_newlines(&String::from_utf8_lossy(&bytes));
+                parsed.push((path, NoteProperties::new(hash, &text)));
+            }
+            // Gone since the vault index saw it; the next refresh drops it
+            Err(_) => stale.removed.push(path),
+        }
+    }
+
+    let count = parsed.len();
+    registry.with_open(vault, |open| {
+        let Some(properties) = open.properties.as_mut() else {
+            return;
+        };
+        for path in &stale.removed {
+            properties.remove(path);
+        }
+        properties.notes.extend(parsed);
+        if let Err(e) = properties.save(root) {
+            warn!(error = %e, "Failed to persist property index");
+        }
+    });
+
+    info!(
+        parsed = count,
+        removed = stale.removed.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Updated property index"
+    );
+}
+
+/// Frontmatter properties of a note, in the order they are written. Read
+/// from the property index when it is current, otherwise from disk.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn get_properties(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    path: String,
+) -> Result<Vec<Property>, SlateError> {
+    let full_path = vault.join(&path)?;
+    let indexed = registry
+        .with_open(&vault, |open| {
+            let hash = &open.index.as_ref()?.get(&path)?.hash;
+            let properties = open.properties.as_ref()?;
+            if !properties.is_current(&path, hash) {
+                return None;
+            }
+            properties.properties(&path).map(<[Property]>::to_vec)
+        })
+        .flatten();
+    if let Some(properties) = indexed {
+        return Ok(properties);
+    }
+
+    let note = note::read(&full_path)?;
+    Ok(frontmatter::parse(&note.content))
+}
+
+/// Sets one frontmatter property of a note, or removes it if `value` is
+/// None, leaving the rest of the frontmatter as it was written. A note
+/// without frontmatter gets a YAML block. Returns the note's new on-disk
+/// version.
+#[tauri::command]
+#[instrument(skip(app, registry, vault, value))]
+pub fn set_property(
+    app: AppHandle,
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    path: String,
+    key: String,
+    value: Option<PropertyValue>,
+) -> Result<NoteVersion, SlateError> {
+    let full_path = vault.join(&path)?;
+    if !is_markdown_file(&full_path) {
+        return Err(SlateError::not_found(
+            &path,
+            format!("Not a note: {}", path),
+        ));
+    }
+    let current = note::read(&full_path)?;
+    let content = frontmatter::set(&current.content, &key, value.as_ref())?;
+    if content == current.content {
+        return Ok(NoteVersion {
+            mtime: current.mtime,
+            hash: current.hash,
+        });
+    }
+
+    let base = BaseVersion {
+        hash: Some(current.hash),
+        mtime: None,
+    };
+    if let Err(theirs) = note::check_base(&full_path, &base, &content)? {
+        return Err(SlateError::Conflict {
+            message: format!("Note changed on disk while it was being edited: {}", path),
+            path,
+            ours: content,
+            theirs,
+        });
+    }
+    let entry = note::write_atomic(&full_path, &content, |entry| {
+        registry.record_write(&vault, &path, entry)
+    })?;
+
+    // Read back right away, rather than when the watcher sees the write
+    registry.with_open(&vault, |open| {
+        if let Some(properties) = open.properties.as_mut() {
+            properties.insert(path.clone(), entry.hash.clone(), &content);
+        }
+    });
+    // Own writes are not reported by the watcher, so tell the frontend here
+    let file = FileEntry::new(vault.root(), &full_path);
+    emit_change(&app, &vault, VaultChange::Modified(file));
+
+    info!(hash = %entry.hash, "Set property");
+    Ok(NoteVersion {
+        mtime: entry.mtime,
+        hash: entry.hash,
+    })
+}
@@ -0,0 +1,871 @@
+//! Queries - a small Dataview-style language over note properties, inline
+//! fields, tags and links, such as
+//! `TABLE due, status FROM #project WHERE status != "done" SORT due`
+//!
+//! ```text
+//! TABLE [WITHOUT ID] expr [AS name], ...  |  LIST [expr]
+//! FROM #tag | "folder" | [[note]] | [[]]     (combined with AND, OR, -, parentheses)
+//! WHERE expr
+//! SORT expr [ASC | DESC], ...
+//! LIMIT n
+//! ```
+//!
+//! Expressions compare fields (`due`, `file.name`, `file.tags`) with
+//! literals using `=`, `!=`, `<`, `<=`, `>`, `>=`, combine with `AND`, `OR`
+//! and `!`, and call `contains`, `length` and `lower`.
+
+use super::fields;
+use super::frontmatter::{Property, PropertyValue};
+use super::index::IndexEntry;
+use super::links::LinkIndex;
+use super::properties::PropertyIndex;
+use super::search::query::QueryError;
+use super::tags;
+use super::{FileEntry, VaultHandle, VaultIndex, VaultRegistry};
+use crate::error::SlateError;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::time::Instant;
+use tauri::State;
+use tracing::{info, instrument};
+
+/// Clause keywords, which end a column list or expression.
+const CLAUSES: [&str; 4] = ["FROM", "WHERE", "SORT", "LIMIT"];
+
+/// What a query returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QueryKind {
+    Table,
+    List,
+}
+
+/// A parsed query.
+#[derive(Clone, Debug)]
+pub struct Query {
+    pub kind: QueryKind,
+    /// `TABLE WITHOUT ID`: rows are shown without their note.
+    pub without_id: bool,
+    columns: Vec<Column>,
+    from: Option<Source>,
+    filters: Vec<Expr>,
+    sort: Vec<(Expr, bool)>,
+    limit: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Column {
+    expr: Expr,
+    name: String,
+}
+
+/// Which notes a query looks at.
+#[derive(Clone, Debug)]
+enum Source {
+    /// Notes with the tag, or a tag nested under it.
+    Tag(String),
+    /// Notes in the folder, or the note at that path.
+    Folder(String),
+    /// Notes linking to the note; empty for the note the query is in.
+    LinksTo(String),
+    Not(Box<Source>),
+    And(Vec<Source>),
+    Or(Vec<Source>),
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Literal(PropertyValue),
+    Field(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, Comparison, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Function {
+    /// `contains(list, item)` or `contains(text, part)`, ignoring case.
+    Contains,
+    /// Items in a list or characters in text.
+    Length,
+    Lower,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<(Self, usize)> {
+        Some(match name.to_lowercase().as_str() {
+            "contains" => (Function::Contains, 2),
+            "length" => (Function::Length, 1),
+            "lower" => (Function::Lower, 1),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Number(f64),
+    Date(String),
+    Tag(String),
+    Link(String),
+    Symbol(&'static str),
+}
+
+/// Longest first, so `!=` is not read as `!` and `=`.
+const SYMBOLS: [&str; 13] = [
+    "!=", "<=", ">=", "=", "<", ">", "!", "(", ")", ",", "-", "&", "|",
+];
+
+/// Splits a query into tokens with their byte ranges.
+fn lex(src: &str) -> Result<Vec<(Token, usize, usize)>, QueryError> {
+    let error = |start: usize, end: usize, message: &str| QueryError {
+        message: message.to_string(),
+        start: src[..start].encode_utf16().count(),
+        end: src[..end.min(src.len())].encode_utf16().count(),
+    };
+
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < src.len() {
+        let rest = &src[pos..];
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        }
+        let start = pos;
+        let (token, len) = if c == '"' {
+            let mut text = String::new();
+            let mut chars = rest.char_indices().skip(1);
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break i + 1,
+                    Some((_, '\\')) => text.extend(chars.next().map(|(_, c)| c)),
+                    Some((_, c)) => text.push(c),
+                    None => return Err(error(start, src.len(), "Unclosed quote")),
+                }
+            };
+            (Token::Str(text), end)
+        } else if let Some(inner) = rest.strip_prefix("[[") {
+            let Some(end) = inner.find("]]") else {
+                return Err(error(start, src.len(), "Unclosed [["));
+            };
+            let target = inner[..end].split(['|', '#']).next().unwrap_or_default();
+            (Token::Link(target.trim().to_string()), end + 4)
+        } else if c == '#' {
+            let len = rest[1..]
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '/')))
+                .unwrap_or(rest.len() - 1);
+            if len == 0 {
+                return Err(error(start, start + 1, "Expected a tag after #"));
+            }
+            (Token::Tag(rest[1..1 + len].to_string()), 1 + len)
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':')))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            if let PropertyValue::Date(date) = super::frontmatter::scalar(word) {
+                (Token::Date(date), len)
+            } else {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .unwrap_or(rest.len());
+                match rest[..len].parse() {
+                    Ok(n) => (Token::Number(n), len),
+                    Err(_) => return Err(error(start, start + len, "Invalid number")),
+                }
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.')))
+                .unwrap_or(rest.len());
+            (Token::Word(rest[..len].to_string()), len)
+        } else if let Some(&symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            (Token::Symbol(symbol), symbol.len())
+        } else {
+            return Err(error(start, start + c.len_utf8(), "Unexpected character"));
+        };
+        pos += len;
+        tokens.push((token, start, pos));
+    }
+    Ok(tokens)
+}
+
+/// Parses a query.
+pub fn parse(src: &str) -> Result<Query, QueryError> {
+    let mut parser = Parser {
+        src,
+        tokens: lex(src)?,
+        pos: 0,
+    };
+    parser.query()
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> QueryError {
+        let (start, end) = match self.tokens.get(self.pos) {
+            Some(&(_, start, end)) => (start, end),
+            None => (self.src.len(), self.src.len()),
+        };
+        let utf16 = |byte: usize| self.src[..byte].encode_utf16().count();
+        QueryError {
+            message: message.into(),
+            start: utf16(start),
+            end: utf16(end),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _, _)| token)
+    }
+
+    /// Byte offset where the next token starts, or the end of the query.
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.src.len(), |&(_, start, _)| start)
+    }
+
+    /// True if the next token is the keyword `word`, in any case.
+    fn at_keyword(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(word))
+    }
+
+    fn eat_keyword(&mut self, word: &str) -> bool {
+        let at = self.at_keyword(word);
+        if at {
+            self.pos += 1;
+        }
+        at
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let at = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        if at {
+            self.pos += 1;
+        }
+        at
+    }
+
+    fn at_clause(&self) -> bool {
+        self.peek().is_none() || CLAUSES.iter().any(|clause| self.at_keyword(clause))
+    }
+
+    fn query(&mut self) -> Result<Query, QueryError> {
+        let kind = if self.eat_keyword("TABLE") {
+            QueryKind::Table
+        } else if self.eat_keyword("LIST") {
+            QueryKind::List
+        } else {
+            return Err(self.error("Expected TABLE or LIST"));
+        };
+        let without_id = kind == QueryKind::Table && self.eat_keyword("WITHOUT");
+        if without_id && !self.eat_keyword("ID") {
+            return Err(self.error("Expected ID after WITHOUT"));
+        }
+
+        let mut columns = Vec::new();
+        while !self.at_clause() {
+            if !columns.is_empty() && (kind == QueryKind::List || !self.eat_symbol(",")) {
+                return Err(self.error("Expected a clause: FROM, WHERE, SORT or LIMIT"));
+            }
+            columns.push(self.column()?);
+        }
+
+        let mut query = Query {
+            kind,
+            without_id,
+            columns,
+            from: None,
+            filters: Vec::new(),
+            sort: Vec::new(),
+            limit: None,
+        };
+        while self.peek().is_some() {
+            if self.eat_keyword("FROM") {
+                if query.from.is_some() {
+                    return Err(self.error("Only one FROM is allowed"));
+                }
+                query.from = Some(self.source_or()?);
+            } else if self.eat_keyword("WHERE") {
+                query.filters.push(self.or()?);
+            } else if self.eat_keyword("SORT") {
+                loop {
+                    let expr = self.or()?;
+                    let descending = self.eat_keyword("DESC");
+                    if !descending {
+                        self.eat_keyword("ASC");
+                    }
+                    query.sort.push((expr, descending));
+                    if !self.eat_symbol(",") {
+                        break;
+                    }
+                }
+            } else if self.eat_keyword("LIMIT") {
+                match self.peek() {
+                    Some(&Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => {
+                        query.limit = Some(n as usize);
+                        self.pos += 1;
+                    }
+                    _ => return Err(self.error("Expected a whole number after LIMIT")),
+                }
+            } else {
+                return Err(self.error("Expected a clause: FROM, WHERE, SORT or LIMIT"));
+            }
+        }
+        Ok(query)
+    }
+
+    /// `expr [AS name]`, named after how it is written unless renamed.
+    fn column(&mut self) -> Result<Column, QueryError> {
+        let start = self.offset();
+        let expr = self.or()?;
+        let end = self.tokens[..self.pos]
+            .last()
+            .map_or(start, |&(_, _, end)| end);
+        let name = if self.eat_keyword("AS") {
+            match self.peek().cloned() {
+                Some(Token::Str(name) | Token::Word(name)) => {
+                    self.pos += 1;
+                    name
+                }
+                _ => return Err(self.error("Expected a column name after AS")),
+            }
+        } else {
+            self.src[start..end].trim().to_string()
+        };
+        Ok(Column { expr, name })
+    }
+
+    fn source_or(&mut self) -> Result<Source, QueryError> {
+        let mut branches = vec![self.source_and()?];
+        while self.eat_keyword("OR") || self.eat_symbol("|") {
+            branches.push(self.source_and()?);
+        }
+        Ok(if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            Source::Or(branches)
+        })
+    }
+
+    fn source_and(&mut self) -> Result<Source, QueryError> {
+        let mut parts = vec![self.source_atom()?];
+        while self.eat_keyword("AND") || self.eat_symbol("&") {
+            parts.push(self.source_atom()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Source::And(parts)
+        })
+    }
+
+    fn source_atom(&mut self) -> Result<Source, QueryError> {
+        if self.eat_symbol("-") || self.eat_symbol("!") {
+            return Ok(Source::Not(Box::new(self.source_atom()?)));
+        }
+        if self.eat_symbol("(") {
+            let inner = self.source_or()?;
+            if !self.eat_symbol(")") {
+                return Err(self.error("Expected ')'"));
+            }
+            return Ok(inner);
+        }
+        let source = match self.peek() {
+            Some(Token::Tag(tag)) => Source::Tag(tag.clone()),
+            Some(Token::Str(folder)) => Source::Folder(folder.trim_matches('/').to_string()),
+            Some(Token::Link(target)) => Source::LinksTo(target.clone()),
+            _ => return Err(self.error("Expected #tag, \"folder\" or [[note]]")),
+        };
+        self.pos += 1;
+        Ok(source)
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.and()?;
+        while self.eat_keyword("OR") || self.eat_symbol("|") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.not()?;
+        while self.eat_keyword("AND") || self.eat_symbol("&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, QueryError> {
+        if self.eat_symbol("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, QueryError> {
+        let left = self.primary()?;
+        let comparison = match self.peek() {
+            Some(Token::Symbol("=")) => Comparison::Eq,
+            Some(Token::Symbol("!=")) => Comparison::Ne,
+            Some(Token::Symbol("<")) => Comparison::Lt,
+            Some(Token::Symbol("<=")) => Comparison::Le,
+            Some(Token::Symbol(">")) => Comparison::Gt,
+            Some(Token::Symbol(">=")) => Comparison::Ge,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.primary()?;
+        Ok(Expr::Compare(Box::new(left), comparison, Box::new(right)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, QueryError> {
+        if self.eat_symbol("(") {
+            let inner = self.or()?;
+            if !self.eat_symbol(")") {
+                return Err(self.error("Expected ')'"));
+            }
+            return Ok(inner);
+        }
+        if self.eat_symbol("-") {
+            return match self.peek() {
+                Some(&Token::Number(n)) => {
+                    self.pos += 1;
+                    Ok(Expr::Literal(PropertyValue::Number(-n)))
+                }
+                _ => Err(self.error("Expected a number after '-'")),
+            };
+        }
+
+        let literal = |value| Ok(Expr::Literal(value));
+        let at = self.pos;
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.error("Expected a value"));
+        };
+        self.pos += 1;
+        match token {
+            Token::Str(text) => literal(PropertyValue::Text(text)),
+            Token::Number(n) => literal(PropertyValue::Number(n)),
+            Token::Date(date) => literal(PropertyValue::Date(date)),
+            Token::Word(word) => match word.to_lowercase().as_str() {
+                "true" => literal(PropertyValue::Boolean(true)),
+                "false" => literal(PropertyValue::Boolean(false)),
+                "null" => literal(PropertyValue::Null),
+                _ if self.eat_symbol("(") => self.call(&word, at),
+                _ => Ok(Expr::Field(word)),
+            },
+            _ => {
+                self.pos = at;
+                Err(self.error("Expected a value"))
+            }
+        }
+    }
+
+    /// The arguments of a function call, after its `(`. `at` is the
+    /// position of the function's name.
+    fn call(&mut self, name: &str, at: usize) -> Result<Expr, QueryError> {
+        let Some((function, arity)) = Function::from_name(name) else {
+            self.pos = at;
+            return Err(self.error(format!("Unknown function: {}", name)));
+        };
+
+        let mut args = Vec::new();
+        if !self.eat_symbol(")") {
+            loop {
+                args.push(self.or()?);
+                if self.eat_symbol(")") {
+                    break;
+                }
+                if !self.eat_symbol(",") {
+                    return Err(self.error("Expected ',' or ')'"));
+                }
+            }
+        }
+        if args.len() != arity {
+            self.pos = at;
+            return Err(self.error(format!(
+                "{} takes {} argument{}",
+                name,
+                arity,
+                if arity == 1 { "" } else { "s" }
+            )));
+        }
+        Ok(Expr::Call(function, args))
+    }
+}
+
+/// What a query can see of one note.
+struct NoteData<'a> {
+    path: &'a str,
+    entry: &'a IndexEntry,
+    frontmatter: &'a [Property],
+    fields: &'a [Property],
+    tags: &'a [String],
+}
+
+impl NoteData<'_> {
+    /// A field by name: `file.*` for the note itself, otherwise a
+    /// frontmatter property or inline field.
+    fn field(&self, name: &str) -> PropertyValue {
+        let lower = name.to_lowercase();
+        if let Some(file_field) = lower.strip_prefix("file.") {
+            let file_name = self.path.rsplit('/').next().unwrap_or(self.path);
+            return match file_field {
+                "name" => PropertyValue::Text(
+                    file_name
+                        .strip_suffix(".md")
+                        .unwrap_or(file_name)
+                        .to_string(),
+                ),
+                "path" => PropertyValue::Text(self.path.to_string()),
+                "folder" => PropertyValue::Text(
+                    self.path
+                        .rsplit_once('/')
+                        .map_or("", |(folder, _)| folder)
+                        .to_string(),
+                ),
+                "tags" => PropertyValue::List(
+                    self.tags
+                        .iter()
+                        .map(|tag| PropertyValue::Text(tag.clone()))
+                        .collect(),
+                ),
+                "size" => PropertyValue::Number(self.entry.size as f64),
+                "mtime" => PropertyValue::Number(self.entry.mtime as f64),
+                _ => PropertyValue::Null,
+            };
+        }
+        fields::lookup(self.frontmatter, self.fields, name)
+            .cloned()
+            .unwrap_or(PropertyValue::Null)
+    }
+}
+
+/// Where a query runs: the indexes it reads, and the note it is in.
+struct Context<'a> {
+    links: &'a LinkIndex,
+    this: Option<&'a str>,
+}
+
+impl Source {
+    fn matches(&self, note: &NoteData, cx: &Context) -> Result<bool, SlateError> {
+        Ok(match self {
+            Source::Tag(tag) => note.tags.iter().any(|t| tags::is_under(t, tag)),
+            Source::Folder(folder) => {
+                folder.is_empty()
+                    || note.path.starts_with(&format!("{}/", folder))
+                    || note.path == folder
+                    || note.path.strip_suffix(".md") == Some(folder)
+            }
+            Source::LinksTo(target) => {
+                let target = if target.is_empty() {
+                    cx.this.ok_or_else(|| {
+                        SlateError::invalid_input("[[]] needs the note the query is in")
+                    })?
+                } else {
+                    match cx.links.resolver().resolve_note(target) {
+                        Some(path) => path.as_str(),
+                        None => return Ok(false),
+                    }
+                };
+                cx.links.backlinks(target).any(|source| source == note.path)
+            }
+            Source::Not(inner) => !inner.matches(note, cx)?,
+            Source::And(parts) => {
+                for part in parts {
+                    if !part.matches(note, cx)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            Source::Or(parts) => {
+                for part in parts {
+                    if part.matches(note, cx)? {
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+        })
+    }
+}
+
+impl Expr {
+    fn eval(&self, note: &NoteData) -> PropertyValue {
+        match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Field(name) => note.field(name),
+            Expr::Not(inner) => PropertyValue::Boolean(!truthy(&inner.eval(note))),
+            Expr::And(a, b) => {
+                PropertyValue::Boolean(truthy(&a.eval(note)) && truthy(&b.eval(note)))
+            }
+            Expr::Or(a, b) => {
+                PropertyValue::Boolean(truthy(&a.eval(note)) || truthy(&b.eval(note)))
+            }
+            Expr::Compare(a, comparison, b) => {
+                let order = compare(&a.eval(note), &b.eval(note));
+                PropertyValue::Boolean(match comparison {
+                    Comparison::Eq => order == Some(Ordering::Equal),
+                    Comparison::Ne => order != Some(Ordering::Equal),
+                    Comparison::Lt => order == Some(Ordering::Less),
+                    Comparison::Le => matches!(order, Some(Ordering::Less | Ordering::Equal)),
+                    Comparison::Gt => order == Some(Ordering::Greater),
+                    Comparison::Ge => {
+                        matches!(order, Some(Ordering::Greater | Ordering::Equal))
+                    }
+                })
+            }
+            Expr::Call(function, args) => {
+                let args: Vec<PropertyValue> = args.iter().map(|arg| arg.eval(note)).collect();
+                call(*function, &args)
+            }
+        }
+    }
+}
+
+fn truthy(value: &PropertyValue) -> bool {
+    match value {
+        PropertyValue::Null => false,
+        PropertyValue::Boolean(b) => *b,
+        PropertyValue::Number(n) => *n != 0.0,
+        PropertyValue::Text(text) => !text.is_empty(),
+        PropertyValue::List(items) => !items.is_empty(),
+        PropertyValue::Date(_) => true,
+    }
+}
+
+/// How two values compare, if they can be: numbers by value, text and
+/// dates as text, and anything with itself when equal.
+fn compare(a: &PropertyValue, b: &PropertyValue) -> Option<Ordering> {
+    use PropertyValue::*;
+    match (a, b) {
+        (Number(x), Number(y)) => x.partial_cmp(y),
+        (Text(x) | Date(x), Text(y) | Date(y)) => Some(x.cmp(y)),
+        (Boolean(x), Boolean(y)) => Some(x.cmp(y)),
+        (Null, Null) => Some(Ordering::Equal),
+        (List(x), List(y)) if x == y => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn call(function: Function, args: &[PropertyValue]) -> PropertyValue {
+    match (function, args) {
+        (Function::Contains, [haystack, needle]) => {
+            let same = |item: &PropertyValue| match (item, needle) {
+                (PropertyValue::Text(a), PropertyValue::Text(b)) => a
+                    .trim_start_matches('#')
+                    .eq_ignore_ascii_case(b.trim_start_matches('#')),
+                (item, needle) => compare(item, needle) == Some(Ordering::Equal),
+            };
+            PropertyValue::Boolean(match (haystack, needle) {
+                (PropertyValue::List(items), _) => items.iter().any(same),
+                (PropertyValue::Text(text), PropertyValue::Text(part)) => {
+                    text.to_lowercase().contains(&part.to_lowercase())
+                }
+                _ => false,
+            })
+        }
+        (Function::Length, [value]) => PropertyValue::Number(match value {
+            PropertyValue::List(items) => items.len() as f64,
+            PropertyValue::Text(text) => text.chars().count() as f64,
+            PropertyValue::Null => 0.0,
+            _ => 1.0,
+        }),
+        (Function::Lower, [PropertyValue::Text(text)]) => PropertyValue::Text(text.to_lowercase()),
+        (Function::Lower, [value]) => value.clone(),
+        _ => PropertyValue::Null,
+    }
+}
+
+/// Sort order for one key: comparable values in order, then values that
+/// cannot be compared, then nothing. Nothing stays last when descending.
+fn sort_order(a: &PropertyValue, b: &PropertyValue, descending: bool) -> Ordering {
+    match (a, b) {
+        (PropertyValue::Null, PropertyValue::Null) => Ordering::Equal,
+        (PropertyValue::Null, _) => Ordering::Greater,
+        (_, PropertyValue::Null) => Ordering::Less,
+        _ => {
+            let order = compare(a, b).unwrap_or(Ordering::Equal);
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        }
+    }
+}
+
+/// One note's row of a query result.
+#[derive(Clone, Debug, Serialize)]
+pub struct QueryRow {
+    pub file: FileEntry,
+    /// A value per column; for a `LIST` with an expression, just its value.
+    pub values: Vec<PropertyValue>,
+}
+
+/// The result of a query, ready to render as a table or list.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryResult {
+    pub kind: QueryKind,
+    /// Column names, not counting the note each row is for.
+    pub columns: Vec<String>,
+    /// False for `TABLE WITHOUT ID`, whose rows are shown without their
+    /// note.
+    pub show_file: bool,
+    pub rows: Vec<QueryRow>,
+}
+
+impl Query {
+    /// Runs the query over every note in the indexes, returning the paths
+    /// and values of the matching rows. `this` is the note the query is in.
+    pub fn run(
+        &self,
+        index: &VaultIndex,
+        properties: &PropertyIndex,
+        links: &LinkIndex,
+        this: Option<&str>,
+    ) -> Result<Vec<(String, Vec<PropertyValue>)>, SlateError> {
+        let cx = Context { links, this };
+        let mut rows = Vec::new();
+        for (path, entry) in index.iter() {
+            let note = NoteData {
+                path,
+                entry,
+                frontmatter: properties.properties(path).unwrap_or(&[]),
+                fields: properties.fields(path),
+                tags: links.tags(path),
+            };
+            if let Some(from) = &self.from {
+                if !from.matches(&note, &cx)? {
+                    continue;
+                }
+            }
+            if !self
+                .filters
+                .iter()
+                .all(|filter| truthy(&filter.eval(&note)))
+            {
+                continue;
+            }
+            let keys: Vec<PropertyValue> =
+                self.sort.iter().map(|(expr, _)| expr.eval(&note)).collect();
+            let values = self
+                .columns
+                .iter()
+                .map(|column| column.expr.eval(&note))
+                .collect();
+            rows.push((path.clone(), keys, values));
+        }
+
+        rows.sort_by(|a, b| {
+            self.sort
+                .iter()
+                .zip(a.1.iter().zip(&b.1))
+                .map(|((_, descending), (x, y))| sort_order(x, y, *descending))
+                .find(|order| order.is_ne())
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        if let Some(limit) = self.limit {
+            rows.truncate(limit);
+        }
+        Ok(rows
+            .into_iter()
+            .map(|(path, _, values)| (path, values))
+            .collect())
+    }
+}
+
+/// The query inside a fenced block, or `text` itself if it is not one.
+pub fn block_body(text: &str) -> &str {
+    let text = text.trim();
+    let Some((first, rest)) = text.split_once('\n') else {
+        return text;
+    };
+    let Some(marker) = ["```", "~~~"].into_iter().find(|m| first.starts_with(m)) else {
+        return text;
+    };
+    match rest.trim_end().rsplit_once('\n') {
+        Some((body, last)) if last.trim_start().starts_with(marker) => body,
+        None if rest.trim_start().starts_with(marker) => "",
+        _ => rest,
+    }
+}
+
+/// Runs a query over an open vault. `query` may be a whole fenced query
+/// block from a note; `path` is the note it is in, which `FROM [[]]` refers
+/// to. Fails like `search_vault` when the query does not parse.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn run_query(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    query: String,
+    path: Option<String>,
+) -> Result<QueryResult, SlateError> {
+    let start = Instant::now();
+    let parsed = parse(block_body(&query))?;
+
+    let rows = registry
+        .with_open(&vault, |open| {
+            let (Some(index), Some(properties), Some(links)) = (
+                open.index.as_ref(),
+                open.properties.as_ref(),
+                open.links.as_ref(),
+            ) else {
+                return Err(SlateError::not_ready("Vault index"));
+            };
+            parsed.run(index, properties, links, path.as_deref())
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))??;
+
+    let root = vault.root();
+    let rows: Vec<QueryRow> = rows
+        .into_iter()
+        .map(|(path, values)| QueryRow {
+            file: FileEntry::new(root, &root.join(&path)),
+            values,
+        })
+        .collect();
+
+    info!(
+        rows = rows.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Ran query"
+    );
+    Ok(QueryResult {
+        kind: parsed.kind,
+        columns: parsed
+            .columns
+            .into_iter()
+            .map(|column| column.name)
+            .collect(),
+        show_file: !parsed.without_id,
+        rows,
+    })
+}
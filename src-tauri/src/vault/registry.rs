@@ -0,0 +1,340 @@
+//! Vault registry - known vaults, open vaults and per-window selection
+
+use super::echo::WriteLog;
+use super::index::IndexEntry;
+use super::links::LinkIndex;
+use super::properties::PropertyIndex;
+use super::search::SearchIndex;
+use super::tasks::TaskIndex;
+use super::watcher::VaultWatcher;
+use super::{VaultConfig, VaultHandle, VaultIndex};
+use crate::error::SlateError;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tauri::State;
+use tracing::{info, instrument, warn};
+
+/// Summary of a known vault, as shown in the vault switcher.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultInfo {
+    /// Canonical root; usable as a vault handle while `available` is true.
+    pub root: String,
+    pub name: String,
+    /// False if the directory has gone away (e.g. an unplugged drive).
+    pub available: bool,
+    pub is_open: bool,
+}
+
+/// State kept for each open vault.
+#[derive(Default)]
+pub struct OpenVault {
+    /// In-memory copy of the persistent index, once it has been loaded.
+    pub index: Option<VaultIndex>,
+    /// Full-text index, kept in line with `index` by `search::refresh`.
+    pub search: Option<SearchIndex>,
+    /// Outgoing links of every note, kept in line with `index` by
+    /// `links::refresh`.
+    pub links: Option<LinkIndex>,
+    /// Frontmatter and inline fields of every note, kept in line with
+    /// `index` by `properties::refresh`.
+    pub properties: Option<PropertyIndex>,
+    /// Task list items of every note, kept in line with `index` by
+    /// `tasks::refresh`.
+    pub tasks: Option<TaskIndex>,
+    /// Filesystem watcher; stops when the vault is closed.
+    pub watcher: Option<VaultWatcher>,
+    /// Slate's own recent writes, so the watcher can ignore their echoes.
+    pub writes: WriteLog,
+    /// Paths the watcher saw change before `index` was loaded, replayed by
+    /// whoever stores it.
+    pub deferred: Vec<PathBuf>,
+}
+
+/// All vaults Slate knows about, held in Tauri managed state.
+pub struct VaultRegistry {
+    state: Mutex<RegistryState>,
+}
+
+struct RegistryState {
+    config: VaultConfig,
+    /// Where `config` is saved; None if there is no config directory.
+    config_path: Option<PathBuf>,
+    open: HashMap<PathBuf, OpenVault>,
+    /// Labels of the windows holding each open vault. A vault stays open
+    /// until the last of them closes it.
+    windows: HashMap<PathBuf, BTreeSet<String>>,
+    /// Cancellation flags of in-flight scans, by vault root.
+    scans: HashMap<PathBuf, Arc<AtomicBool>>,
+}
+
+impl RegistryState {
+    fn save(&self) -> Result<(), SlateError> {
+        let path = self
+            .config_path
+            .as_deref()
+            .ok_or_else(|| SlateError::internal("Could not determine config directory"))?;
+        self.config.save_to(path)
+    }
+
+    fn cancel_scan(&mut self, root: &Path) -> bool {
+        match self.scans.remove(root) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                info!(vault = %root.display(), "Cancelled scan");
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl VaultRegistry {
+    /// Creates a registry from the persisted vault config.
+    pub fn load() -> Self {
+        Self::from_config(VaultConfig::default_path())
+    }
+
+    /// Creates a registry that keeps its config at `path` instead of the
+    /// config directory.
+    pub fn with_config(path: &Path) -> Self {
+        Self::from_config(Some(path.to_path_buf()))
+    }
+
+    fn from_config(config_path: Option<PathBuf>) -> Self {
+        let mut config = config_path
+            .as_deref()
+            .map(VaultConfig::load_from)
+            .unwrap_or_default();
+        // Configs written before the registry existed only have `last_vault`
+        if let Some(last) = config.last_vault.clone() {
+            if !config.known_vaults.contains(&last) {
+                config.known_vaults.push(last);
+            }
+        }
+
+        VaultRegistry {
+            state: Mutex::new(RegistryState {
+                config,
+                config_path,
+                open: HashMap::new(),
+                windows: HashMap::new(),
+                scans: HashMap::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, RegistryState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn list(&self) -> Vec<VaultInfo> {
+        let state = self.state();
+        state
+            .config
+            .known_vaults
+            .iter()
+            .map(|root| vault_info(root, state.open.contains_key(root)))
+            .collect()
+    }
+
+    /// Opens a vault for `window`, remembering it as that window's last vault.
+    /// A scan still running for the window's previous vault is cancelled.
+    pub fn open(&self, window: &str, handle: VaultHandle) -> Result<VaultInfo, SlateError> {
+        let root = handle.root().to_path_buf();
+        let mut state = self.state();
+
+        if let Some(previous) = state.config.last_used.get(window).cloned() {
+            if previous != root {
+                state.cancel_scan(&previous);
+            }
+        }
+
+        if !state.config.known_vaults.contains(&root) {
+            state.config.known_vaults.push(root.clone());
+        }
+        state.config.last_vault = Some(root.clone());
+        state
+            .config
+            .last_used
+            .insert(window.to_string(), root.clone());
+        state.save()?;
+
+        state.open.entry(root.clone()).or_default();
+        state
+            .windows
+            .entry(root.clone())
+            .or_default()
+            .insert(window.to_string());
+        info!(vault = %root.display(), window, "Opened vault");
+
+        Ok(vault_info(&root, true))
+    }
+
+    /// Closes a vault for `window`. Its in-memory state is dropped once no
+    /// other window holds it. Returns false if `window` did not have it open.
+    pub fn close(&self, window: &str, root: &Path) -> bool {
+        let mut state = self.state();
+        let Some(windows) = state.windows.get_mut(root) else {
+            return false;
+        };
+        if !windows.remove(window) {
+            return false;
+        }
+        if windows.is_empty() {
+            state.windows.remove(root);
+            state.cancel_scan(root);
+            state.open.remove(root);
+            info!(vault = %root.display(), window, "Closed vault");
+        }
+        true
+    }
+
+    /// Closes a vault and removes every trace of it from the config.
+    pub fn forget(&self, root: &Path) -> Result<(), SlateError> {
+        let mut state = self.state();
+        state.cancel_scan(root);
+        state.open.remove(root);
+        state.windows.remove(root);
+        state.config.known_vaults.retain(|p| p != root);
+        state.config.last_used.retain(|_, p| p != root);
+        if state.config.last_vault.as_deref() == Some(root) {
+            state.config.last_vault = None;
+        }
+        state.save()?;
+        info!(vault = %root.display(), "Forgot vault");
+        Ok(())
+    }
+
+    /// The vault `window` used last, if it is still available.
+    pub fn last_vault(&self, window: &str) -> Option<VaultHandle> {
+        self.state().config.last_vault(window)
+    }
+
+    /// Registers a new scan of `handle`, cancelling any scan of the same vault
+    /// that is still running. The scan should poll the returned flag.
+    pub fn begin_scan(&self, handle: &VaultHandle) -> Arc<AtomicBool> {
+        let mut state = self.state();
+        state.cancel_scan(handle.root());
+        let flag = Arc::new(AtomicBool::new(false));
+        state
+            .scans
+            .insert(handle.root().to_path_buf(), Arc::clone(&flag));
+        flag
+    }
+
+    /// Unregisters a finished scan, unless a newer one has replaced it.
+    pub fn end_scan(&self, handle: &VaultHandle, flag: &Arc<AtomicBool>) {
+        let mut state = self.state();
+        if state
+            .scans
+            .get(handle.root())
+            .is_some_and(|current| Arc::ptr_eq(current, flag))
+        {
+            state.scans.remove(handle.root());
+        }
+    }
+
+    /// Cancels the in-flight scan of a vault. Returns false if none was running.
+    pub fn cancel_scan(&self, root: &Path) -> bool {
+        self.state().cancel_scan(root)
+    }
+
+    /// Records a write Slate is making to `relative_path`, so the watcher
+    /// does not report it back as an outside change. Every backend write to
+    /// a note must go through here, before the file lands on disk.
+    pub fn record_write(&self, vault: &VaultHandle, relative_path: &str, entry: &IndexEntry) {
+        self.with_open(vault, |open| open.writes.record(relative_path, entry));
+    }
+
+    /// Runs `f` against an open vault. Returns None if the vault is not open.
+    pub fn with_open<R>(
+        &self,
+        handle: &VaultHandle,
+        f: impl FnOnce(&mut OpenVault) -> R,
+    ) -> Option<R> {
+        self.state().open.get_mut(handle.root()).map(f)
+    }
+}
+
+fn vault_info(root: &Path, is_open: bool) -> VaultInfo {
+    let handle = VaultHandle::from_path(root).ok();
+    VaultInfo {
+        root: root.to_string_lossy().to_string(),
+        name: handle
+            .as_ref()
+            .map(VaultHandle::name)
+            .unwrap_or_else(|| root.to_string_lossy().to_string()),
+        available: handle.is_some(),
+        is_open,
+    }
+}
+
+/// Lists every known vault.
+#[tauri::command]
+pub fn list_vaults(registry: State<'_, VaultRegistry>) -> Vec<VaultInfo> {
+    registry.list()
+}
+
+/// Opens (and if needed registers) the vault at `path` for the calling window
+/// and starts watching it for changes.
+#[tauri::command]
+#[instrument(skip(app, window, registry))]
+pub fn open_vault(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    registry: State<'_, VaultRegistry>,
+    path: String,
+) -> Result<VaultInfo, SlateError> {
+    let handle = VaultHandle::resolve(&path, dirs::home_dir().as_deref())?;
+    let info = registry.open(window.label(), handle.clone())?;
+
+    let needs_watcher = registry
+        .with_open(&handle, |open| open.watcher.is_none())
+        .unwrap_or(false);
+    if needs_watcher {
+        // A vault without a watcher still works; it just won't see outside edits
+        match VaultWatcher::start(app, handle.clone()) {
+            Ok(watcher) => {
+                registry.with_open(&handle, |open| open.watcher = Some(watcher));
+            }
+            Err(e) => warn!(error = %e, "Failed to watch vault"),
+        }
+    }
+
+    Ok(info)
+}
+
+/// Closes a vault for the calling window; other windows keep it open.
+/// Closing a vault that is not open is a no-op. Like `forget_vault` it takes
+/// a plain path, so a vault whose directory has gone away can still be closed.
+#[tauri::command]
+pub fn close_vault(window: tauri::Window, registry: State<'_, VaultRegistry>, root: String) {
+    registry.close(window.label(), Path::new(&root));
+}
+
+/// Removes a vault from the known list. Takes a plain path rather than a
+/// handle so that vaults whose directory no longer exists can be forgotten.
+#[tauri::command]
+#[instrument(skip(registry))]
+pub fn forget_vault(registry: State<'_, VaultRegistry>, root: String) -> Result<(), SlateError> {
+    registry.forget(Path::new(&root))
+}
+
+/// Returns the vault the calling window used last, if it is still available.
+#[tauri::command]
+pub fn get_last_vault(
+    window: tauri::Window,
+    registry: State<'_, VaultRegistry>,
+) -> Option<VaultHandle> {
+    registry.last_vault(window.label())
+}
+
+/// Cancels the in-flight streaming scan of a vault, if any.
+#[tauri::command]
+pub fn cancel_scan(registry: State<'_, VaultRegistry>, vault: VaultHandle) -> bool {
+    registry.cancel_scan(vault.root())
+}
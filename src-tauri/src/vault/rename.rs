@@ -0,0 +1,515 @@
+//! Renaming notes - moves a note and rewrites every link that would break
+//! or lead somewhere else because of it, found through the link index
+
+use super::index::{hash_bytes, IndexEntry};
+use super::links::{self, Link, LinkIndex, LinkSyntax, Resolver};
+use super::note::{self, BaseVersion};
+use super::watcher::{emit_change, VaultChange};
+use super::{is_hidden_path, is_markdown_file, FileEntry, VaultHandle, VaultRegistry};
+use crate::error::SlateError;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use tauri::{AppHandle, State};
+use tracing::{info, instrument, warn};
+
+/// A link rewritten by a rename.
+#[derive(Clone, Debug, Serialize)]
+pub struct LinkEdit {
+    /// 1-based line number.
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// The links rewritten in one note.
+#[derive(Clone, Debug, Serialize)]
+pub struct NoteEdits {
+    /// Relative path of the note after the rename.
+    pub path: String,
+    pub edits: Vec<LinkEdit>,
+}
+
+/// What a rename did, or would do.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameReport {
+    pub from: String,
+    pub to: String,
+    /// False for a dry run, which moves and writes nothing.
+    pub applied: bool,
+    /// Notes whose links were rewritten, by path.
+    pub notes: Vec<NoteEdits>,
+    /// Notes that could not be rewritten. A rename that gets this far is
+    /// refused before anything moves, so once applied this only lists notes
+    /// that changed on disk or failed to write after the move; their links
+    /// still point at the old path.
+    pub failed: Vec<String>,
+}
+
+/// Moves the note at `from` to `to` (both relative to the vault root) and
+/// rewrites the links that led to it, along with the relative links in the
+/// note itself. Links to another note that the new name would take over
+/// are rewritten by path, so they still lead where they did. Aliases, anchors and the way each link was written (by name
+/// or by path, relative or from the root) are kept.
+///
+/// With `dry_run`, nothing is moved or written; the report lists the edits
+/// that would be made. Otherwise the rename is refused, with nothing moved,
+/// if any note whose links need rewriting cannot be read or has changed.
+#[tauri::command]
+#[instrument(skip(app, registry, vault))]
+pub fn rename_note(
+    app: AppHandle,
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    from: String,
+    to: String,
+    dry_run: Option<bool>,
+) -> Result<RenameReport, SlateError> {
+    let start = Instant::now();
+    let root = vault.root();
+    let from_path = vault.join(&from)?;
+    let to_path = vault.join(&to)?;
+    if !is_markdown_file(&from_path) {
+        return Err(SlateError::not_found(
+            &from,
+            format!("Not a note: {}", from),
+        ));
+    }
+    if Path::new(&to).extension().is_none_or(|ext| ext != "md") {
+        return Err(SlateError::invalid_path(
+            &to,
+            format!("Notes must end in .md: {}", to),
+        ));
+    }
+    if is_hidden_path(root, &to_path) {
+        return Err(SlateError::invalid_path(
+            &to,
+            format!("Cannot move a note into a hidden folder: {}", to),
+        ));
+    }
+    if from == to {
+        return Err(SlateError::invalid_input(format!(
+            "Note is already at {}",
+            to
+        )));
+    }
+    // A case-only rename finds the note itself on case-insensitive disks
+    if to_path.exists() && from.to_lowercase() != to.to_lowercase() {
+        return Err(SlateError::AlreadyExists {
+            message: format!("A file already exists at {}", to),
+            path: to,
+        });
+    }
+
+    let (paths, sources) = registry
+        .with_open(&vault, |open| {
+            let index = open.links.as_ref()?;
+            let paths: Vec<String> = index.iter().map(|(path, _)| path.clone()).collect();
+            Some((paths, sources(index, &from, &to)))
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Link index"))?;
+
+    // Work out every edit before touching anything
+    let Plan {
+        notes: planned,
+        mut failed,
+    } = Rename::new(root, &paths, &from, &to).plan(&sources);
+
+    if dry_run.unwrap_or(false) {
+        return Ok(RenameReport {
+            notes: planned
+                .into_iter()
+                .map(|note| NoteEdits {
+                    path: note.path,
+                    edits: note.edits,
+                })
+                .collect(),
+            from,
+            to,
+            applied: false,
+            failed,
+        });
+    }
+    // Links left pointing at the old path are hard to find afterwards, so
+    // only move once every note that needs it can be rewritten
+    verify(root, &planned, &failed)?;
+
+    // Moving and updating the index under the registry lock keeps the
+    // watcher from seeing the move half done and reporting it again
+    registry
+        .with_open(&vault, |open| -> Result<(), SlateError> {
+            if let Some(parent) = to_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| SlateError::io("Failed to create", parent, e))?;
+            }
+            fs::rename(&from_path, &to_path)
+                .map_err(|e| SlateError::io("Failed to move", &from_path, e))?;
+            if let Some(index) = open.index.as_mut() {
+                if let Some(entry) = index.remove(&from) {
+                    index.insert(to.clone(), entry);
+                }
+            }
+            Ok(())
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))??;
+
+    let mut notes = Vec::new();
+    let mut written: Vec<(String, IndexEntry)> = Vec::new();
+    for PlannedNote {
+        path,
+        hash,
+        content,
+        edits,
+        ..
+    } in planned
+    {
+        let full_path = vault.join(&path)?;
+        let base = BaseVersion {
+            hash: Some(hash),
+            mtime: None,
+        };
+        // Checked again: the note may have changed since `verify`
+        match note::check_base(&full_path, &base, &content) {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => {
+                warn!(note = %path, "Note changed on disk during rename; links not rewritten");
+                failed.push(path);
+                continue;
+            }
+            Err(e) => {
+                warn!(note = %path, error = %e, "Failed to reread note; links not rewritten");
+                failed.push(path);
+                continue;
+            }
+        }
+        match note::write_atomic(&full_path, &content, |entry| {
+            registry.record_write(&vault, &path, entry)
+        }) {
+            Ok(entry) => {
+                written.push((path.clone(), entry));
+                notes.push(NoteEdits { path, edits });
+            }
+            Err(e) => {
+                warn!(note = %path, error = %e, "Failed to rewrite links");
+                failed.push(path);
+            }
+        }
+    }
+
+    registry.with_open(&vault, |open| {
+        let Some(index) = open.index.as_mut() else {
+            return;
+        };
+        for (path, entry) in &written {
+            index.insert(path.clone(), entry.clone());
+        }
+        if let Err(e) = index.save(root) {
+            warn!(error = %e, "Failed to persist vault index");
+        }
+    });
+    super::refresh_derived(&registry, &vault);
+
+    // Own writes are not reported by the watcher, so tell the frontend here
+    emit_change(
+        &app,
+        &vault,
+        VaultChange::Renamed {
+            from: FileEntry::new(root, &from_path),
+            to: FileEntry::new(root, &to_path),
+        },
+    );
+    for (path, _) in &written {
+        let file = FileEntry::new(root, &root.join(path));
+        emit_change(&app, &vault, VaultChange::Modified(file));
+    }
+
+    info!(
+        notes = notes.len(),
+        failed = failed.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Renamed note"
+    );
+    notes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(RenameReport {
+        from,
+        to,
+        applied: true,
+        notes,
+        failed,
+    })
+}
+
+/// The notes whose links a rename of `from` to `to` may change: those
+/// linking to `from`, those linking to a note named like `to` (which the
+/// new name could take their links from), and `from` itself.
+pub fn sources(index: &LinkIndex, from: &str, to: &str) -> Vec<String> {
+    let name = |path: &str| {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        name.strip_suffix(".md").unwrap_or(name).to_lowercase()
+    };
+    let to_name = name(to);
+    let mut sources: Vec<String> = index.backlinks(from).cloned().collect();
+    for (path, _) in index.iter() {
+        if path != from && name(path) == to_name {
+            sources.extend(index.backlinks(path).cloned());
+        }
+    }
+    sources.retain(|source| source != from);
+    sources.sort();
+    sources.dedup();
+    sources.push(from.to_string());
+    sources
+}
+
+/// A rename being planned: where links lead before it and after it.
+pub struct Rename<'a> {
+    root: &'a Path,
+    from: &'a str,
+    to: &'a str,
+    old: Resolver,
+    new: Resolver,
+}
+
+/// The rewrites a rename needs, worked out before anything is touched.
+pub struct Plan {
+    pub notes: Vec<PlannedNote>,
+    /// Notes that could not be read, by path after the rename.
+    pub failed: Vec<String>,
+}
+
+/// A note whose links a rename rewrites.
+pub struct PlannedNote {
+    /// Relative path after the rename.
+    pub path: String,
+    /// Relative path before the rename, where the note is read from.
+    pub source: String,
+    /// Hash of the text the edits were made against.
+    pub hash: String,
+    /// The note with its links rewritten.
+    pub content: String,
+    pub edits: Vec<LinkEdit>,
+}
+
+impl<'a> Rename<'a> {
+    /// Plans moving the note `from` to `to` in a vault whose notes are
+    /// `paths`, all relative to `root`.
+    pub fn new(root: &'a Path, paths: &[String], from: &'a str, to: &'a str) -> Self {
+        let mut renamed: Vec<String> = paths.iter().filter(|&path| path != from).cloned().collect();
+        renamed.push(to.to_string());
+        renamed.sort();
+        Rename {
+            root,
+            from,
+            to,
+            old: Resolver::new(paths),
+            new: Resolver::new(&renamed),
+        }
+    }
+
+    /// Reads each of `sources` (see [`sources`]) and works out its
+    /// rewrites.
+    pub fn plan(&self, sources: &[String]) -> Plan {
+        let mut plan = Plan {
+            notes: Vec::new(),
+            failed: Vec::new(),
+        };
+        for source in sources {
+            let path = if source == self.from {
+                self.to
+            } else {
+                source.as_str()
+            };
+            let bytes = match fs::read(self.root.join(source)) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    warn!(note = %source, error = %e, "Failed to read note to rewrite");
+                    plan.failed.push(path.to_string());
+                    continue;
+                }
+            };
+            let hash = hash_bytes(&bytes);
+            let Ok(text) = String::from_utf8(bytes) else {
+                warn!(note = %source, "Note to rewrite is not valid UTF-8");
+                plan.failed.push(path.to_string());
+                continue;
+            };
+            let (content, edits) = self.rewrite(source, &text);
+            if !edits.is_empty() {
+                plan.notes.push(PlannedNote {
+                    path: path.to_string(),
+                    source: source.clone(),
+                    hash,
+                    content,
+                    edits,
+                });
+            }
+        }
+        plan
+    }
+
+    /// `text` of the note at `source` with the links the rename would break
+    /// or send elsewhere rewritten, and the edits made.
+    pub fn rewrite(&self, source: &str, text: &str) -> (String, Vec<LinkEdit>) {
+        let new_source = if source == self.from { self.to } else { source };
+        let mut rewritten = String::with_capacity(text.len());
+        let mut edits = Vec::new();
+        let mut copied = 0;
+
+        for link in links::parse(text) {
+            if link.target.is_empty() {
+                continue;
+            }
+            let Some(target) = self.old.resolve(self.root, source, &link) else {
+                continue;
+            };
+            let new_target = if target == self.from {
+                self.to
+            } else {
+                &target
+            };
+            if self.new.resolve(self.root, new_source, &link).as_deref() == Some(new_target) {
+                continue;
+            }
+
+            let before = &text[link.start..link.end];
+            let Some((start, end)) = links::target_range(before, link.syntax) else {
+                continue;
+            };
+            let angled = before[..start].ends_with('<');
+            let written = self.written_target(source, &link, new_target, angled);
+            let after = format!("{}{}{}", &before[..start], written, &before[end..]);
+
+            rewritten.push_str(&text[copied..link.start]);
+            rewritten.push_str(&after);
+            copied = link.end;
+            edits.push(LinkEdit {
+                line: link.line,
+                before: before.to_string(),
+                after,
+            });
+        }
+
+        rewritten.push_str(&text[copied..]);
+        (rewritten, edits)
+    }
+
+    /// How `link`, found in `source`, should now write its target to lead to
+    /// `new_target`, in the same style as before.
+    fn written_target(&self, source: &str, link: &Link, new_target: &str, angled: bool) -> String {
+        let new_source = if source == self.from { self.to } else { source };
+        let bare = new_target.strip_suffix(".md").unwrap_or(new_target);
+
+        match link.syntax {
+            LinkSyntax::Wikilink => {
+                // By name if that is how it was written and still unambiguous
+                let name = bare.rsplit('/').next().unwrap_or(bare);
+                let by_name = Link {
+                    target: name.to_string(),
+                    ..link.clone()
+                };
+                let resolves = self.new.resolve(self.root, new_source, &by_name).as_deref()
+                    == Some(new_target);
+                if !link.target.contains('/') && resolves {
+                    name.to_string()
+                } else {
+                    bare.to_string()
+                }
+            }
+            LinkSyntax::Markdown => {
+                let with_ext = link.target.to_lowercase().ends_with(".md") || bare == new_target;
+                let path = if with_ext { new_target } else { bare };
+                let written = if link.target.starts_with('/') {
+                    format!("/{}", path)
+                } else if self.was_relative(source, link) {
+                    relative_path(new_source, path)
+                } else {
+                    path.to_string()
+                };
+                if angled {
+                    written
+                } else {
+                    percent_encode(&written)
+                }
+            }
+        }
+    }
+
+    /// True if a markdown link in `source` was found relative to the note,
+    /// rather than from the vault root.
+    fn was_relative(&self, source: &str, link: &Link) -> bool {
+        let dir = source.rsplit_once('/').map_or("", |(dir, _)| dir);
+        let Some(relative) = links::normalize(&format!("{}/{}", dir, link.target)) else {
+            return false;
+        };
+        let target = self.old.resolve(self.root, source, link);
+        [relative.clone(), format!("{}.md", relative)]
+            .iter()
+            .any(|path| {
+                target
+                    .as_deref()
+                    .is_some_and(|t| t.eq_ignore_ascii_case(path))
+            })
+    }
+}
+
+/// Refuses a rename if any planned note could not be read, or has changed
+/// on disk since it was.
+pub fn verify(root: &Path, planned: &[PlannedNote], failed: &[String]) -> Result<(), SlateError> {
+    let changed = planned.iter().filter(|note| {
+        let base = BaseVersion {
+            hash: Some(note.hash.clone()),
+            mtime: None,
+        };
+        !matches!(
+            note::check_base(&root.join(&note.source), &base, &note.content),
+            Ok(Ok(()))
+        )
+    });
+    let blocked: Vec<&str> = failed
+        .iter()
+        .map(String::as_str)
+        .chain(changed.map(|note| note.path.as_str()))
+        .collect();
+    if blocked.is_empty() {
+        return Ok(());
+    }
+    Err(SlateError::invalid_input(format!(
+        "Cannot rewrite links in {}; nothing was renamed",
+        blocked.join(", ")
+    )))
+}
+
+/// The path of `path` relative to the folder holding `source`, both
+/// relative to the vault root.
+fn relative_path(source: &str, path: &str) -> String {
+    let mut dir: Vec<&str> = source.split('/').collect();
+    dir.pop();
+    let target: Vec<&str> = path.split('/').collect();
+    let common = dir
+        .iter()
+        .zip(&target[..target.len() - 1])
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts = vec![".."; dir.len() - common];
+    parts.extend(&target[common..]);
+    parts.join("/")
+}
+
+/// Escapes what would end or garble a markdown link destination.
+fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' => encoded.push_str("%20"),
+            '%' => encoded.push_str("%25"),
+            '(' => encoded.push_str("%28"),
+            ')' => encoded.push_str("%29"),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
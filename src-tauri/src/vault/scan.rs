@@ -0,0 +1,162 @@
+//! Streaming vault scan - pushes files to the frontend in batches as they are
+//! found, so the file list fills in before the walk finishes
+
+use super::{
+    cached_index, commit_index, markdown_files, FileEntry, Vault, VaultHandle, VaultIndex,
+    VaultRegistry,
+};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, instrument, warn};
+
+/// Number of files sent per batch.
+const BATCH_SIZE: usize = 256;
+
+/// Messages sent over the scan channel.
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum ScanEvent {
+    /// Files found since the previous batch, in walk order.
+    Batch { entries: Vec<FileEntry> },
+    /// Running count of files found so far.
+    Progress { scanned: usize },
+    /// The scan completed. `from_index` is set when the files came straight
+    /// from the persistent index rather than a walk.
+    Finished {
+        total: usize,
+        elapsed_ms: u64,
+        from_index: bool,
+    },
+    /// The scan was cancelled after finding `scanned` files.
+    Cancelled { scanned: usize },
+}
+
+/// Scans a vault in the background, streaming results over `on_event`.
+///
+/// Starting a scan cancels any scan of the same vault that is still running;
+/// switching or closing the vault cancels it too (see `cancel_scan`). Once a
+/// full walk finishes, the persistent index is built from it.
+#[tauri::command]
+#[instrument(skip_all, fields(vault = %vault.root().display()))]
+pub fn scan_vault_streaming(
+    app: AppHandle,
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    on_event: Channel<ScanEvent>,
+) {
+    let cancel = registry.begin_scan(&vault);
+    let cached = cached_index(&registry, &vault);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        stream_scan(&app, &vault, cached, &cancel, &on_event);
+        app.state::<VaultRegistry>().end_scan(&vault, &cancel);
+    });
+}
+
+fn stream_scan(
+    app: &AppHandle,
+    vault: &VaultHandle,
+    cached: Option<VaultIndex>,
+    cancel: &AtomicBool,
+    channel: &Channel<ScanEvent>,
+) {
+    let start = Instant::now();
+    let root = vault.root();
+    let send = |event: ScanEvent| {
+        if let Err(e) = channel.send(event) {
+            warn!(error = %e, "Failed to send scan event");
+        }
+    };
+    let elapsed_ms = || start.elapsed().as_millis() as u64;
+
+    if let Some(index) = cached {
+        let entries = index.entries(root);
+        for (i, chunk) in entries.chunks(BATCH_SIZE).enumerate() {
+            if cancel.load(Ordering::Relaxed) {
+                send(ScanEvent::Cancelled {
+                    scanned: i * BATCH_SIZE,
+                });
+                return;
+            }
+            send(ScanEvent::Batch {
+                entries: chunk.to_vec(),
+            });
+        }
+        send(ScanEvent::Finished {
+            total: entries.len(),
+            elapsed_ms: elapsed_ms(),
+            from_index: true,
+        });
+        update_index(app, vault, index, markdown_files(root), cancel, true);
+        return;
+    }
+
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut files = Vec::new();
+
+    for file in markdown_files(root) {
+        if cancel.load(Ordering::Relaxed) {
+            info!(scanned = files.len(), "Scan cancelled");
+            send(ScanEvent::Cancelled {
+                scanned: files.len(),
+            });
+            return;
+        }
+
+        batch.push(FileEntry::new(root, &file));
+        files.push(file);
+
+        if batch.len() == BATCH_SIZE {
+            send(ScanEvent::Batch {
+                entries: std::mem::take(&mut batch),
+            });
+            send(ScanEvent::Progress {
+                scanned: files.len(),
+            });
+        }
+    }
+
+    if !batch.is_empty() {
+        send(ScanEvent::Batch { entries: batch });
+    }
+    send(ScanEvent::Finished {
+        total: files.len(),
+        elapsed_ms: elapsed_ms(),
+        from_index: false,
+    });
+    info!(
+        file_count = files.len(),
+        elapsed_ms = elapsed_ms(),
+        "Streaming scan complete"
+    );
+
+    // The listing is on screen; build the index from the files just found,
+    // without walking again or announcing every file a second time
+    update_index(app, vault, VaultIndex::default(), files, cancel, false);
+}
+
+/// Reconciles `index` against `files` and commits it, unless the scan is
+/// cancelled first, in which case the index is dropped; whatever cancelled
+/// the scan closed the vault or started a new scan.
+fn update_index(
+    app: &AppHandle,
+    vault: &VaultHandle,
+    mut index: VaultIndex,
+    files: impl IntoIterator<Item = PathBuf>,
+    cancel: &AtomicBool,
+    announce: bool,
+) {
+    let on_disk = Vault::on_disk(vault.root());
+    if let Some(delta) = index.reconcile_paths(&on_disk, files, cancel) {
+        commit_index(app, vault, index, delta, announce);
+    }
+}
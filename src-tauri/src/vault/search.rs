@@ -0,0 +1,686 @@
+//! Full-text search - an inverted index over note contents with stemming,
+//! BM25 ranking, highlighted snippets and a query language on top
+
+pub mod query;
+mod tokenize;
+
+pub use query::{Query, QueryError};
+pub use tokenize::{stem, tokenize, Token};
+
+use super::index::{hash_bytes, write_slate_file, Stale, SLATE_DIR};
+use super::note;
+use super::{FileEntry, VaultHandle, VaultIndex, VaultRegistry};
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use tauri::State;
+use tracing::{debug, info, instrument, warn};
+
+const SEARCH_FILE: &str = "search.bin";
+/// Bump whenever the on-disk layout or tokenization changes; older search
+/// indexes are rebuilt.
+const SEARCH_VERSION: u32 = 1;
+
+/// Results returned when the caller does not ask for a limit.
+const DEFAULT_LIMIT: usize = 50;
+/// Snippets returned per note.
+const MAX_SNIPPETS: usize = 3;
+/// Bytes of context kept either side of a match in a snippet.
+const SNIPPET_CONTEXT: usize = 80;
+
+// BM25 parameters, at their usual values
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+type DocId = u32;
+
+/// What the search index knows about a note.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Doc {
+    path: String,
+    /// Hash of the content that was indexed; compared with the vault index
+    /// to find notes that need reindexing.
+    hash: String,
+    /// Number of tokens, for BM25 length normalization.
+    len: u32,
+    /// Distinct stems, so the note can be taken out of the postings.
+    stems: Vec<String>,
+}
+
+/// Inverted index of every note in a vault, keyed by stem.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    docs: Vec<Option<Doc>>,
+    ids: HashMap<String, DocId>,
+    /// Slots in `docs` left by removed notes.
+    free: Vec<DocId>,
+    /// Stem -> note -> token positions, ascending.
+    postings: HashMap<String, HashMap<DocId, Vec<u32>>>,
+    /// Every lowercased word seen, with its stem, for prefix queries. Never
+    /// pruned; a word no note uses any more only costs a lookup.
+    vocabulary: BTreeMap<String, String>,
+    total_len: u64,
+}
+
+/// A note tokenized and ready to go into the index. Built without holding
+/// the index, since tokenizing a whole vault takes a while.
+pub struct Analyzed {
+    path: String,
+    hash: String,
+    len: u32,
+    positions: HashMap<String, Vec<u32>>,
+    words: BTreeMap<String, String>,
+}
+
+impl Analyzed {
+    /// Tokenizes a note. The file name is indexed ahead of the body, so a
+    /// note can be found by its title.
+    pub fn new(path: String, hash: String, text: &str) -> Self {
+        let title = Path::new(&path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let title = tokenize(&title);
+        // Leave a gap so a phrase cannot match across the title and the body
+        let body_start = title.len() as u32 + 1;
+        let tokens = title
+            .into_iter()
+            .enumerate()
+            .map(|(i, token)| (i as u32, token))
+            .chain(
+                tokenize(text)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, token)| (body_start + i as u32, token)),
+            );
+
+        let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut words = BTreeMap::new();
+        let mut len = 0;
+        for (position, token) in tokens {
+            positions
+                .entry(token.stem.clone())
+                .or_default()
+                .push(position);
+            words.insert(token.word, token.stem);
+            len += 1;
+        }
+
+        Analyzed {
+            path,
+            hash,
+            len,
+            positions,
+            words,
+        }
+    }
+}
+
+impl SearchIndex {
+    fn file_path(root: &Path) -> std::path::PathBuf {
+        root.join(SLATE_DIR).join(SEARCH_FILE)
+    }
+
+    /// Loads the stored search index. Returns None if there is none or it
+    /// cannot be used.
+    pub fn load(root: &Path) -> Option<Self> {
+        let path = Self::file_path(root);
+        let bytes = fs::read(&path).ok()?;
+        match bincode::deserialize::<(u32, SearchIndex)>(&bytes) {
+            Ok((SEARCH_VERSION, index)) => {
+                debug!(note_count = index.ids.len(), "Loaded search index");
+                Some(index)
+            }
+            Ok((version, _)) => {
+                info!(version, "Discarding search index from another version");
+                None
+            }
+            Err(e) => {
+                warn!(path = ?path, error = %e, "Discarding unreadable search index");
+                None
+            }
+        }
+    }
+
+    /// Writes the search index to `.slate/search.bin`.
+    pub fn save(&self, root: &Path) -> Result<(), SlateError> {
+        let bytes = bincode::serialize(&(SEARCH_VERSION, self)).map_err(|e| {
+            SlateError::internal(format!("Failed to serialize search index: {}", e))
+        })?;
+        write_slate_file(root, SEARCH_FILE, &bytes)
+    }
+
+    /// Builds a search index from scratch by reading every note in the
+    /// vault, for use without an open vault.
+    pub fn build(root: &Path) -> Self {
+        let mut index = SearchIndex::default();
+        for file in super::markdown_files(root) {
+            let Ok(path) = file.strip_prefix(root) else {
+                continue;
+            };
+            let Ok(bytes) = fs::read(&file) else {
+                continue;
+            };
+            let path = path.to_string_lossy().into_owned();
+            index.insert(Analyzed::new(
+                path,
+                hash_bytes(&bytes),
+                &String::from_utf8_lossy(&bytes),
+            ));
+        }
+        index
+    }
+
+    /// Number of indexed notes.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Compares the indexed content hashes with the vault index.
+    pub fn stale(&self, index: &VaultIndex) -> Stale {
+        index.stale(self.ids.keys(), |path| {
+            self.doc(path).map(|doc| doc.hash.as_str())
+        })
+    }
+
+    fn doc(&self, path: &str) -> Option<&Doc> {
+        self.docs[*self.ids.get(path)? as usize].as_ref()
+    }
+
+    /// Adds a note, replacing any earlier version of it.
+    pub fn insert(&mut self, note: Analyzed) {
+        self.remove(&note.path);
+
+        let id = self.free.pop().unwrap_or(self.docs.len() as DocId);
+        let stems = note.positions.keys().cloned().collect();
+        for (stem, positions) in note.positions {
+            self.postings.entry(stem).or_default().insert(id, positions);
+        }
+        self.vocabulary.extend(note.words);
+        self.total_len += note.len as u64;
+
+        let doc = Doc {
+            path: note.path.clone(),
+            hash: note.hash,
+            len: note.len,
+            stems,
+        };
+        match self.docs.get_mut(id as usize) {
+            Some(slot) => *slot = Some(doc),
+            None => self.docs.push(Some(doc)),
+        }
+        self.ids.insert(note.path, id);
+    }
+
+    pub fn remove(&mut self, path: &str) {
+        let Some(id) = self.ids.remove(path) else {
+            return;
+        };
+        let Some(doc) = self.docs[id as usize].take() else {
+            return;
+        };
+        for stem in &doc.stems {
+            if let Some(docs) = self.postings.get_mut(stem) {
+                docs.remove(&id);
+                if docs.is_empty() {
+                    self.postings.remove(stem);
+                }
+            }
+        }
+        self.total_len -= doc.len as u64;
+        self.free.push(id);
+    }
+
+    /// Occurrences of `term` per note.
+    fn frequencies(&self, term: &TextTerm) -> HashMap<DocId, u32> {
+        match term {
+            TextTerm::Word(stem) => self
+                .postings
+                .get(stem)
+                .map(|docs| {
+                    docs.iter()
+                        .map(|(&id, positions)| (id, positions.len() as u32))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            TextTerm::Prefix(prefix) => {
+                let stems: BTreeSet<&String> = self
+                    .vocabulary
+                    .range(prefix.clone()..)
+                    .take_while(|(word, _)| word.starts_with(prefix.as_str()))
+                    .map(|(_, stem)| stem)
+                    .collect();
+                let mut frequencies = HashMap::new();
+                for docs in stems.into_iter().filter_map(|stem| self.postings.get(stem)) {
+                    for (&id, positions) in docs {
+                        *frequencies.entry(id).or_default() += positions.len() as u32;
+                    }
+                }
+                frequencies
+            }
+            TextTerm::Phrase(stems) => {
+                let Some(lists) = stems
+                    .iter()
+                    .map(|stem| self.postings.get(stem))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    return HashMap::new();
+                };
+                let Some((first, rest)) = lists.split_first() else {
+                    return HashMap::new();
+                };
+                first
+                    .iter()
+                    .filter_map(|(&id, starts)| {
+                        let rest = rest
+                            .iter()
+                            .map(|docs| docs.get(&id))
+                            .collect::<Option<Vec<_>>>()?;
+                        let count = starts
+                            .iter()
+                            .filter(|&&start| {
+                                rest.iter().enumerate().all(|(i, positions)| {
+                                    positions.binary_search(&(start + i as u32 + 1)).is_ok()
+                                })
+                            })
+                            .count() as u32;
+                        (count > 0).then_some((id, count))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Relative paths of every indexed note.
+    pub fn paths(&self) -> impl Iterator<Item = &String> {
+        self.ids.keys()
+    }
+
+    /// Relative paths of the notes containing `term`.
+    pub fn matching(&self, term: &TextTerm) -> impl Iterator<Item = &String> {
+        self.frequencies(term)
+            .into_keys()
+            .filter_map(|id| self.docs[id as usize].as_ref().map(|doc| &doc.path))
+    }
+
+    /// BM25 score of each note containing any of `terms`, by relative path.
+    pub fn scores(&self, terms: &[TextTerm]) -> HashMap<String, f32> {
+        let notes = self.ids.len() as f32;
+        let average_len = self.total_len as f32 / notes.max(1.0);
+        let mut scores = HashMap::new();
+
+        for term in terms {
+            let frequencies = self.frequencies(term);
+            let matching = frequencies.len() as f32;
+            let idf = ((notes - matching + 0.5) / (matching + 0.5)).ln_1p();
+
+            for (id, tf) in frequencies {
+                let Some(doc) = self.docs[id as usize].as_ref() else {
+                    continue;
+                };
+                let tf = tf as f32;
+                let norm = K1 * (1.0 - B + B * doc.len as f32 / average_len);
+                *scores.entry(doc.path.clone()).or_default() += idf * tf * (K1 + 1.0) / (tf + norm);
+            }
+        }
+        scores
+    }
+}
+
+/// A single full-text search term.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TextTerm {
+    /// A stemmed word.
+    Word(String),
+    /// Any word starting with this (lowercased, unstemmed) text.
+    Prefix(String),
+    /// Stemmed words that must appear next to each other, in order.
+    Phrase(Vec<String>),
+}
+
+impl TextTerm {
+    /// The term for a piece of query text. Text that tokenizes to several
+    /// words is a phrase; `prefix` only applies to a single word.
+    pub fn from_text(text: &str, prefix: bool) -> Option<Self> {
+        let mut tokens = tokenize(text);
+        match tokens.len() {
+            0 => None,
+            1 => {
+                let token = tokens.remove(0);
+                Some(if prefix {
+                    TextTerm::Prefix(token.word)
+                } else {
+                    TextTerm::Word(token.stem)
+                })
+            }
+            _ => Some(TextTerm::Phrase(
+                tokens.into_iter().map(|token| token.stem).collect(),
+            )),
+        }
+    }
+
+    /// If the term matches starting at `tokens[i]`, the byte offset where the
+    /// match ends.
+    pub fn match_at(&self, tokens: &[Token], i: usize) -> Option<usize> {
+        let token = &tokens[i];
+        match self {
+            TextTerm::Word(stem) => (token.stem == *stem).then_some(token.end),
+            TextTerm::Prefix(prefix) => {
+                token.word.starts_with(prefix.as_str()).then_some(token.end)
+            }
+            TextTerm::Phrase(stems) => tokens
+                .get(i..i + stems.len())
+                .filter(|words| words.iter().zip(stems).all(|(t, s)| t.stem == *s))
+                .and_then(|words| words.last())
+                .map(|last| last.end),
+        }
+    }
+
+    /// True if the term matches anywhere in `tokens`.
+    pub fn matches(&self, tokens: &[Token]) -> bool {
+        (0..tokens.len()).any(|i| self.match_at(tokens, i).is_some())
+    }
+}
+
+/// Byte ranges of the words in `text` that match any of `terms`, sorted and
+/// merged where they overlap.
+pub fn highlight(text: &str, terms: &[TextTerm]) -> Vec<(usize, usize)> {
+    let tokens = tokenize(text);
+    let mut ranges = Vec::new();
+    for i in 0..tokens.len() {
+        for term in terms {
+            if let Some(end) = term.match_at(&tokens, i) {
+                ranges.push((tokens[i].start, end));
+            }
+        }
+    }
+    merge_ranges(ranges)
+}
+
+/// Sorts byte ranges and merges the ones that overlap.
+pub fn merge_ranges(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// A line of a note around one or more matches.
+#[derive(Clone, Debug, Serialize)]
+pub struct Snippet {
+    /// 1-based line number.
+    pub line: usize,
+    pub text: String,
+    /// Matched ranges of `text` as [start, end), in UTF-16 code units so
+    /// they can be used directly on JavaScript strings.
+    pub highlights: Vec<(usize, usize)>,
+}
+
+/// Snippets for the first lines of `text` with a match in `ranges`.
+pub fn snippets(text: &str, ranges: &[(usize, usize)]) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    let mut remaining = ranges;
+    let mut line_start = 0;
+
+    for (number, line) in text.split_inclusive('\n').enumerate() {
+        if snippets.len() == MAX_SNIPPETS || remaining.is_empty() {
+            break;
+        }
+        let content = line.trim_end_matches(['\n', '\r']);
+        let line_end = line_start + content.len();
+
+        let count = remaining
+            .iter()
+            .take_while(|(start, _)| *start < line_start + line.len())
+            .count();
+        let in_line: Vec<(usize, usize)> = remaining[..count]
+            .iter()
+            .filter(|(start, _)| *start < line_end)
+            .map(|&(start, end)| (start - line_start, end.min(line_end) - line_start))
+            .collect();
+        remaining = &remaining[count..];
+
+        if !in_line.is_empty() {
+            snippets.push(snippet(content, number + 1, &in_line));
+        }
+        line_start += line.len();
+    }
+
+    snippets
+}
+
+/// Cuts a long line down to the text around its first match.
+pub(crate) fn snippet(line: &str, number: usize, ranges: &[(usize, usize)]) -> Snippet {
+    let (first_start, first_end) = ranges[0];
+    let from = floor_char_boundary(line, first_start.saturating_sub(SNIPPET_CONTEXT));
+    let to = ceil_char_boundary(line, (first_end + SNIPPET_CONTEXT).min(line.len()));
+
+    let mut text = String::new();
+    if from > 0 {
+        text.push('…');
+    }
+    let offset = text.encode_utf16().count();
+    text.push_str(&line[from..to]);
+    if to < line.len() {
+        text.push('…');
+    }
+
+    let utf16 = |byte: usize| offset + line[from..byte].encode_utf16().count();
+    let highlights = ranges
+        .iter()
+        .filter(|(start, _)| *start < to)
+        .map(|&(start, end)| (utf16(start), utf16(end.min(to))))
+        .collect();
+
+    Snippet {
+        line: number,
+        text,
+        highlights,
+    }
+}
+
+fn floor_char_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn ceil_char_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i += 1;
+    }
+    i
+}
+
+/// Brings an open vault's search index in line with its vault index,
+/// reading only notes whose content hash changed. Notes are read and
+/// tokenized without holding the registry lock.
+pub(crate) fn refresh(registry: &VaultRegistry, vault: &VaultHandle) {
+    let root = vault.root();
+    if registry.with_open(vault, |open| open.search.is_none()) == Some(true) {
+        let loaded = SearchIndex::load(root).unwrap_or_default();
+        registry.with_open(vault, |open| {
+            open.search.get_or_insert(loaded);
+        });
+    }
+
+    let Some(mut stale) = registry
+        .with_open(vault, |open| {
+            Some(open.search.as_ref()?.stale(open.index.as_ref()?))
+        })
+        .flatten()
+    else {
+        return;
+    };
+    if stale.is_empty() {
+        return;
+    }
+
+    let start = Instant::now();
+    let mut analyzed = Vec::with_capacity(stale.changed.len());
+    for path in std::mem::take(&mut stale.changed) {
+        match fs::read(root.join(&path)) {
+            Ok(bytes) => {
+                let hash = hash_bytes(&bytes);
+                analyzed.push(Analyzed::new(path, hash, &String::from_utf8_lossy(&bytes)));
+            }
+            // Gone since the vault index saw it; the next refresh drops it
+            Err(_) => stale.removed.push(path),
+        }
+    }
+
+    let indexed = analyzed.len();
+    registry.with_open(vault, |open| {
+        let Some(search) = open.search.as_mut() else {
+            return;
+        };
+        for path in &stale.removed {
+            search.remove(path);
+        }
+        for note in analyzed {
+            search.insert(note);
+        }
+        if let Err(e) = search.save(root) {
+            warn!(error = %e, "Failed to persist search index");
+        }
+    });
+
+    info!(
+        indexed,
+        removed = stale.removed.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Updated search index"
+    );
+}
+
+/// A note matching a search, with the lines that matched.
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchHit {
+    pub file: FileEntry,
+    pub score: f32,
+    pub snippets: Vec<Snippet>,
+}
+
+/// Searches an open vault's notes with the query language in [`query`].
+///
+/// Words are stemmed, so "running" finds "runs"; `"quoted words"` must
+/// appear together and `word*` matches any word starting with `word`.
+/// Operators narrow the search to paths, file names, tags, lines, sections
+/// or tasks. Results are ranked by BM25 over the words the query looks for.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn search_vault(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>, SlateError> {
+    let start = Instant::now();
+    let query = query::parse(&query)?;
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (candidates, scores) = registry
+        .with_open(&vault, |open| {
+            open.search
+                .as_ref()
+                .map(|search| candidates(search, &query))
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Search index"))?;
+
+    // Evaluate the query outside the registry lock
+    let hits = rank(&vault, &query, candidates, &scores, limit);
+    info!(
+        hits = hits.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Search complete"
+    );
+    Ok(hits)
+}
+
+/// Searches a vault's notes with `index`, for use without an open vault.
+pub fn search(
+    index: &SearchIndex,
+    vault: &VaultHandle,
+    query: &Query,
+    limit: Option<usize>,
+) -> Vec<SearchHit> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let (candidates, scores) = candidates(index, query);
+    rank(vault, query, candidates, &scores, limit)
+}
+
+/// The notes that may match `query`, and the BM25 score of every note with
+/// one of the words it looks for.
+fn candidates(search: &SearchIndex, query: &Query) -> (HashSet<String>, HashMap<String, f32>) {
+    let candidates = query
+        .candidates(search)
+        .unwrap_or_else(|| search.paths().cloned().collect());
+    (candidates, search.scores(&query.text_terms()))
+}
+
+/// Checks each candidate against `query` and returns the best `limit`, with
+/// snippets. Notes are read from disk only if the query looks at their
+/// contents.
+fn rank(
+    vault: &VaultHandle,
+    query: &Query,
+    candidates: HashSet<String>,
+    scores: &HashMap<String, f32>,
+    limit: Option<usize>,
+) -> Vec<SearchHit> {
+    let mut matched: Vec<(String, f32, Option<String>)> = candidates
+        .into_iter()
+        .filter_map(|path| {
+            let content = if query.needs_content() {
+                Some(note::read(&vault.join(&path).ok()?).ok()?.content)
+            } else {
+                None
+            };
+            let note = query::Note {
+                path: &path,
+                text: content.as_deref().unwrap_or_default(),
+            };
+            if !query.matches(&note) {
+                return None;
+            }
+            let score = scores.get(&path).copied().unwrap_or_default();
+            Some((path, score, content))
+        })
+        .collect();
+
+    matched.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    matched.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+
+    matched
+        .into_iter()
+        .filter_map(|(path, score, content)| {
+            let full_path = vault.join(&path).ok()?;
+            let snippets = content
+                .map(|content| snippets(&content, &query.highlights(&content)))
+                .unwrap_or_default();
+            Some(SearchHit {
+                file: FileEntry::new(vault.root(), &full_path),
+                score,
+                snippets,
+            })
+        })
+        .collect()
+}
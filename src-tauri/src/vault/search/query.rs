@@ -0,0 +1,588 @@
+//! Search query language - parses queries like
+//! `tag:#work -path:archive (meeting OR standup) line:("due" /\d{4}/)` into an
+//! AST, and evaluates it against notes
+
+use super::{highlight, merge_ranges, tokenize, SearchIndex, TextTerm, Token};
+use crate::vault::{links, markdown, tags};
+use regex::Regex;
+use serde::Serialize;
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// A parsed search query.
+#[derive(Clone, Debug)]
+pub enum Query {
+    /// Every part must match. Empty for an empty query.
+    And(Vec<Query>),
+    /// Any part must match.
+    Or(Vec<Query>),
+    /// `-query`
+    Not(Box<Query>),
+    /// A word, `prefix*` or `"quoted phrase"`.
+    Text(TextTerm),
+    /// `/regex/`, matched against the raw text.
+    Regex(Regex),
+    /// `path:...`, matched against the note's path relative to the vault.
+    Path(Pattern),
+    /// `file:...`, matched against the note's file name.
+    File(Pattern),
+    /// `tag:#name`, also matching nested tags such as `#name/sub`.
+    Tag(String),
+    /// `line:(...)`: some single line matches.
+    Line(Box<Query>),
+    /// `section:(...)`: some section, from one heading to the next, matches.
+    Section(Box<Query>),
+    /// `task:(...)`, `task-todo:(...)`, `task-done:(...)`: some task, in the
+    /// given state if any, matches.
+    Task {
+        done: Option<bool>,
+        query: Box<Query>,
+    },
+}
+
+/// What `path:` and `file:` match against.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// Case-insensitive substring (stored lowercased).
+    Text(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn matches(&self, text: &str) -> bool {
+        match self {
+            Pattern::Text(needle) => text.to_lowercase().contains(needle.as_str()),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// A query that failed to parse.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct QueryError {
+    pub message: String,
+    /// The offending part of the query as [start, end), in UTF-16 code units
+    /// so the UI can underline it directly.
+    pub start: usize,
+    pub end: usize,
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (at {}..{})", self.message, self.start, self.end)
+    }
+}
+
+/// Parses a search query.
+///
+/// Terms separated by spaces must all match; `OR` between terms makes either
+/// enough, and binds looser than the implicit AND. Parentheses group, and a
+/// leading `-` negates a term or group.
+pub fn parse(query: &str) -> Result<Query, QueryError> {
+    let mut parser = Parser { src: query, pos: 0 };
+    let parsed = parser.or()?;
+    parser.skip_whitespace();
+    if parser.pos < query.len() {
+        // or() only stops early at a ')' it has no '(' for
+        return Err(parser.error(parser.pos, parser.pos + 1, "Unmatched ')'"));
+    }
+    Ok(parsed)
+}
+
+/// Operators that take an argument after a colon.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Path,
+    File,
+    Tag,
+    Line,
+    Section,
+    Task(Option<bool>),
+}
+
+impl Operator {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "path" => Operator::Path,
+            "file" => Operator::File,
+            "tag" => Operator::Tag,
+            "line" => Operator::Line,
+            "section" => Operator::Section,
+            "task" => Operator::Task(None),
+            "task-todo" => Operator::Task(Some(false)),
+            "task-done" => Operator::Task(Some(true)),
+            _ => return None,
+        })
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, start: usize, end: usize, message: impl Into<String>) -> QueryError {
+        let utf16 = |byte: usize| self.src[..byte.min(self.src.len())].encode_utf16().count();
+        QueryError {
+            message: message.into(),
+            start: utf16(start),
+            end: utf16(end),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// True if the next word is the `OR` keyword.
+    fn at_or(&self) -> bool {
+        let rest = self.rest();
+        rest.starts_with("OR")
+            && rest[2..]
+                .chars()
+                .next()
+                .is_none_or(|c| c.is_whitespace() || matches!(c, '(' | ')'))
+    }
+
+    fn or(&mut self) -> Result<Query, QueryError> {
+        let mut branches = vec![self.and()?];
+        loop {
+            self.skip_whitespace();
+            if !self.at_or() {
+                break;
+            }
+            let (start, end) = (self.pos, self.pos + 2);
+            if is_empty(&branches[branches.len() - 1]) {
+                return Err(self.error(start, end, "Nothing before OR"));
+            }
+            self.pos = end;
+            let branch = self.and()?;
+            if is_empty(&branch) {
+                return Err(self.error(start, end, "Nothing after OR"));
+            }
+            branches.push(branch);
+        }
+        Ok(if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            Query::Or(branches)
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, QueryError> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek().is_none_or(|c| c == ')') || self.at_or() {
+                break;
+            }
+            parts.push(self.unary()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Query::And(parts)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        if self.peek() == Some('-') {
+            let start = self.pos;
+            self.pos += 1;
+            if self.peek().is_none_or(|c| c.is_whitespace() || c == ')') {
+                return Err(self.error(start, self.pos, "Nothing to negate after '-'"));
+            }
+            return Ok(Query::Not(Box::new(self.atom()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Query, QueryError> {
+        match self.peek() {
+            Some('(') => self.group(),
+            Some('"') => {
+                let start = self.pos;
+                let phrase = self.quoted()?;
+                TextTerm::from_text(phrase, false)
+                    .map(Query::Text)
+                    .ok_or_else(|| self.error(start, self.pos, "Nothing to search for in quotes"))
+            }
+            Some('/') => Ok(Query::Regex(self.regex()?)),
+            _ => self.word_or_operator(),
+        }
+    }
+
+    /// `( query )`
+    fn group(&mut self) -> Result<Query, QueryError> {
+        let start = self.pos;
+        self.pos += 1;
+        let inner = self.or()?;
+        self.skip_whitespace();
+        if self.peek() != Some(')') {
+            return Err(self.error(start, start + 1, "Unclosed '('"));
+        }
+        self.pos += 1;
+        if is_empty(&inner) {
+            return Err(self.error(start, self.pos, "Empty group"));
+        }
+        Ok(inner)
+    }
+
+    /// `"text"`, returning the text between the quotes.
+    fn quoted(&mut self) -> Result<&'a str, QueryError> {
+        let start = self.pos;
+        let Some(len) = self.src[start + 1..].find('"') else {
+            return Err(self.error(start, self.src.len(), "Unclosed quote"));
+        };
+        self.pos = start + 1 + len + 1;
+        Ok(&self.src[start + 1..start + 1 + len])
+    }
+
+    /// `/pattern/`, where `\/` stands for a literal slash.
+    fn regex(&mut self) -> Result<Regex, QueryError> {
+        let start = self.pos;
+        let mut pattern = String::new();
+        let mut chars = self.src[start + 1..].char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((i, '/')) => break start + 1 + i,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, '/')) => pattern.push('/'),
+                    Some((_, c)) => {
+                        pattern.push('\\');
+                        pattern.push(c);
+                    }
+                    None => pattern.push('\\'),
+                },
+                Some((_, c)) => pattern.push(c),
+                None => {
+                    return Err(self.error(start, self.src.len(), "Unclosed regular expression"))
+                }
+            }
+        };
+        self.pos = end + 1;
+
+        if pattern.is_empty() {
+            return Err(self.error(start, self.pos, "Empty regular expression"));
+        }
+        Regex::new(&pattern).map_err(|e| {
+            let reason = e.to_string();
+            let reason = reason.lines().last().unwrap_or_default().trim();
+            let reason = reason.strip_prefix("error: ").unwrap_or(reason);
+            self.error(
+                start,
+                self.pos,
+                format!("Invalid regular expression: {}", reason),
+            )
+        })
+    }
+
+    /// A bare word, or `operator:argument`.
+    fn word_or_operator(&mut self) -> Result<Query, QueryError> {
+        let start = self.pos;
+        let rest = self.rest();
+
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphabetic() || c == '-'))
+            .unwrap_or(rest.len());
+        if rest[name_len..].starts_with(':') {
+            if let Some(operator) = Operator::from_name(&rest[..name_len]) {
+                self.pos += name_len + 1;
+                return self.argument(operator, start);
+            }
+        }
+
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+            .unwrap_or(rest.len());
+        let word = &rest[..len];
+        self.pos += len;
+        let term = match word.strip_suffix('*') {
+            Some(prefix) => TextTerm::from_text(prefix, true),
+            None => TextTerm::from_text(word, false),
+        };
+        term.map(Query::Text).ok_or_else(|| {
+            self.error(
+                start,
+                self.pos,
+                format!("Nothing to search for in '{}'", word),
+            )
+        })
+    }
+
+    fn argument(&mut self, operator: Operator, start: usize) -> Result<Query, QueryError> {
+        let name = &self.src[start..self.pos];
+        if self.peek().is_none_or(|c| c.is_whitespace() || c == ')') {
+            return Err(self.error(start, self.pos, format!("Missing value after {}", name)));
+        }
+
+        let scoped = |query: Query| Box::new(query);
+        match operator {
+            Operator::Line => Ok(Query::Line(scoped(self.atom()?))),
+            Operator::Section => Ok(Query::Section(scoped(self.atom()?))),
+            Operator::Task(done) => Ok(Query::Task {
+                done,
+                query: scoped(self.atom()?),
+            }),
+            Operator::Path => Ok(Query::Path(self.pattern(name)?)),
+            Operator::File => Ok(Query::File(self.pattern(name)?)),
+            Operator::Tag => {
+                let value_start = self.pos;
+                let tag = match self.pattern(name)? {
+                    Pattern::Text(tag) => tag,
+                    Pattern::Regex(_) => {
+                        return Err(self.error(
+                            value_start,
+                            self.pos,
+                            "tag: takes a tag name, not a regular expression",
+                        ))
+                    }
+                };
+                let tag = tag.trim_start_matches('#').to_string();
+                if tag.is_empty() {
+                    return Err(self.error(start, self.pos, "Missing tag name after tag:"));
+                }
+                Ok(Query::Tag(tag))
+            }
+        }
+    }
+
+    /// The argument of `path:`, `file:` or `tag:`: a word, quoted text or a
+    /// regex.
+    fn pattern(&mut self, name: &str) -> Result<Pattern, QueryError> {
+        let start = self.pos;
+        let text = match self.peek() {
+            Some('"') => self.quoted()?,
+            Some('/') => return Ok(Pattern::Regex(self.regex()?)),
+            Some('(') => {
+                return Err(self.error(
+                    self.pos,
+                    self.pos + 1,
+                    format!("{} takes a word, quoted text or /regex/, not a group", name),
+                ))
+            }
+            _ => {
+                let rest = self.rest();
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')'))
+                    .unwrap_or(rest.len());
+                self.pos += len;
+                &rest[..len]
+            }
+        };
+        // An empty pattern would match every note
+        if text.is_empty() {
+            return Err(self.error(start, self.pos, format!("Missing value after {}", name)));
+        }
+        Ok(Pattern::Text(text.to_lowercase()))
+    }
+}
+
+fn is_empty(query: &Query) -> bool {
+    matches!(query, Query::And(parts) if parts.is_empty())
+}
+
+/// A note being matched against a query.
+pub struct Note<'a> {
+    /// Path relative to the vault root.
+    pub path: &'a str,
+    pub text: &'a str,
+}
+
+/// A piece of a note a query is evaluated against: the whole note, a line,
+/// a section or a task. Tokenized only if a text term needs it.
+struct Scope<'a> {
+    text: &'a str,
+    tokens: OnceCell<Vec<Token>>,
+}
+
+impl<'a> Scope<'a> {
+    fn new(text: &'a str) -> Self {
+        Scope {
+            text,
+            tokens: OnceCell::new(),
+        }
+    }
+
+    fn tokens(&self) -> &[Token] {
+        self.tokens.get_or_init(|| tokenize(self.text))
+    }
+}
+
+impl Query {
+    /// True for the empty query, which matches nothing.
+    pub fn is_empty(&self) -> bool {
+        is_empty(self)
+    }
+
+    pub fn matches(&self, note: &Note) -> bool {
+        !self.is_empty() && self.matches_in(note, &Scope::new(note.text))
+    }
+
+    fn matches_in(&self, note: &Note, scope: &Scope) -> bool {
+        match self {
+            Query::And(parts) => parts.iter().all(|q| q.matches_in(note, scope)),
+            Query::Or(parts) => parts.iter().any(|q| q.matches_in(note, scope)),
+            Query::Not(query) => !query.matches_in(note, scope),
+            Query::Text(term) => term.matches(scope.tokens()),
+            Query::Regex(regex) => regex.is_match(scope.text),
+            Query::Path(pattern) => pattern.matches(note.path),
+            Query::File(pattern) => {
+                let name = Path::new(note.path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default();
+                pattern.matches(&name)
+            }
+            Query::Tag(tag) => tags::note_tags(scope.text)
+                .iter()
+                .any(|found| tags::is_under(found, tag)),
+            Query::Line(query) => scope
+                .text
+                .lines()
+                .any(|line| query.matches_in(note, &Scope::new(line))),
+            Query::Section(query) => sections(scope.text)
+                .into_iter()
+                .any(|section| query.matches_in(note, &Scope::new(section))),
+            Query::Task { done, query } => outside_code(scope.text)
+                .filter_map(markdown::task_item)
+                .any(|item| {
+                    done.is_none_or(|done| done == item.checked())
+                        && query.matches_in(note, &Scope::new(item.text))
+                }),
+        }
+    }
+
+    /// False if the query only looks at paths, so notes need not be read.
+    pub fn needs_content(&self) -> bool {
+        match self {
+            Query::And(parts) | Query::Or(parts) => parts.iter().any(Query::needs_content),
+            Query::Not(query) => query.needs_content(),
+            Query::Path(_) | Query::File(_) => false,
+            _ => true,
+        }
+    }
+
+    /// Notes that can possibly match, worked out from the search index; None
+    /// if the index cannot narrow it down (negations, regexes).
+    pub fn candidates(&self, index: &SearchIndex) -> Option<HashSet<String>> {
+        match self {
+            Query::And(parts) => parts
+                .iter()
+                .filter_map(|q| q.candidates(index))
+                .reduce(|a, b| a.intersection(&b).cloned().collect()),
+            Query::Or(parts) => parts
+                .iter()
+                .map(|q| q.candidates(index))
+                .collect::<Option<Vec<_>>>()
+                .map(|sets| sets.into_iter().flatten().collect()),
+            Query::Not(_) | Query::Regex(_) => None,
+            Query::Text(term) => Some(index.matching(term).cloned().collect()),
+            Query::Path(pattern) => Some(
+                index
+                    .paths()
+                    .filter(|path| pattern.matches(path))
+                    .cloned()
+                    .collect(),
+            ),
+            Query::File(_) => None,
+            // The tag's words are indexed like any other text
+            Query::Tag(tag) => {
+                TextTerm::from_text(tag, false).map(|term| index.matching(&term).cloned().collect())
+            }
+            Query::Line(query) | Query::Section(query) | Query::Task { query, .. } => {
+                query.candidates(index)
+            }
+        }
+    }
+
+    /// Text terms that count towards a note matching, for ranking.
+    pub fn text_terms(&self) -> Vec<TextTerm> {
+        let mut terms = Vec::new();
+        self.collect_positive(&mut |query| {
+            if let Query::Text(term) = query {
+                terms.push(term.clone());
+            }
+        });
+        terms
+    }
+
+    /// Byte ranges of `text` to highlight: matches of the text terms and
+    /// regexes that count towards a note matching.
+    pub fn highlights(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut ranges = highlight(text, &self.text_terms());
+        self.collect_positive(&mut |query| {
+            if let Query::Regex(regex) = query {
+                ranges.extend(
+                    regex
+                        .find_iter(text)
+                        .filter(|m| !m.is_empty())
+                        .map(|m| (m.start(), m.end())),
+                );
+            }
+        });
+        merge_ranges(ranges)
+    }
+
+    /// Visits every leaf that is not under a negation.
+    fn collect_positive(&self, f: &mut impl FnMut(&Query)) {
+        match self {
+            Query::And(parts) | Query::Or(parts) => {
+                for part in parts {
+                    part.collect_positive(f);
+                }
+            }
+            Query::Not(_) => {}
+            Query::Line(query) | Query::Section(query) | Query::Task { query, .. } => {
+                query.collect_positive(f)
+            }
+            leaf => f(leaf),
+        }
+    }
+}
+
+/// The lines of `text` outside code blocks, without their line breaks.
+fn outside_code(text: &str) -> impl Iterator<Item = &str> {
+    let code = links::code_ranges(text);
+    let mut offset = 0;
+    text.split_inclusive('\n').filter_map(move |line| {
+        let start = offset;
+        offset += line.len();
+        let in_code = code.iter().any(|&(s, e)| s <= start && start < e);
+        (!in_code).then(|| line.trim_end_matches(['\n', '\r']))
+    })
+}
+
+/// Splits a note at its headings. Text before the first heading is a
+/// section of its own; headings inside code blocks do not count.
+fn sections(text: &str) -> Vec<&str> {
+    let code = links::code_ranges(text);
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let in_code = code.iter().any(|&(s, e)| s <= offset && offset < e);
+        let line_text = line.trim_end_matches(['\n', '\r']);
+        if !in_code && offset > start && markdown::heading(line_text).is_some() {
+            sections.push(&text[start..offset]);
+            start = offset;
+        }
+        offset += line.len();
+    }
+    if start < text.len() {
+        sections.push(&text[start..]);
+    }
+    sections
+}
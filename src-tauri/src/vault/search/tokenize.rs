@@ -0,0 +1,61 @@
+//! Tokenizer shared by indexing, querying and highlighting, so the three
+//! always agree on what a word is
+
+use rust_stemmers::{Algorithm, Stemmer};
+use std::sync::LazyLock;
+
+static STEMMER: LazyLock<Stemmer> = LazyLock::new(|| Stemmer::create(Algorithm::English));
+
+/// A word in a text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    /// Lowercased surface form.
+    pub word: String,
+    /// Stemmed form; what the index is keyed by.
+    pub stem: String,
+    /// Byte range in the source text.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Splits `text` into words: runs of letters and digits, allowing an
+/// apostrophe between two of them ("don't" is one word).
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let in_word = c.is_alphanumeric()
+            || (start.is_some()
+                && matches!(c, '\'' | '’')
+                && chars.peek().is_some_and(|(_, next)| next.is_alphanumeric()));
+        match (in_word, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                tokens.push(token(text, s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(token(text, s, text.len()));
+    }
+    tokens
+}
+
+fn token(text: &str, start: usize, end: usize) -> Token {
+    let word = text[start..end].to_lowercase().replace('’', "'");
+    Token {
+        stem: stem(&word),
+        word,
+        start,
+        end,
+    }
+}
+
+/// Stems a lowercased word.
+pub fn stem(word: &str) -> String {
+    STEMMER.stem(word).into_owned()
+}
@@ -0,0 +1,514 @@
+//! Tags - finds `#tags` in notes and in their frontmatter, lists them as a
+//! hierarchy and renames them across a vault
+
+use super::frontmatter::{self, PropertyValue};
+use super::index::{hash_bytes, IndexEntry};
+use super::links::{self, LinkIndex};
+use super::note::{self, BaseVersion};
+use super::watcher::{emit_change, VaultChange};
+use super::{FileEntry, VaultHandle, VaultRegistry};
+use crate::error::SlateError;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::time::Instant;
+use tauri::{AppHandle, State};
+use tracing::{info, instrument, warn};
+
+/// Frontmatter keys holding a note's tags.
+const TAG_KEYS: [&str; 2] = ["tags", "tag"];
+
+/// A `#tag` found in a note.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tag {
+    /// The tag as written, without `#`. Nested tags keep their slashes:
+    /// `project/slate`.
+    pub name: String,
+    /// Byte range of the tag in the note, `#` included.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Finds every tag in a note's text, outside code, links and frontmatter. A
+/// tag starts a word and is made of letters, digits, `_`, `-` and `/`, with
+/// at least one non-digit, so `#1` and `# Heading` are not tags.
+pub fn parse(text: &str) -> Vec<Tag> {
+    let mut code = links::code_ranges(text);
+    code.extend(frontmatter::range(text).map(|range| (range.start, range.end)));
+    // `[[#Heading]]` and `[jump](#intro)` are links, not tags
+    code.extend(
+        links::parse(text)
+            .into_iter()
+            .map(|link| (link.start, link.end)),
+    );
+    let mut tags = Vec::new();
+
+    for (i, _) in text.match_indices('#') {
+        if code.iter().any(|&(start, end)| (start..end).contains(&i)) {
+            continue;
+        }
+        let starts_word = text[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || matches!(c, '(' | '[' | ','));
+        if !starts_word {
+            continue;
+        }
+        let rest = &text[i + 1..];
+        let name = &rest[..rest.find(|c| !is_tag_char(c)).unwrap_or(rest.len())];
+        let name = name.trim_end_matches('/');
+        if name.is_empty() || name.chars().all(|c| c.is_ascii_digit() || c == '/') {
+            continue;
+        }
+        tags.push(Tag {
+            name: name.to_string(),
+            start: i,
+            end: i + 1 + name.len(),
+        });
+    }
+    tags
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+/// True if `name` (without `#`) can be written as a tag.
+fn is_valid(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('/')
+        && !name.ends_with('/')
+        && name.chars().all(is_tag_char)
+        && !name.chars().all(|c| c.is_ascii_digit() || c == '/')
+}
+
+/// Every tag of a note, from its frontmatter `tags` property and its text,
+/// each once (ignoring case) as first written.
+pub fn note_tags(text: &str) -> Vec<String> {
+    let properties = frontmatter::parse(text);
+    let from_frontmatter = properties
+        .iter()
+        .filter(|property| is_tag_key(&property.key))
+        .flat_map(|property| property_tags(&property.value));
+    let from_text = parse(text).into_iter().map(|tag| tag.name);
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in from_frontmatter.chain(from_text) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+fn is_tag_key(key: &str) -> bool {
+    TAG_KEYS.iter().any(|k| k.eq_ignore_ascii_case(key))
+}
+
+/// Tags in a frontmatter value: a list, or one text of tags separated by
+/// commas or spaces. A leading `#` is optional.
+fn property_tags(value: &PropertyValue) -> Vec<String> {
+    let texts: Vec<&str> = match value {
+        PropertyValue::Text(text) => text.split([',', ' ']).collect(),
+        PropertyValue::List(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                PropertyValue::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    texts
+        .into_iter()
+        .map(|text| text.trim().trim_start_matches('#'))
+        .filter(|name| is_valid(name))
+        .map(str::to_string)
+        .collect()
+}
+
+/// True if `tag` is `parent` or nested under it, ignoring case.
+pub fn is_under(tag: &str, parent: &str) -> bool {
+    let tag = tag.to_lowercase();
+    let parent = parent.trim_start_matches('#').to_lowercase();
+    tag == parent
+        || tag
+            .strip_prefix(&parent)
+            .is_some_and(|sub| sub.starts_with('/'))
+}
+
+/// A tag and the tags nested under it.
+#[derive(Clone, Debug, Serialize)]
+pub struct TagNode {
+    /// Last part of the tag: `alpha` for `project/alpha`.
+    pub name: String,
+    /// The whole tag, as first written.
+    pub tag: String,
+    /// Notes with exactly this tag.
+    pub count: usize,
+    /// Notes with this tag or one nested under it.
+    pub total: usize,
+    pub children: Vec<TagNode>,
+}
+
+/// Every tag in the link index as a tree of nested tags, sorted by name.
+/// Tags differing only in case are one tag. A parent that no note uses on
+/// its own still appears, with a count of 0.
+pub fn tree(index: &LinkIndex) -> Vec<TagNode> {
+    struct Counts<'a> {
+        tag: String,
+        count: usize,
+        notes: HashSet<&'a str>,
+    }
+
+    let mut counts: BTreeMap<String, Counts> = BTreeMap::new();
+    for (path, _) in index.iter() {
+        for tag in index.tags(path) {
+            let mut end = tag.len();
+            let mut exact = true;
+            loop {
+                let written = &tag[..end];
+                let counts = counts
+                    .entry(written.to_lowercase())
+                    .or_insert_with(|| Counts {
+                        tag: written.to_string(),
+                        count: 0,
+                        notes: HashSet::new(),
+                    });
+                if exact {
+                    counts.count += 1;
+                    exact = false;
+                }
+                counts.notes.insert(path);
+                match written.rfind('/') {
+                    Some(slash) => end = slash,
+                    None => break,
+                }
+            }
+        }
+    }
+
+    let mut children: BTreeMap<Option<&str>, Vec<&str>> = BTreeMap::new();
+    for key in counts.keys() {
+        let parent = key.rsplit_once('/').map(|(parent, _)| parent);
+        children.entry(parent).or_default().push(key);
+    }
+
+    fn build(
+        key: &str,
+        counts: &BTreeMap<String, Counts>,
+        children: &BTreeMap<Option<&str>, Vec<&str>>,
+    ) -> TagNode {
+        let tag = &counts[key];
+        TagNode {
+            name: tag.tag.rsplit('/').next().unwrap_or(&tag.tag).to_string(),
+            tag: tag.tag.clone(),
+            count: tag.count,
+            total: tag.notes.len(),
+            children: children
+                .get(&Some(key))
+                .map(|keys| {
+                    keys.iter()
+                        .map(|key| build(key, counts, children))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    children
+        .get(&None)
+        .map(|keys| {
+            keys.iter()
+                .map(|key| build(key, &counts, &children))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Every tag in an open vault, as a tree of nested tags with note counts.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn list_tags(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+) -> Result<Vec<TagNode>, SlateError> {
+    registry
+        .with_open(&vault, |open| open.links.as_ref().map(tree))
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Link index"))
+}
+
+/// Notes with `tag` (`#` optional, case ignored), sorted by path. Unless
+/// `nested` is false, notes with a tag nested under it count too.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn notes_with_tag(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    tag: String,
+    nested: Option<bool>,
+) -> Result<Vec<FileEntry>, SlateError> {
+    let nested = nested.unwrap_or(true);
+    let tag = tag.trim_start_matches('#');
+    let paths: Vec<String> = registry
+        .with_open(&vault, |open| {
+            let index = open.links.as_ref()?;
+            let paths = index
+                .iter()
+                .filter(|(path, _)| {
+                    index.tags(path).iter().any(|t| {
+                        if nested {
+                            is_under(t, tag)
+                        } else {
+                            t.eq_ignore_ascii_case(tag)
+                        }
+                    })
+                })
+                .map(|(path, _)| path.clone())
+                .collect();
+            Some(paths)
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Link index"))?;
+
+    let root = vault.root();
+    Ok(paths
+        .iter()
+        .map(|path| FileEntry::new(root, &root.join(path)))
+        .collect())
+}
+
+/// What a tag rename did, or would do.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagRenameReport {
+    pub from: String,
+    pub to: String,
+    /// False for a dry run, which writes nothing.
+    pub applied: bool,
+    /// True if notes already had the new tag, so the two are now one.
+    pub merged: bool,
+    /// Notes rewritten, by path.
+    pub notes: Vec<String>,
+    /// Notes that could not be rewritten: unreadable, or changed on disk
+    /// while the rename ran. They still have the old tag.
+    pub failed: Vec<String>,
+}
+
+/// Renames the tag `from` to `to` in every note, in the text and in the
+/// frontmatter, along with the tags nested under it: renaming `project` to
+/// `work` turns `#project/alpha` into `#work/alpha`. Notes that already
+/// have `to` end up with it once in their frontmatter, merging the tags.
+///
+/// With `dry_run`, nothing is written; the report lists the notes that
+/// would change.
+#[tauri::command]
+#[instrument(skip(app, registry, vault))]
+pub fn rename_tag(
+    app: AppHandle,
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    from: String,
+    to: String,
+    dry_run: Option<bool>,
+) -> Result<TagRenameReport, SlateError> {
+    let start = Instant::now();
+    let root = vault.root();
+    let (from, to) = rename_names(&from, &to)?;
+
+    let (sources, merged) = registry
+        .with_open(&vault, |open| {
+            let index = open.links.as_ref()?;
+            let mut sources = Vec::new();
+            let mut merged = false;
+            for (path, _) in index.iter() {
+                let tags = index.tags(path);
+                if tags.iter().any(|tag| is_under(tag, &from)) {
+                    sources.push(path.clone());
+                }
+                merged |= tags
+                    .iter()
+                    .any(|tag| is_under(tag, &to) && !is_under(tag, &from));
+            }
+            Some((sources, merged))
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Link index"))?;
+
+    // Work out every rewrite before touching anything
+    let mut planned = Vec::new();
+    let mut failed = Vec::new();
+    for path in sources {
+        let bytes = match fs::read(root.join(&path)) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!(note = %path, error = %e, "Failed to read note to retag");
+                failed.push(path);
+                continue;
+            }
+        };
+        let hash = hash_bytes(&bytes);
+        let Ok(text) = String::from_utf8(bytes) else {
+            warn!(note = %path, "Note to retag is not valid UTF-8");
+            failed.push(path);
+            continue;
+        };
+        let text = note::normalize_newlines(&text);
+        match retag(&text, &from, &to) {
+            Ok(rewritten) if rewritten != text => planned.push((path, hash, rewritten)),
+            Ok(_) => {}
+            Err(e) => {
+                warn!(note = %path, error = %e, "Failed to retag note");
+                failed.push(path);
+            }
+        }
+    }
+
+    if dry_run.unwrap_or(false) {
+        return Ok(TagRenameReport {
+            notes: planned.into_iter().map(|(path, _, _)| path).collect(),
+            from,
+            to,
+            applied: false,
+            merged,
+            failed,
+        });
+    }
+
+    let mut written: Vec<(String, IndexEntry)> = Vec::new();
+    for (path, hash, content) in planned {
+        let full_path = vault.join(&path)?;
+        let base = BaseVersion {
+            hash: Some(hash),
+            mtime: None,
+        };
+        match note::check_base(&full_path, &base, &content) {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => {
+                warn!(note = %path, "Note changed on disk during tag rename; not retagged");
+                failed.push(path);
+                continue;
+            }
+            Err(e) => {
+                warn!(note = %path, error = %e, "Failed to reread note; not retagged");
+                failed.push(path);
+                continue;
+            }
+        }
+        match note::write_atomic(&full_path, &content, |entry| {
+            registry.record_write(&vault, &path, entry)
+        }) {
+            Ok(entry) => written.push((path, entry)),
+            Err(e) => {
+                warn!(note = %path, error = %e, "Failed to retag note");
+                failed.push(path);
+            }
+        }
+    }
+
+    registry.with_open(&vault, |open| {
+        let Some(index) = open.index.as_mut() else {
+            return;
+        };
+        for (path, entry) in &written {
+            index.insert(path.clone(), entry.clone());
+        }
+        if let Err(e) = index.save(root) {
+            warn!(error = %e, "Failed to persist vault index");
+        }
+    });
+    super::refresh_derived(&registry, &vault);
+
+    // Own writes are not reported by the watcher, so tell the frontend here
+    for (path, _) in &written {
+        let file = FileEntry::new(root, &root.join(path));
+        emit_change(&app, &vault, VaultChange::Modified(file));
+    }
+
+    info!(
+        notes = written.len(),
+        failed = failed.len(),
+        merged,
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Renamed tag"
+    );
+    Ok(TagRenameReport {
+        from,
+        to,
+        applied: true,
+        merged,
+        notes: written.into_iter().map(|(path, _)| path).collect(),
+        failed,
+    })
+}
+
+/// The tags of a rename without their `#`, or an error if either is not a
+/// tag or `to` is nested under `from`.
+pub fn rename_names(from: &str, to: &str) -> Result<(String, String), SlateError> {
+    let from = from.trim().trim_start_matches('#').to_string();
+    let to = to.trim().trim_start_matches('#').to_string();
+    if !is_valid(&from) {
+        return Err(SlateError::invalid_input(format!("Not a tag: #{}", from)));
+    }
+    if !is_valid(&to) {
+        return Err(SlateError::invalid_input(format!(
+            "Not a valid tag: #{}",
+            to
+        )));
+    }
+    if from == to {
+        return Err(SlateError::invalid_input(format!("Tag is already #{}", to)));
+    }
+    // Case-only renames are fine; moving a tag under itself is not
+    if is_under(&to, &from) && !to.eq_ignore_ascii_case(&from) {
+        return Err(SlateError::invalid_input(format!(
+            "Cannot move #{} under itself",
+            from
+        )));
+    }
+    Ok((from, to))
+}
+
+/// `text` with the tag `from`, and the tags nested under it, renamed to
+/// `to` in the text and in the frontmatter.
+pub fn retag(text: &str, from: &str, to: &str) -> Result<String, SlateError> {
+    let renamed = |tag: &str| -> Option<String> {
+        if !is_under(tag, from) {
+            return None;
+        }
+        let nested: String = tag.chars().skip(from.chars().count()).collect();
+        Some(format!("{}{}", to, nested))
+    };
+
+    let mut rewritten = String::with_capacity(text.len());
+    let mut copied = 0;
+    for tag in parse(text) {
+        if let Some(new) = renamed(&tag.name) {
+            rewritten.push_str(&text[copied..tag.start + 1]);
+            rewritten.push_str(&new);
+            copied = tag.end;
+        }
+    }
+    rewritten.push_str(&text[copied..]);
+
+    for property in frontmatter::parse(&rewritten) {
+        if !is_tag_key(&property.key) {
+            continue;
+        }
+        let old = property_tags(&property.value);
+        if !old.iter().any(|tag| is_under(tag, from)) {
+            continue;
+        }
+        let mut tags: Vec<String> = Vec::new();
+        for tag in old {
+            let tag = renamed(&tag).unwrap_or(tag);
+            if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                tags.push(tag);
+            }
+        }
+        let value = PropertyValue::List(tags.into_iter().map(PropertyValue::Text).collect());
+        rewritten = frontmatter::set(&rewritten, &property.key, Some(&value))?;
+    }
+    Ok(rewritten)
+}
@@ -0,0 +1,631 @@
+//! Tasks - finds `- [ ]` items across a vault with their dates, priority
+//! and tags, keeps them in `.slate/`, and answers task queries
+
+use super::frontmatter;
+use super::index::{hash_bytes, write_slate_file, Stale, SLATE_DIR};
+use super::links;
+use super::markdown::{self, task_item};
+use super::note::{self, BaseVersion};
+use super::tags;
+use super::watcher::{emit_change, VaultChange};
+use super::{FileEntry, VaultHandle, VaultIndex, VaultRegistry};
+use crate::error::SlateError;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use tauri::{AppHandle, State};
+use tracing::{debug, info, instrument, warn};
+
+const TASKS_FILE: &str = "tasks.bin";
+/// Bump whenever the on-disk layout or the parser changes; older task
+/// indexes are rebuilt.
+const TASKS_VERSION: u32 = 2;
+
+/// Dates a task can carry, by their emoji and by their field name.
+const DATES: [(&str, &str, DateKind); 4] = [
+    ("📅", "due", DateKind::Due),
+    ("⏳", "scheduled", DateKind::Scheduled),
+    ("🛫", "start", DateKind::Start),
+    ("✅", "completion", DateKind::Done),
+];
+
+const PRIORITIES: [(&str, Priority); 5] = [
+    ("🔺", Priority::Highest),
+    ("⏫", Priority::High),
+    ("🔼", Priority::Medium),
+    ("🔽", Priority::Low),
+    ("⏬", Priority::Lowest),
+];
+
+#[derive(Clone, Copy)]
+enum DateKind {
+    Due,
+    Scheduled,
+    Start,
+    Done,
+}
+
+/// How urgent a task is. Tasks without one sit between medium and low.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Priority {
+    Highest,
+    High,
+    Medium,
+    Low,
+    Lowest,
+}
+
+impl Priority {
+    fn parse(s: &str) -> Option<Self> {
+        PRIORITIES
+            .iter()
+            .map(|&(_, priority)| priority)
+            .find(|priority| format!("{:?}", priority).eq_ignore_ascii_case(s))
+    }
+
+    /// Higher is more urgent; no priority is 0.
+    fn rank(priority: Option<Self>) -> i8 {
+        match priority {
+            Some(Priority::Highest) => 3,
+            Some(Priority::High) => 2,
+            Some(Priority::Medium) => 1,
+            None => 0,
+            Some(Priority::Low) => -1,
+            Some(Priority::Lowest) => -2,
+        }
+    }
+}
+
+/// A task list item.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    /// Stays the same while the task's text does, wherever the task moves
+    /// in its note and whether or not it is checked.
+    pub id: String,
+    /// Relative path of the note the task is in.
+    pub path: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// What is inside the brackets: ` `, `x`, or another marker such as
+    /// `-` (cancelled) or `/` (in progress).
+    pub status: char,
+    /// True if checked with `x` or `X`.
+    pub completed: bool,
+    /// The text after the checkbox, as written.
+    pub text: String,
+    /// Headings the task is under, outermost first.
+    pub headings: Vec<String>,
+    /// Dates as `YYYY-MM-DD`, from `📅 2026-10-20` or `due:: 2026-10-20`
+    /// and their scheduled, start and done counterparts.
+    pub due: Option<String>,
+    pub scheduled: Option<String>,
+    pub start: Option<String>,
+    pub done: Option<String>,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+    /// The task this one is nested under, if any.
+    pub parent: Option<String>,
+    /// Tasks nested directly under this one.
+    pub subtasks: Vec<String>,
+}
+
+/// Finds every task in a note, outside code and frontmatter.
+pub fn parse(path: &str, text: &str) -> Vec<Task> {
+    let code = links::code_ranges(text);
+    let skip_to = frontmatter::range(text).map_or(0, |range| range.end);
+
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    // List items a later, deeper indented task could be nested under, by
+    // indent, with their index in `tasks` if they are tasks
+    let mut open: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut offset = 0;
+
+    for (i, raw) in text.split_inclusive('\n').enumerate() {
+        let start = offset;
+        offset += raw.len();
+        if start < skip_to || code.iter().any(|&(s, e)| s <= start && start < e) {
+            continue;
+        }
+        let line = raw.trim_end_matches(['\n', '\r']);
+
+        if let Some((level, heading)) = markdown::heading(line) {
+            headings.retain(|&(l, _)| l < level);
+            headings.push((level, heading.to_string()));
+            open.clear();
+            continue;
+        }
+        let Some(item) = task_item(line) else {
+            if let Some(indent) = markdown::list_item(line) {
+                open.retain(|&(i, _)| i < indent);
+                open.push((indent, None));
+            } else if !line.trim().is_empty() && !line.starts_with([' ', '\t']) {
+                // A paragraph ends the list; indented text continues it
+                open.clear();
+            }
+            continue;
+        };
+
+        open.retain(|&(indent, _)| indent < item.indent);
+        let parent = open.last().and_then(|&(_, index)| index);
+        let n = seen.entry(item.text.to_string()).or_insert(0);
+        let id = task_id(path, item.text, *n);
+        *n += 1;
+
+        let mut task = Task {
+            id,
+            path: path.to_string(),
+            line: i + 1,
+            status: item.status,
+            completed: matches!(item.status, 'x' | 'X'),
+            text: item.text.to_string(),
+            headings: headings.iter().map(|(_, h)| h.clone()).collect(),
+            due: None,
+            scheduled: None,
+            start: None,
+            done: None,
+            priority: None,
+            tags: Vec::new(),
+            parent: parent.map(|index| tasks[index].id.clone()),
+            subtasks: Vec::new(),
+        };
+        metadata(&mut task);
+        if let Some(index) = parent {
+            let id = task.id.clone();
+            tasks[index].subtasks.push(id);
+        }
+        open.push((item.indent, Some(tasks.len())));
+        tasks.push(task);
+    }
+    tasks
+}
+
+/// A task's ID: the note, the task's text, and which of the tasks with
+/// that text in the note it is.
+fn task_id(path: &str, text: &str, n: usize) -> String {
+    let hash = hash_bytes(format!("{}\n{}\n{}", path, text, n).as_bytes());
+    hash[..16].to_string()
+}
+
+/// Fills in a task's dates, priority and tags from its text.
+fn metadata(task: &mut Task) {
+    let text = task.text.clone();
+    for (emoji, field, kind) in DATES {
+        let date = text
+            .split(emoji)
+            .nth(1)
+            .and_then(|rest| date_at(rest.trim_start()))
+            .or_else(|| field_value(&text, field).and_then(date_at));
+        if let Some(date) = date {
+            let slot = match kind {
+                DateKind::Due => &mut task.due,
+                DateKind::Scheduled => &mut task.scheduled,
+                DateKind::Start => &mut task.start,
+                DateKind::Done => &mut task.done,
+            };
+            *slot = Some(date.to_string());
+        }
+    }
+
+    task.priority = PRIORITIES
+        .iter()
+        .find(|(emoji, _)| text.contains(emoji))
+        .map(|&(_, priority)| priority)
+        .or_else(|| field_value(&text, "priority").and_then(Priority::parse));
+
+    for tag in tags::parse(&text) {
+        if !task.tags.iter().any(|t| t.eq_ignore_ascii_case(&tag.name)) {
+            task.tags.push(tag.name);
+        }
+    }
+}
+
+/// A `YYYY-MM-DD` date at the start of `s`.
+fn date_at(s: &str) -> Option<&str> {
+    let date = s.get(..10)?;
+    let b = date.as_bytes();
+    let shape = b.iter().enumerate().all(|(i, c)| match i {
+        4 | 7 => *c == b'-',
+        _ => c.is_ascii_digit(),
+    });
+    shape.then_some(date)
+}
+
+/// The value of an inline `name:: value` field, bracketed or not. Without
+/// brackets the value is the next word.
+fn field_value<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    let marker = format!("{}::", name);
+    let mut from = 0;
+    while let Some(found) = text[from..].find(&marker) {
+        let at = from + found;
+        from = at + marker.len();
+        let starts_word = text[..at]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || matches!(c, '[' | '('));
+        if !starts_word {
+            continue;
+        }
+        let rest = text[from..].trim_start();
+        let bracketed = text[..at].ends_with(['[', '(']);
+        let end = if bracketed {
+            rest.find([']', ')']).unwrap_or(rest.len())
+        } else {
+            rest.find(char::is_whitespace).unwrap_or(rest.len())
+        };
+        let value = rest[..end].trim();
+        if !value.is_empty() {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct NoteTasks {
+    /// Content hash of the note the tasks were found in.
+    hash: String,
+    tasks: Vec<Task>,
+}
+
+/// Tasks of every note in a vault.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TaskIndex {
+    notes: BTreeMap<String, NoteTasks>,
+}
+
+impl TaskIndex {
+    fn file_path(root: &Path) -> std::path::PathBuf {
+        root.join(SLATE_DIR).join(TASKS_FILE)
+    }
+
+    /// Loads the stored task index. Returns None if there is none or it
+    /// cannot be used.
+    pub fn load(root: &Path) -> Option<Self> {
+        let path = Self::file_path(root);
+        let bytes = fs::read(&path).ok()?;
+        match bincode::deserialize::<(u32, TaskIndex)>(&bytes) {
+            Ok((TASKS_VERSION, index)) => {
+                debug!(note_count = index.notes.len(), "Loaded task index");
+                Some(index)
+            }
+            Ok((version, _)) => {
+                info!(version, "Discarding task index from another version");
+                None
+            }
+            Err(e) => {
+                warn!(path = ?path, error = %e, "Discarding unreadable task index");
+                None
+            }
+        }
+    }
+
+    /// Writes the task index to `.slate/tasks.bin`.
+    pub fn save(&self, root: &Path) -> Result<(), SlateError> {
+        let bytes = bincode::serialize(&(TASKS_VERSION, self))
+            .map_err(|e| SlateError::internal(format!("Failed to serialize task index: {}", e)))?;
+        write_slate_file(root, TASKS_FILE, &bytes)
+    }
+
+    /// Builds a task index from scratch by reading every note in the vault,
+    /// for use without an open vault.
+    pub fn build(root: &Path) -> Self {
+        let mut index = TaskIndex::default();
+        for file in super::markdown_files(root) {
+            let Ok(path) = file.strip_prefix(root) else {
+                continue;
+            };
+            let Ok(bytes) = fs::read(&file) else {
+                continue;
+            };
+            let path = path.to_string_lossy().into_owned();
+            index.insert(path, hash_bytes(&bytes), &String::from_utf8_lossy(&bytes));
+        }
+        index
+    }
+
+    /// Compares the parsed content hashes with the vault index.
+    pub fn stale(&self, index: &VaultIndex) -> Stale {
+        index.stale(self.notes.keys(), |path| {
+            self.notes.get(path).map(|note| note.hash.as_str())
+        })
+    }
+
+    /// Finds the tasks of the note at `path` in its `text`, replacing what
+    /// was there.
+    pub fn insert(&mut self, path: String, hash: String, text: &str) {
+        let tasks = parse(&path, text);
+        self.notes.insert(path, NoteTasks { hash, tasks });
+    }
+
+    pub fn remove(&mut self, path: &str) {
+        self.notes.remove(path);
+    }
+
+    /// Every task in the vault, by path, then line.
+    pub fn iter(&self) -> impl Iterator<Item = &Task> {
+        self.notes.values().flat_map(|note| &note.tasks)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Task> {
+        self.iter().find(|task| task.id == id)
+    }
+}
+
+/// Brings an open vault's task index in line with its vault index, reading
+/// only notes whose content hash changed, without holding the registry
+/// lock.
+pub(crate) fn refresh(registry: &VaultRegistry, vault: &VaultHandle) {
+    let root = vault.root();
+    if registry.with_open(vault, |open| open.tasks.is_none()) == Some(true) {
+        let loaded = TaskIndex::load(root).unwrap_or_default();
+        registry.with_open(vault, |open| {
+            open.tasks.get_or_insert(loaded);
+        });
+    }
+
+    let Some(mut stale) = registry
+        .with_open(vault, |open| {
+            Some(open.tasks.as_ref()?.stale(open.index.as_ref()?))
+        })
+        .flatten()
+    else {
+        return;
+    };
+    if stale.is_empty() {
+        return;
+    }
+
+    let start = Instant::now();
+    let mut parsed = Vec::with_capacity(stale.changed.len());
+    for path in std::mem::take(&mut stale.changed) {
+        match fs::read(root.join(&path)) {
+            Ok(bytes) => {
+                let hash = hash_bytes(&bytes);
+                let tasks = parse(&path, &String::from_utf8_lossy(&bytes));
+                parsed.push((path, NoteTasks { hash, tasks }));
+            }
+            // Gone since the vault index saw it; the next refresh drops it
+            Err(_) => stale.removed.push(path),
+        }
+    }
+
+    let count = parsed.len();
+    registry.with_open(vault, |open| {
+        let Some(tasks) = open.tasks.as_mut() else {
+            return;
+        };
+        for path in &stale.removed {
+            tasks.remove(path);
+        }
+        tasks.notes.extend(parsed);
+        if let Err(e) = tasks.save(root) {
+            warn!(error = %e, "Failed to persist task index");
+        }
+    });
+
+    info!(
+        parsed = count,
+        removed = stale.removed.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Updated task index"
+    );
+}
+
+/// How to order tasks. Every order falls back to path, then line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskSort {
+    /// Soonest due first; tasks without a due date last.
+    Due,
+    /// Most urgent first.
+    Priority,
+    #[default]
+    Path,
+}
+
+/// Which tasks to return. Every field is optional; an empty query returns
+/// every task.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TaskQuery {
+    /// Only checked (true) or unchecked (false) tasks.
+    pub completed: Option<bool>,
+    /// Only tasks in notes under this folder.
+    pub folder: Option<String>,
+    /// Only tasks with one of these tags, or a tag nested under one.
+    pub tags: Vec<String>,
+    /// Only tasks whose text contains this, ignoring case.
+    pub text: Option<String>,
+    /// Only tasks due on or after / on or before these dates (`YYYY-MM-DD`).
+    /// Either excludes tasks without a due date.
+    pub due_after: Option<String>,
+    pub due_before: Option<String>,
+    /// Only tasks with (true) or without (false) a due date.
+    pub has_due: Option<bool>,
+    /// Only tasks at least this urgent.
+    pub min_priority: Option<Priority>,
+    pub sort: TaskSort,
+    pub limit: Option<usize>,
+}
+
+impl TaskQuery {
+    pub fn matches(&self, task: &Task) -> bool {
+        let due = task.due.as_deref();
+        self.completed
+            .is_none_or(|completed| task.completed == completed)
+            && self.folder.as_deref().is_none_or(|folder| {
+                let folder = folder.trim_matches('/');
+                folder.is_empty() || task.path.starts_with(&format!("{}/", folder))
+            })
+            && (self.tags.is_empty()
+                || task
+                    .tags
+                    .iter()
+                    .any(|tag| self.tags.iter().any(|parent| tags::is_under(tag, parent))))
+            && self
+                .text
+                .as_deref()
+                .is_none_or(|text| task.text.to_lowercase().contains(&text.to_lowercase()))
+            && self
+                .due_after
+                .as_deref()
+                .is_none_or(|after| due.is_some_and(|due| due >= after))
+            && self
+                .due_before
+                .as_deref()
+                .is_none_or(|before| due.is_some_and(|due| due <= before))
+            && self.has_due.is_none_or(|has| due.is_some() == has)
+            && self
+                .min_priority
+                .is_none_or(|min| Priority::rank(task.priority) >= Priority::rank(Some(min)))
+    }
+
+    /// The matching tasks, sorted and limited.
+    pub fn run<'a>(&self, tasks: impl Iterator<Item = &'a Task>) -> Vec<Task> {
+        let mut found: Vec<&Task> = tasks.filter(|task| self.matches(task)).collect();
+        let by_position = |a: &&Task, b: &&Task| a.path.cmp(&b.path).then(a.line.cmp(&b.line));
+        match self.sort {
+            TaskSort::Due => found.sort_by(|a, b| {
+                match (&a.due, &b.due) {
+                    (Some(x), Some(y)) => x.cmp(y),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+                .then_with(|| by_position(a, b))
+            }),
+            TaskSort::Priority => found.sort_by(|a, b| {
+                Priority::rank(b.priority)
+                    .cmp(&Priority::rank(a.priority))
+                    .then_with(|| by_position(a, b))
+            }),
+            TaskSort::Path => found.sort_by(by_position),
+        }
+        if let Some(limit) = self.limit {
+            found.truncate(limit);
+        }
+        found.into_iter().cloned().collect()
+    }
+}
+
+/// Tasks across an open vault that match `query`.
+#[tauri::command]
+#[instrument(skip(registry, vault))]
+pub fn query_tasks(
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    query: Option<TaskQuery>,
+) -> Result<Vec<Task>, SlateError> {
+    let start = Instant::now();
+    let query = query.unwrap_or_default();
+    let tasks = registry
+        .with_open(&vault, |open| {
+            open.tasks.as_ref().map(|tasks| query.run(tasks.iter()))
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::not_ready("Task index"))?;
+
+    info!(
+        count = tasks.len(),
+        elapsed_ms = format!("{:.2}", start.elapsed().as_secs_f64() * 1000.0),
+        "Queried tasks"
+    );
+    Ok(tasks)
+}
+
+/// Checks an unchecked task, or unchecks any other, in its note on disk.
+/// The task is found again in the note by its ID, so it does not matter if
+/// lines have moved since it was listed. Returns the task as it is now.
+#[tauri::command]
+#[instrument(skip(app, registry, vault))]
+pub fn toggle_task(
+    app: AppHandle,
+    registry: State<'_, VaultRegistry>,
+    vault: VaultHandle,
+    id: String,
+) -> Result<Task, SlateError> {
+    let path = registry
+        .with_open(&vault, |open| {
+            Some(open.tasks.as_ref()?.get(&id)?.path.clone())
+        })
+        .ok_or_else(|| SlateError::vault_not_open(vault.root()))?
+        .ok_or_else(|| SlateError::invalid_input(format!("No such task: {}", id)))?;
+    let full_path = vault.join(&path)?;
+
+    let current = note::read(&full_path)?;
+    let content = toggle(&path, &current.content, &id)?;
+
+    let base = BaseVersion {
+        hash: Some(current.hash),
+        mtime: None,
+    };
+    if let Err(theirs) = note::check_base(&full_path, &base, &content)? {
+        return Err(SlateError::Conflict {
+            message: format!("Note changed on disk while it was being edited: {}", path),
+            path,
+            ours: content,
+            theirs,
+        });
+    }
+    let entry = note::write_atomic(&full_path, &content, |entry| {
+        registry.record_write(&vault, &path, entry)
+    })?;
+
+    // Read back right away, rather than when the watcher sees the write
+    registry.with_open(&vault, |open| {
+        if let Some(tasks) = open.tasks.as_mut() {
+            tasks.insert(path.clone(), entry.hash.clone(), &content);
+        }
+    });
+    // Own writes are not reported by the watcher, so tell the frontend here
+    let file = FileEntry::new(vault.root(), &full_path);
+    emit_change(&app, &vault, VaultChange::Modified(file));
+
+    let task = parse(&path, &content)
+        .into_iter()
+        .find(|task| task.id == id)
+        .ok_or_else(|| task_gone(&path))?;
+    info!(completed = task.completed, "Toggled task");
+    Ok(task)
+}
+
+/// `text`, the note at `path`, with the checkbox of task `id` flipped:
+/// checked if it was unchecked, unchecked if it had any other status.
+pub fn toggle(path: &str, text: &str, id: &str) -> Result<String, SlateError> {
+    let task = parse(path, text)
+        .into_iter()
+        .find(|task| task.id == id)
+        .ok_or_else(|| task_gone(path))?;
+    toggled(text, task.line).ok_or_else(|| task_gone(path))
+}
+
+fn task_gone(path: &str) -> SlateError {
+    SlateError::invalid_input(format!("Task is no longer in {}", path))
+}
+
+/// `text` with the checkbox of the task on 1-based `line` flipped.
+fn toggled(text: &str, line: usize) -> Option<String> {
+    let mut offset = 0;
+    for (i, raw) in text.split_inclusive('\n').enumerate() {
+        if i + 1 == line {
+            let item = task_item(raw.trim_end_matches(['\n', '\r']))?;
+            let at = offset + item.status_at;
+            let status = if item.status == ' ' { "x" } else { " " };
+            return Some(format!(
+                "{}{}{}",
+                &text[..at],
+                status,
+                &text[at + item.status.len_utf8()..]
+            ));
+        }
+        offset += raw.len();
+    }
+    None
+}
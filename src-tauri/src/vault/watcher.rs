@@ -0,0 +1,360 @@
+//! Filesystem watcher - turns raw notify events for an open vault into
+//! debounced, typed `vault://created|modified|deleted|renamed` events
+
+use super::echo::WriteLog;
+use super::index::IndexEntry;
+use super::{
+    is_hidden_path, is_markdown_file, markdown_files, FileEntry, VaultHandle, VaultIndex,
+    VaultRegistry,
+};
+use crate::error::SlateError;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{debug, info, warn};
+
+/// Quiet period that ends a burst of events.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+/// Upper bound on how long a continuous stream of events is held back.
+const MAX_BURST: Duration = Duration::from_secs(2);
+
+/// A change to a markdown file in the vault, after debouncing.
+#[derive(Clone, Debug)]
+pub enum VaultChange {
+    Created(FileEntry),
+    Modified(FileEntry),
+    Deleted(FileEntry),
+    Renamed { from: FileEntry, to: FileEntry },
+}
+
+impl VaultChange {
+    fn event_name(&self) -> &'static str {
+        match self {
+            VaultChange::Created(_) => "vault://created",
+            VaultChange::Modified(_) => "vault://modified",
+            VaultChange::Deleted(_) => "vault://deleted",
+            VaultChange::Renamed { .. } => "vault://renamed",
+        }
+    }
+}
+
+/// Payload of `vault://created`, `vault://modified` and `vault://deleted`.
+#[derive(Clone, Serialize)]
+struct FileEvent {
+    vault: VaultHandle,
+    file: FileEntry,
+}
+
+/// Payload of `vault://renamed`.
+#[derive(Clone, Serialize)]
+struct RenameEvent {
+    vault: VaultHandle,
+    from: FileEntry,
+    to: FileEntry,
+}
+
+/// Watches an open vault. Dropping it stops the watcher and its thread.
+pub struct VaultWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl VaultWatcher {
+    pub fn start(app: AppHandle, vault: VaultHandle) -> Result<Self, SlateError> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)
+            .map_err(|e| SlateError::internal(format!("Failed to create file watcher: {}", e)))?;
+        watcher
+            .watch(vault.root(), RecursiveMode::Recursive)
+            .map_err(|e| match e.kind {
+                notify::ErrorKind::Io(io) => SlateError::io("Failed to watch", vault.root(), io),
+                _ => SlateError::internal(format!("Failed to watch {:?}: {}", vault.root(), e)),
+            })?;
+
+        info!(vault = %vault.root().display(), "Watching vault");
+        thread::Builder::new()
+            .name("vault-watcher".to_string())
+            .spawn(move || watch_loop(&app, &vault, rx))
+            .map_err(|e| SlateError::Io {
+                path: None,
+                io_kind: e.kind(),
+                message: format!("Failed to start watcher thread: {}", e),
+            })?;
+
+        Ok(VaultWatcher { _watcher: watcher })
+    }
+}
+
+/// Collects events into bursts and processes each burst once things go
+/// quiet. Exits when the watcher is dropped and the channel disconnects.
+fn watch_loop(app: &AppHandle, vault: &VaultHandle, rx: Receiver<notify::Result<notify::Event>>) {
+    while let Ok(first) = rx.recv() {
+        let started = Instant::now();
+        let mut touched = Vec::new();
+        collect_paths(first, &mut touched);
+
+        let disconnected = loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(event) => {
+                    collect_paths(event, &mut touched);
+                    if started.elapsed() >= MAX_BURST {
+                        break false;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => break false,
+                Err(RecvTimeoutError::Disconnected) => break true,
+            }
+        };
+
+        if !touched.is_empty() {
+            process_burst(app, vault, &touched);
+        }
+        if disconnected {
+            break;
+        }
+    }
+    info!(vault = %vault.root().display(), "Stopped watching vault");
+}
+
+fn collect_paths(event: notify::Result<notify::Event>, touched: &mut Vec<PathBuf>) {
+    match event {
+        Ok(event) if !event.kind.is_access() => touched.extend(event.paths),
+        Ok(_) => {}
+        Err(e) => warn!(error = %e, "File watcher error"),
+    }
+}
+
+/// Applies a burst of touched paths to an open vault's index and emits the
+/// resulting changes. Until the vault has an index, the paths are deferred
+/// for the reconcile that stores one to replay.
+pub(crate) fn process_burst(app: &AppHandle, vault: &VaultHandle, touched: &[PathBuf]) {
+    let registry = app.state::<VaultRegistry>();
+    // Stat and hash before taking the registry lock, so a large folder move
+    // does not hold up every other command
+    let observed = observe(vault.root(), touched);
+    let changes = registry.with_open(vault, |open| {
+        let Some(index) = open.index.as_mut() else {
+            // Saving an index of just these files would truncate the cache
+            debug!(
+                paths = touched.len(),
+                "Deferring changes until the index is loaded"
+            );
+            open.deferred.extend_from_slice(touched);
+            return Vec::new();
+        };
+        let changes = apply(vault.root(), observed, index, &mut open.writes);
+        if !changes.is_empty() {
+            if let Err(e) = index.save(vault.root()) {
+                warn!(error = %e, "Failed to persist vault index");
+            }
+        }
+        changes
+    });
+    // Own writes update the index without being reported, so always check
+    super::refresh_derived(&registry, vault);
+
+    for change in changes.unwrap_or_default() {
+        debug!(?change, "Vault change");
+        emit_change(app, vault, change);
+    }
+}
+
+/// Sends a change to the frontend as its `vault://` event.
+pub(crate) fn emit_change(app: &AppHandle, vault: &VaultHandle, change: VaultChange) {
+    let name = change.event_name();
+    let result = match change {
+        VaultChange::Created(file) | VaultChange::Modified(file) | VaultChange::Deleted(file) => {
+            app.emit(
+                name,
+                FileEvent {
+                    vault: vault.clone(),
+                    file,
+                },
+            )
+        }
+        VaultChange::Renamed { from, to } => app.emit(
+            name,
+            RenameEvent {
+                vault: vault.clone(),
+                from,
+                to,
+            },
+        ),
+    };
+    if let Err(e) = result {
+        warn!(error = %e, "Failed to emit vault change");
+    }
+}
+
+/// The state of a touched path on disk, read outside the registry lock.
+enum Observed {
+    /// A markdown file that exists, with its current stat and hash.
+    Present(String, IndexEntry),
+    /// A path that no longer exists: a file, or a whole directory.
+    Gone(String),
+}
+
+/// Files found changed while processing a burst, before rename pairing.
+#[derive(Default)]
+struct Burst {
+    seen: HashSet<String>,
+    created: Vec<(String, IndexEntry)>,
+    modified: Vec<(String, IndexEntry)>,
+    deleted: Vec<(String, IndexEntry)>,
+}
+
+impl Burst {
+    /// Records a markdown file that exists on disk, unless its current state
+    /// is one Slate wrote itself.
+    fn present(
+        &mut self,
+        rel: String,
+        entry: IndexEntry,
+        index: &mut VaultIndex,
+        writes: &mut WriteLog,
+    ) {
+        if !self.seen.insert(rel.clone()) {
+            return;
+        }
+        if writes.take_echo(&rel, &entry) {
+            debug!(path = %rel, "Ignoring echo of own write");
+            index.insert(rel, entry);
+            return;
+        }
+        match index.get(&rel) {
+            None => self.created.push((rel, entry)),
+            Some(old) if old.hash != entry.hash => self.modified.push((rel, entry)),
+            // Same content: only keep the metadata current
+            Some(_) => index.insert(rel, entry),
+        }
+    }
+
+    /// Records a path that no longer exists: a file, or a whole directory.
+    fn gone(&mut self, rel: String, index: &VaultIndex) {
+        let paths = if index.get(&rel).is_some() {
+            vec![rel]
+        } else {
+            index.paths_under(&rel)
+        };
+        for rel in paths {
+            if let Some(old) = index.get(&rel) {
+                if self.seen.insert(rel.clone()) {
+                    self.deleted.push((rel, old.clone()));
+                }
+            }
+        }
+    }
+}
+
+/// Works out what a burst of touched paths amounts to by comparing the disk
+/// against the index, and updates the index to match.
+///
+/// Hidden and non-markdown paths are ignored. Files whose content hash is
+/// unchanged, or whose state matches a write recorded in `writes`, are not
+/// reported. A deletion and a creation with the same content hash are
+/// reported as a single rename.
+pub fn apply_changes(
+    root: &Path,
+    touched: &[PathBuf],
+    index: &mut VaultIndex,
+    writes: &mut WriteLog,
+) -> Vec<VaultChange> {
+    apply(root, observe(root, touched), index, writes)
+}
+
+/// Stats and hashes the markdown files behind a burst of touched paths,
+/// each once. Hidden and non-markdown paths are left out.
+fn observe(root: &Path, touched: &[PathBuf]) -> Vec<Observed> {
+    let mut observed = Vec::new();
+    let mut hashed = HashSet::new();
+    let mut present = |path: &Path, observed: &mut Vec<Observed>| {
+        let Some(rel) = relative(root, path) else {
+            return;
+        };
+        if !hashed.insert(rel.clone()) {
+            return;
+        }
+        if let Some(entry) = IndexEntry::read(path) {
+            observed.push(Observed::Present(rel, entry));
+        }
+    };
+
+    for path in touched {
+        if is_hidden_path(root, path) {
+            continue;
+        }
+        match path.metadata() {
+            Ok(meta) if meta.is_dir() => {
+                for file in markdown_files(path) {
+                    present(&file, &mut observed);
+                }
+            }
+            Ok(_) if is_markdown_file(path) => present(path, &mut observed),
+            Ok(_) => {}
+            Err(_) => observed.extend(relative(root, path).map(Observed::Gone)),
+        }
+    }
+    observed
+}
+
+/// Applies what [`observe`] found to the index, pairing deletions with
+/// creations of the same content into renames.
+fn apply(
+    root: &Path,
+    observed: Vec<Observed>,
+    index: &mut VaultIndex,
+    writes: &mut WriteLog,
+) -> Vec<VaultChange> {
+    let mut burst = Burst::default();
+    for observation in observed {
+        match observation {
+            Observed::Present(rel, entry) => burst.present(rel, entry, index, writes),
+            Observed::Gone(rel) => burst.gone(rel, index),
+        }
+    }
+
+    let entry = |rel: &str| FileEntry::new(root, &root.join(rel));
+    let Burst {
+        mut created,
+        modified,
+        deleted,
+        ..
+    } = burst;
+    let mut changes = Vec::new();
+
+    for (from, old) in deleted {
+        index.remove(&from);
+        match created.iter().position(|(_, new)| new.hash == old.hash) {
+            Some(i) => {
+                let (to, new) = created.remove(i);
+                changes.push(VaultChange::Renamed {
+                    from: entry(&from),
+                    to: entry(&to),
+                });
+                index.insert(to, new);
+            }
+            None => changes.push(VaultChange::Deleted(entry(&from))),
+        }
+    }
+    for (rel, new) in created {
+        changes.push(VaultChange::Created(entry(&rel)));
+        index.insert(rel, new);
+    }
+    for (rel, new) in modified {
+        changes.push(VaultChange::Modified(entry(&rel)));
+        index.insert(rel, new);
+    }
+
+    changes
+}
+
+fn relative(root: &Path, path: &Path) -> Option<String> {
+    path.strip_prefix(root)
+        .ok()
+        .filter(|rel| !rel.as_os_str().is_empty())
+        .map(|rel| rel.to_string_lossy().to_string())
+}
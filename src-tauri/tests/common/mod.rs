@@ -0,0 +1,17 @@
+//! Helpers shared by the integration tests.
+
+use slate_lib::vault::VaultHandle;
+use std::fs;
+
+/// A vault in a temporary directory holding `notes`, given as (relative
+/// path, text) pairs. The directory is removed when the `TempDir` drops.
+pub fn vault(notes: &[(&str, &str)]) -> (tempfile::TempDir, VaultHandle) {
+    let dir = tempfile::tempdir().unwrap();
+    for (path, text) in notes {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+    let handle = VaultHandle::from_path(dir.path()).unwrap();
+    (dir, handle)
+}
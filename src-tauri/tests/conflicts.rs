@@ -0,0 +1,71 @@
+//! Sync conflicts: conflicted copies are recognised by each sync client's
+//! naming scheme, and iCloud's ambiguous "Name 2" only next to its original.
+
+use slate_lib::vault::conflicts::{detect, SyncService};
+use std::path::Path;
+
+/// Detects `path` in a vault holding `notes`.
+fn detect_in(notes: &[&str], path: &str) -> Option<(SyncService, String)> {
+    let is_note = |rel: &str| notes.contains(&rel);
+    detect(Path::new("/vault"), path, &is_note)
+        .map(|copy| (copy.service, copy.original.relative_path))
+}
+
+#[test]
+fn dropbox_copies_name_their_original() {
+    assert_eq!(
+        detect_in(&[], "Note (Alice's conflicted copy 2026-10-01).md"),
+        Some((SyncService::Dropbox, "Note.md".to_string()))
+    );
+    assert_eq!(
+        detect_in(&[], "dir/Note (Bob's conflicted copy 2026-10-01 (1)).md"),
+        Some((SyncService::Dropbox, "dir/Note.md".to_string()))
+    );
+    assert_eq!(detect_in(&[], "Note (draft).md"), None);
+}
+
+#[test]
+fn syncthing_copies_name_their_original() {
+    assert_eq!(
+        detect_in(&[], "Note.sync-conflict-20261001-120000-ABCDEFG.md"),
+        Some((SyncService::Syncthing, "Note.md".to_string()))
+    );
+    assert_eq!(
+        detect_in(&[], ".sync-conflict-20261001-120000-ABCDEFG.md"),
+        None
+    );
+}
+
+#[test]
+fn icloud_copies_need_their_original_alongside() {
+    assert_eq!(
+        detect_in(&["Note.md"], "Note 2.md"),
+        Some((SyncService::Icloud, "Note.md".to_string()))
+    );
+    assert_eq!(detect_in(&["dir/Note.md"], "Note 2.md"), None);
+    assert_eq!(detect_in(&[], "Chapter 2.md"), None);
+}
+
+#[test]
+fn ordinary_numbered_names_are_not_copies() {
+    let notes = ["Chapter.md"];
+    for name in [
+        "Chapter 1.md",
+        "Chapter 02.md",
+        "Chapter two.md",
+        "Chapter2.md",
+    ] {
+        assert_eq!(detect_in(&notes, name), None, "{}", name);
+    }
+}
+
+#[test]
+fn originals_are_looked_up_without_the_disk() {
+    // Nothing exists under /vault; the lookup alone decides
+    let copy = detect(Path::new("/vault"), "a/Note 3.md", &|rel| {
+        rel == "a/Note.md"
+    })
+    .unwrap();
+    assert_eq!(copy.service, SyncService::Icloud);
+    assert_eq!(copy.original.path, "/vault/a/Note.md");
+}
@@ -0,0 +1,233 @@
+//! Watcher bursts: Slate's own saves must not come back as outside
+//! modifications, however quickly they follow each other, moves come out as
+//! renames, and hidden or non-markdown files are never reported.
+
+use slate_lib::vault::echo::WriteLog;
+use slate_lib::vault::note;
+use slate_lib::vault::watcher::{apply_changes, VaultChange};
+use slate_lib::vault::VaultIndex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+struct Fixture {
+    _dir: tempfile::TempDir,
+    root: PathBuf,
+    index: VaultIndex,
+    writes: WriteLog,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::write(root.join("note.md"), "original").unwrap();
+        let mut index = VaultIndex::default();
+        index.reconcile(&root);
+        Fixture {
+            _dir: dir,
+            root,
+            index,
+            writes: WriteLog::default(),
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.root.join("note.md")
+    }
+
+    /// Saves the note through the backend write path.
+    fn save(&mut self, content: &str) {
+        let path = self.path();
+        let writes = &mut self.writes;
+        note::write_atomic(&path, content, |entry| writes.record("note.md", entry)).unwrap();
+    }
+
+    fn external_write(&self, content: &str) {
+        fs::write(self.path(), content).unwrap();
+    }
+
+    /// Processes a watcher burst touching the note.
+    fn burst(&mut self) -> Vec<VaultChange> {
+        self.touch(&["note.md"])
+    }
+
+    /// Processes a watcher burst touching vault-relative `paths`.
+    fn touch(&mut self, paths: &[&str]) -> Vec<VaultChange> {
+        let touched: Vec<PathBuf> = paths.iter().map(|path| self.root.join(path)).collect();
+        apply_changes(&self.root, &touched, &mut self.index, &mut self.writes)
+    }
+
+    /// Writes files outside Slate, creating their folders.
+    fn create(&self, files: &[(&str, &str)]) {
+        for (path, text) in files {
+            let path = self.root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+    }
+
+    fn indexed(&self) -> Vec<String> {
+        self.index.iter().map(|(path, _)| path.clone()).collect()
+    }
+}
+
+/// Each change as "kind path", by relative path.
+fn summary(changes: &[VaultChange]) -> Vec<String> {
+    changes
+        .iter()
+        .map(|change| match change {
+            VaultChange::Created(f) => format!("created {}", f.relative_path),
+            VaultChange::Modified(f) => format!("modified {}", f.relative_path),
+            VaultChange::Deleted(f) => format!("deleted {}", f.relative_path),
+            VaultChange::Renamed { from, to } => {
+                format!("renamed {} -> {}", from.relative_path, to.relative_path)
+            }
+        })
+        .collect()
+}
+
+fn is_modified(changes: &[VaultChange], path: &Path) -> bool {
+    changes
+        .iter()
+        .any(|c| matches!(c, VaultChange::Modified(f) if Path::new(&f.path) == path))
+}
+
+#[test]
+fn own_save_is_not_reported() {
+    let mut fx = Fixture::new();
+    fx.save("edited");
+    assert!(fx.burst().is_empty());
+}
+
+#[test]
+fn external_change_is_reported() {
+    let mut fx = Fixture::new();
+    fx.external_write("changed elsewhere");
+    let changes = fx.burst();
+    assert!(is_modified(&changes, &fx.path()));
+}
+
+#[test]
+fn rapid_saves_seen_as_one_burst_are_suppressed() {
+    let mut fx = Fixture::new();
+    for i in 0..20 {
+        fx.save(&format!("draft {}", i));
+    }
+    // The watcher only gets to look once, after the last save
+    assert!(fx.burst().is_empty());
+}
+
+#[test]
+fn rapid_saves_seen_at_intermediate_states_are_suppressed() {
+    let mut fx = Fixture::new();
+    fx.save("one");
+    fx.save("two");
+    fx.save("three");
+
+    // The watcher processes a burst while "three" is on disk, then more
+    // saves land and it processes another burst
+    assert!(fx.burst().is_empty());
+    fx.save("four");
+    fx.save("five");
+    assert!(fx.burst().is_empty());
+}
+
+#[test]
+fn external_change_between_own_saves_is_reported() {
+    let mut fx = Fixture::new();
+    fx.save("mine");
+    assert!(fx.burst().is_empty());
+
+    fx.external_write("theirs");
+    let changes = fx.burst();
+    assert!(is_modified(&changes, &fx.path()));
+
+    fx.save("mine again");
+    assert!(fx.burst().is_empty());
+}
+
+#[test]
+fn external_change_after_rapid_saves_is_reported() {
+    let mut fx = Fixture::new();
+    fx.save("a");
+    fx.save("b");
+    fx.save("c");
+    fx.external_write("someone else");
+    let changes = fx.burst();
+    assert!(is_modified(&changes, &fx.path()));
+}
+
+#[test]
+fn consumed_write_does_not_mask_later_external_revert() {
+    let mut fx = Fixture::new();
+    fx.save("mine");
+    assert!(fx.burst().is_empty());
+
+    // Another editor writes different content, then puts ours back; the
+    // second state matches our earlier write's hash but is not our write
+    fx.external_write("theirs");
+    assert!(is_modified(&fx.burst(), &fx.path()));
+    fx.external_write("mine");
+    assert!(is_modified(&fx.burst(), &fx.path()));
+}
+
+#[test]
+fn a_move_is_one_rename() {
+    let mut fx = Fixture::new();
+    fx.create(&[("other.md", "other")]);
+    fx.index.reconcile(&fx.root);
+
+    fs::create_dir(fx.root.join("moved")).unwrap();
+    fs::rename(fx.path(), fx.root.join("moved/note.md")).unwrap();
+    fx.create(&[("new.md", "new")]);
+    fs::remove_file(fx.root.join("other.md")).unwrap();
+    let changes = fx.touch(&["note.md", "moved", "new.md", "other.md"]);
+    assert_eq!(
+        summary(&changes),
+        [
+            "renamed note.md -> moved/note.md",
+            "deleted other.md",
+            "created new.md"
+        ]
+    );
+    assert_eq!(fx.indexed(), ["moved/note.md", "new.md"]);
+}
+
+#[test]
+fn removing_a_folder_deletes_the_notes_in_it() {
+    let mut fx = Fixture::new();
+    fx.create(&[
+        ("dir/a.md", "a"),
+        ("dir/sub/b.md", "b"),
+        ("dirt.md", "not in dir"),
+    ]);
+    fx.index.reconcile(&fx.root);
+
+    fs::remove_dir_all(fx.root.join("dir")).unwrap();
+    let changes = fx.touch(&["dir"]);
+    assert_eq!(
+        summary(&changes),
+        ["deleted dir/a.md", "deleted dir/sub/b.md"]
+    );
+    assert_eq!(fx.indexed(), ["dirt.md", "note.md"]);
+}
+
+#[test]
+fn hidden_and_other_files_are_ignored() {
+    let mut fx = Fixture::new();
+    fx.create(&[
+        (".obsidian/workspace.md", "settings"),
+        (".draft.md", "hidden"),
+        ("notes/.trash/old.md", "hidden too"),
+        ("picture.png", "not a note"),
+    ]);
+    let changes = fx.touch(&[
+        ".obsidian",
+        ".obsidian/workspace.md",
+        ".draft.md",
+        "notes/.trash/old.md",
+        "picture.png",
+    ]);
+    assert!(changes.is_empty(), "{:?}", summary(&changes));
+    assert_eq!(fx.indexed(), ["note.md"]);
+}
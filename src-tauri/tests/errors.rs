@@ -0,0 +1,102 @@
+//! Backend errors: failures must reach the frontend with a stable code and
+//! the path they concern, so the UI can react without parsing messages.
+
+use slate_lib::error::SlateError;
+use slate_lib::vault::note;
+use slate_lib::vault::VaultHandle;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+fn vault() -> (tempfile::TempDir, VaultHandle) {
+    let dir = tempfile::tempdir().unwrap();
+    let handle = VaultHandle::from_path(dir.path()).unwrap();
+    (dir, handle)
+}
+
+#[test]
+fn missing_vault_is_vault_not_found() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("Vault");
+    let err = VaultHandle::from_path(&missing).unwrap_err();
+    assert_eq!(err.code(), "vaultNotFound");
+
+    let json = serde_json::to_value(&err).unwrap();
+    assert_eq!(json["code"], "vaultNotFound");
+    assert_eq!(json["path"], &*missing.to_string_lossy());
+    assert_eq!(json["message"], err.message());
+}
+
+#[test]
+fn file_as_vault_is_not_a_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("note.md");
+    fs::write(&file, "text").unwrap();
+    let err = VaultHandle::from_path(&file).unwrap_err();
+    assert_eq!(err.code(), "notADirectory");
+}
+
+#[test]
+fn paths_outside_the_vault_are_invalid() {
+    let (_dir, vault) = vault();
+    for path in ["../escape.md", "/etc/passwd", "", "./note.md"] {
+        let err = vault.join(path).unwrap_err();
+        assert_eq!(err.code(), "invalidPath", "{:?}", path);
+    }
+}
+
+#[test]
+fn missing_note_is_not_found() {
+    let (_dir, vault) = vault();
+    let path = vault.join("missing.md").unwrap();
+    match note::read(&path).unwrap_err() {
+        SlateError::NotFound { path: reported, .. } => {
+            assert_eq!(reported, path.to_string_lossy())
+        }
+        other => panic!("expected notFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn non_utf8_note_is_invalid_encoding() {
+    let (_dir, vault) = vault();
+    let path = vault.join("binary.md").unwrap();
+    fs::write(&path, [0xff, 0xfe, 0x00]).unwrap();
+    assert_eq!(note::read(&path).unwrap_err().code(), "invalidEncoding");
+}
+
+#[test]
+fn other_io_errors_carry_their_kind() {
+    let err = SlateError::io(
+        "Failed to write",
+        Path::new("/vault/a.md"),
+        io::ErrorKind::Interrupted.into(),
+    );
+    let json = serde_json::to_value(&err).unwrap();
+    assert_eq!(json["code"], "io");
+    assert_eq!(json["ioKind"], "Interrupted");
+    assert_eq!(json["path"], "/vault/a.md");
+
+    let denied = SlateError::io(
+        "Failed to read",
+        Path::new("/vault/a.md"),
+        io::ErrorKind::PermissionDenied.into(),
+    );
+    assert_eq!(denied.code(), "permissionDenied");
+}
+
+#[test]
+fn code_matches_the_serialized_tag() {
+    let errors = [
+        SlateError::not_ready("Link index"),
+        SlateError::vault_not_open(Path::new("/vault")),
+        SlateError::not_found("a.md", "Not a note: a.md"),
+        SlateError::invalid_input("Not a tag: #"),
+        SlateError::internal("Could not determine home directory"),
+    ];
+    for err in errors {
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], err.code());
+        assert_eq!(json["message"], err.to_string());
+    }
+}
@@ -0,0 +1,158 @@
+//! Frontmatter: editing a property changes only its own lines, in YAML and
+//! TOML alike, and values read back with the type they were written with.
+
+use slate_lib::vault::frontmatter::{parse, set, Property, PropertyValue};
+
+use PropertyValue::{Boolean, Date, List, Null, Number, Text};
+
+fn text(s: &str) -> PropertyValue {
+    Text(s.to_string())
+}
+
+fn props(note: &str) -> Vec<(String, PropertyValue)> {
+    parse(note)
+        .into_iter()
+        .map(|Property { key, value }| (key, value))
+        .collect()
+}
+
+fn value(note: &str, key: &str) -> PropertyValue {
+    props(note)
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v)
+        .unwrap()
+}
+
+#[test]
+fn comments_and_other_keys_are_left_alone() {
+    let note = "---\ntitle: Old\n# a comment\n\nstatus: draft # for now\n---\nBody\n";
+    assert_eq!(
+        set(note, "title", Some(&text("New"))).unwrap(),
+        "---\ntitle: New\n# a comment\n\nstatus: draft # for now\n---\nBody\n"
+    );
+    assert_eq!(
+        set(note, "status", None).unwrap(),
+        "---\ntitle: Old\n# a comment\n\n---\nBody\n"
+    );
+}
+
+#[test]
+fn lists_keep_their_style() {
+    let note = "---\ntags: [a, b]\naliases:\n    - One\n    - Two\n---\n";
+    let note = set(note, "tags", Some(&List(vec![text("c"), text("d")]))).unwrap();
+    let note = set(&note, "aliases", Some(&List(vec![text("Three")]))).unwrap();
+    assert_eq!(note, "---\ntags: [c, d]\naliases:\n    - Three\n---\n");
+
+    // New lists are indented like the first block list already there
+    let note = set(&note, "cssclasses", Some(&List(vec![text("wide")]))).unwrap();
+    assert!(note.ends_with("cssclasses:\n    - wide\n---\n"));
+}
+
+#[test]
+fn quoted_keys_are_found_and_written_quoted() {
+    let note = "---\n\"my key\": 1\n'other: x': y\n---\n";
+    assert_eq!(
+        props(note),
+        [
+            ("my key".to_string(), Number(1.0)),
+            ("other: x".to_string(), text("y"))
+        ]
+    );
+    assert_eq!(
+        set(note, "other: x", Some(&text("z"))).unwrap(),
+        "---\n\"my key\": 1\n\"other: x\": z\n---\n"
+    );
+}
+
+#[test]
+fn notes_without_a_trailing_newline() {
+    assert_eq!(
+        set("---\na: 1\n---", "b", Some(&Number(2.0))).unwrap(),
+        "---\na: 1\nb: 2\n---"
+    );
+    assert_eq!(
+        set("Body", "a", Some(&text("x"))).unwrap(),
+        "---\na: x\n---\nBody"
+    );
+}
+
+#[test]
+fn removing_the_last_key_leaves_an_empty_block() {
+    let note = set("---\ntitle: x\n---\nBody", "title", None).unwrap();
+    assert_eq!(note, "---\n---\nBody");
+    assert!(props(&note).is_empty());
+    // Removing a key that is not there changes nothing
+    assert_eq!(set("Body", "title", None).unwrap(), "Body");
+}
+
+#[test]
+fn toml_edits_keep_comments_and_decor() {
+    let note = "+++\n# settings\ntitle = \"Old\" # keep me\ncount = 1\n+++\nBody";
+    assert_eq!(
+        set(note, "title", Some(&text("New"))).unwrap(),
+        "+++\n# settings\ntitle = \"New\" # keep me\ncount = 1\n+++\nBody"
+    );
+
+    let note = set(note, "draft", Some(&Boolean(true))).unwrap();
+    assert_eq!(value(&note, "draft"), Boolean(true));
+    assert_eq!(value(&note, "count"), Number(1.0));
+    assert_eq!(
+        set(&note, "draft", Some(&Null)).unwrap_err().code(),
+        "invalidInput"
+    );
+}
+
+#[test]
+fn yaml_values_are_typed() {
+    let note = "---\nn: 3\nf: -2.5\nd: 2026-10-15\ndt: 2026-10-15 09:30\nb: true\ns: \"true\"\nv: 1.2.3\nlist:\n  - 1\n  - x\nflow: [2026-01-01, false]\nempty:\n---\n";
+    let expected = [
+        ("n", Number(3.0)),
+        ("f", Number(-2.5)),
+        ("d", Date("2026-10-15".to_string())),
+        ("dt", Date("2026-10-15 09:30".to_string())),
+        ("b", Boolean(true)),
+        ("s", text("true")),
+        ("v", text("1.2.3")),
+        ("list", List(vec![Number(1.0), text("x")])),
+        (
+            "flow",
+            List(vec![Date("2026-01-01".to_string()), Boolean(false)]),
+        ),
+        ("empty", Null),
+    ];
+    let expected: Vec<(String, PropertyValue)> = expected
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+    assert_eq!(props(note), expected);
+}
+
+#[test]
+fn toml_values_are_typed() {
+    let note = "+++\nn = 3\nf = 2.5\nd = 2026-10-15\nb = false\nl = [\"a\", 1]\n+++\n";
+    assert_eq!(value(note, "n"), Number(3.0));
+    assert_eq!(value(note, "f"), Number(2.5));
+    assert_eq!(value(note, "d"), Date("2026-10-15".to_string()));
+    assert_eq!(value(note, "b"), Boolean(false));
+    assert_eq!(value(note, "l"), List(vec![text("a"), Number(1.0)]));
+}
+
+#[test]
+fn written_values_read_back_as_themselves() {
+    let values = [
+        Number(2.5),
+        text("true"),
+        text("a: b"),
+        text("#tag"),
+        text(" padded "),
+        text("line 1\nline 2"),
+        Date("2026-10-15".to_string()),
+        Boolean(false),
+        List(vec![text("x, y"), Number(1.0)]),
+    ];
+    for written in values {
+        let note = set("---\ntitle: x\n---\n", "k", Some(&written)).unwrap();
+        assert_eq!(value(&note, "k"), written, "{}", note);
+    }
+}
@@ -0,0 +1,144 @@
+//! Full-text search: phrases and prefixes find the notes they should, BM25
+//! puts the closer match first, highlights count UTF-16 units, and the index
+//! follows notes as they change.
+
+use slate_lib::vault::search::{self, query, Analyzed, SearchHit, SearchIndex};
+use slate_lib::vault::{VaultHandle, VaultIndex};
+use std::fs;
+
+mod common;
+
+fn hits(index: &SearchIndex, vault: &VaultHandle, src: &str) -> Vec<SearchHit> {
+    search::search(index, vault, &query::parse(src).unwrap(), None)
+}
+
+fn paths(index: &SearchIndex, vault: &VaultHandle, src: &str) -> Vec<String> {
+    hits(index, vault, src)
+        .into_iter()
+        .map(|hit| hit.file.relative_path)
+        .collect()
+}
+
+#[test]
+fn phrases_need_their_words_together_and_in_order() {
+    let (_dir, vault) = common::vault(&[
+        ("a.md", "The quick brown fox."),
+        ("b.md", "A brown and quick fox."),
+        ("c.md", "Brown quick foxes."),
+        // The title does not run on into the body
+        ("quick.md", "brown bear"),
+    ]);
+    let index = SearchIndex::build(vault.root());
+    assert_eq!(paths(&index, &vault, "\"quick brown\""), ["a.md"]);
+    // Phrase words are stemmed like any other
+    assert_eq!(paths(&index, &vault, "\"brown quick fox\""), ["c.md"]);
+
+    let mut found = paths(&index, &vault, "quick brown");
+    found.sort();
+    assert_eq!(found, ["a.md", "b.md", "c.md"]);
+}
+
+#[test]
+fn prefixes_match_the_start_of_any_word() {
+    let (_dir, vault) = common::vault(&[
+        ("a.md", "Photography basics"),
+        ("b.md", "A photo of the sea"),
+        ("c.md", "Phone numbers"),
+    ]);
+    let index = SearchIndex::build(vault.root());
+    let mut found = paths(&index, &vault, "phot*");
+    found.sort();
+    assert_eq!(found, ["a.md", "b.md"]);
+    assert_eq!(paths(&index, &vault, "ph*").len(), 3);
+    // Without the star only the word itself matches
+    assert_eq!(paths(&index, &vault, "photo"), ["b.md"]);
+}
+
+#[test]
+fn bm25_favours_more_matches_in_shorter_notes() {
+    let (_dir, vault) = common::vault(&[
+        ("once.md", "apple kiwi kiwi kiwi"),
+        ("many.md", "apple apple apple kiwi"),
+        (
+            "long.md",
+            "apple kiwi kiwi kiwi kiwi kiwi kiwi kiwi kiwi kiwi",
+        ),
+        ("none.md", "kiwi"),
+    ]);
+    let index = SearchIndex::build(vault.root());
+    let found = hits(&index, &vault, "apple");
+    let ranked: Vec<&str> = found
+        .iter()
+        .map(|hit| hit.file.relative_path.as_str())
+        .collect();
+    assert_eq!(ranked, ["many.md", "once.md", "long.md"]);
+    assert!(found.windows(2).all(|pair| pair[0].score > pair[1].score));
+}
+
+#[test]
+fn highlights_count_utf16_units() {
+    let long = format!("{}café", "word ".repeat(30));
+    let (_dir, vault) = common::vault(&[
+        ("menu.md", "# Menu\n🔥 Hot café menu: café au lait\n"),
+        ("long.md", &long),
+    ]);
+    let index = SearchIndex::build(vault.root());
+    let found = hits(&index, &vault, "café");
+    let snippet = |path: &str| {
+        let hit = found
+            .iter()
+            .find(|hit| hit.file.relative_path == path)
+            .unwrap();
+        hit.snippets[0].clone()
+    };
+
+    // 🔥 takes two units
+    let menu = snippet("menu.md");
+    assert_eq!(menu.line, 2);
+    assert_eq!(menu.text, "🔥 Hot café menu: café au lait");
+    assert_eq!(menu.highlights, [(7, 11), (18, 22)]);
+
+    // A long line is cut before the match; the ellipsis takes one unit
+    let cut = snippet("long.md");
+    assert!(cut.text.starts_with('…') && cut.text.ends_with("café"));
+    assert_eq!(cut.highlights, [(81, 85)]);
+}
+
+#[test]
+fn changed_notes_are_reindexed_alone() {
+    let (_dir, vault) = common::vault(&[
+        ("a.md", "old words"),
+        ("b.md", "other words"),
+        ("c.md", "kept words"),
+    ]);
+    let root = vault.root();
+    let mut notes = VaultIndex::default();
+    notes.reconcile(root);
+    let mut index = SearchIndex::build(root);
+    assert!(index.stale(&notes).is_empty());
+
+    fs::write(root.join("a.md"), "new words").unwrap();
+    fs::remove_file(root.join("b.md")).unwrap();
+    notes.reconcile(root);
+    let stale = index.stale(&notes);
+    assert_eq!(stale.changed, ["a.md"]);
+    assert_eq!(stale.removed, ["b.md"]);
+
+    for path in &stale.removed {
+        index.remove(path);
+    }
+    for path in stale.changed {
+        let text = fs::read_to_string(root.join(&path)).unwrap();
+        let hash = notes.get(&path).unwrap().hash.clone();
+        index.insert(Analyzed::new(path, hash, &text));
+    }
+    assert!(index.stale(&notes).is_empty());
+    assert_eq!(index.len(), 2);
+
+    assert!(paths(&index, &vault, "old").is_empty());
+    assert!(paths(&index, &vault, "other").is_empty());
+    assert_eq!(paths(&index, &vault, "new"), ["a.md"]);
+    let mut found = paths(&index, &vault, "words");
+    found.sort();
+    assert_eq!(found, ["a.md", "c.md"]);
+}
@@ -0,0 +1,128 @@
+//! Quick switcher matching: which paths score higher, positions reported in
+//! UTF-16, and ranking a whole vault's worth of candidates.
+
+use slate_lib::vault::fuzzy::{rank, Candidate, Matcher, Recency};
+use std::time::{Duration, Instant};
+
+fn score(query: &str, path: &str) -> Option<i32> {
+    Matcher::new(query).score_path(path)
+}
+
+fn candidates(paths: &[&str]) -> Vec<Candidate> {
+    paths
+        .iter()
+        .map(|path| Candidate {
+            path: path.to_string(),
+            // Long ago, so modification time adds nothing
+            mtime: 0,
+            aliases: Vec::new(),
+        })
+        .collect()
+}
+
+fn ranked(query: &str, candidates: &[Candidate], recent: &[&str]) -> Vec<String> {
+    let recency = Recency::new(recent.iter().map(|p| p.to_string()).collect());
+    rank(&mut Matcher::new(query), &recency, candidates, 10)
+        .into_iter()
+        .map(|(_, candidate, _)| candidate.path.clone())
+        .collect()
+}
+
+#[test]
+fn word_starts_and_file_names_score_higher() {
+    assert!(score("mn", "meeting-notes.md") > score("mn", "command.md"));
+    assert!(score("alpha", "alpha.md") > score("alpha", "alpha/zeta.md"));
+    assert!(score("note", "notes.md") > score("note", "n-o-t-e.md"));
+    assert_eq!(score("xyz", "notes.md"), None);
+}
+
+#[test]
+fn case_is_smart_and_spaces_match_separators() {
+    assert!(score("notes", "Notes.md").is_some());
+    assert_eq!(score("Notes", "notes.md"), None);
+    assert!(score("meet notes", "meeting-notes.md").is_some());
+    assert!(score("a b", "a/b.md").is_some());
+}
+
+#[test]
+fn non_ascii_paths_fold_case_and_report_utf16_positions() {
+    assert!(score("éc", "École.md").is_some());
+
+    let mut matcher = Matcher::new("id");
+    // 🔥 takes two UTF-16 units
+    let scored = matcher.indices("🔥 ideas.md", true).unwrap();
+    assert_eq!(scored.indices, [3, 4]);
+
+    let scored = Matcher::new("üb").indices("Über/plan.md", true).unwrap();
+    assert_eq!(scored.indices, [0, 1]);
+}
+
+#[test]
+fn empty_query_matches_everything_by_recency() {
+    let mut matcher = Matcher::new("   ");
+    assert!(matcher.is_empty());
+    assert_eq!(matcher.score_path("any.md"), Some(0));
+    assert!(matcher.indices("any.md", true).unwrap().indices.is_empty());
+
+    let notes = candidates(&["a.md", "b.md", "c.md"]);
+    assert_eq!(
+        ranked("", &notes, &["c.md", "a.md"]),
+        ["c.md", "a.md", "b.md"]
+    );
+}
+
+#[test]
+fn ranking_breaks_ties_by_length_then_name_and_matches_aliases() {
+    let notes = candidates(&["b/plan.md", "a/plan.md", "plan.md"]);
+    assert_eq!(
+        ranked("plan", &notes, &[]),
+        ["plan.md", "a/plan.md", "b/plan.md"]
+    );
+
+    let mut notes = candidates(&["daily/2026-10-15.md", "standard.md"]);
+    notes[0].aliases = vec!["Standup".to_string()];
+    let recency = Recency::new(Vec::new());
+    let top = rank(&mut Matcher::new("standup"), &recency, &notes, 10);
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].1.path, "daily/2026-10-15.md");
+    assert_eq!(top[0].2.map(String::as_str), Some("Standup"));
+}
+
+/// Run with `cargo test --release --test fuzzy -- --ignored`; debug builds
+/// are far slower.
+#[test]
+#[ignore]
+fn ranks_50k_paths_within_5ms() {
+    let paths: Vec<String> = (0..50_000)
+        .map(|i| {
+            format!(
+                "area {}/project-{}/{} meeting notes {}.md",
+                i % 40,
+                i % 300,
+                ["daily", "weekly", "Review", "draft"][i % 4],
+                i
+            )
+        })
+        .collect();
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    let notes = candidates(&paths);
+    let recency = Recency::new(Vec::new());
+
+    for query in ["mtng", "weekly notes", "prj 12 rev", "zzz"] {
+        let mut matcher = Matcher::new(query);
+        let mut best = Duration::MAX;
+        for _ in 0..5 {
+            let start = Instant::now();
+            let top = rank(&mut matcher, &recency, &notes, 50);
+            best = best.min(start.elapsed());
+            assert!(top.len() <= 50);
+        }
+        println!("{:?}: best of 5 took {:?}", query, best);
+        assert!(
+            best < Duration::from_millis(5),
+            "{:?} took {:?}",
+            query,
+            best
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Link graph: which notes and links a filter keeps, and exports that stay
+//! well formed whatever the note names hold.
+
+use slate_lib::vault::graph::{
+    build, export, Graph, GraphEdge, GraphFilter, GraphFormat, GraphNode,
+};
+use slate_lib::vault::links::LinkIndex;
+use std::fs;
+
+fn index() -> (tempfile::TempDir, LinkIndex) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("archive")).unwrap();
+    for (path, text) in [
+        ("a.md", "[[b]] and [[b|again]], [[Ghost]] and ![[pic.png]]"),
+        ("b.md", "[[c]]"),
+        ("c.md", "[[d]]"),
+        ("d.md", ""),
+        ("lone.md", "No links."),
+        ("archive/old.md", "[[a]]"),
+    ] {
+        fs::write(dir.path().join(path), text).unwrap();
+    }
+    let index = LinkIndex::build(dir.path());
+    (dir, index)
+}
+
+fn ids(index: &LinkIndex, filter: &GraphFilter) -> Vec<String> {
+    build(index, filter)
+        .unwrap()
+        .nodes
+        .into_iter()
+        .map(|node| node.id)
+        .collect()
+}
+
+fn edges(graph: &Graph) -> Vec<(&str, &str, usize)> {
+    graph
+        .edges
+        .iter()
+        .map(|edge| (edge.source.as_str(), edge.target.as_str(), edge.count))
+        .collect()
+}
+
+#[test]
+fn whole_graph_counts_repeated_links() {
+    let (_dir, index) = index();
+    let graph = build(&index, &GraphFilter::default()).unwrap();
+    assert_eq!(graph.nodes.len(), 6);
+    assert_eq!(
+        edges(&graph),
+        [
+            ("a.md", "b.md", 2),
+            ("archive/old.md", "a.md", 1),
+            ("b.md", "c.md", 1),
+            ("c.md", "d.md", 1)
+        ]
+    );
+}
+
+#[test]
+fn empty_folder_entries_are_ignored() {
+    let (_dir, index) = index();
+    let filter = GraphFilter {
+        exclude_folders: vec![String::new(), "/".to_string()],
+        folders: vec![String::new()],
+        ..Default::default()
+    };
+    assert_eq!(ids(&index, &filter).len(), 6);
+
+    let filter = GraphFilter {
+        exclude_folders: vec!["archive/".to_string(), String::new()],
+        ..Default::default()
+    };
+    assert_eq!(
+        ids(&index, &filter),
+        ["a.md", "b.md", "c.md", "d.md", "lone.md"]
+    );
+}
+
+#[test]
+fn local_graphs_stop_at_their_depth() {
+    let (_dir, index) = index();
+    let around = |depth| GraphFilter {
+        center: Some("a.md".to_string()),
+        depth,
+        ..Default::default()
+    };
+    assert_eq!(
+        ids(&index, &around(None)),
+        ["a.md", "archive/old.md", "b.md"]
+    );
+    assert_eq!(
+        ids(&index, &around(Some(2))),
+        ["a.md", "archive/old.md", "b.md", "c.md"]
+    );
+    assert_eq!(ids(&index, &around(Some(0))), ["a.md"]);
+
+    let filter = GraphFilter {
+        center: Some("nowhere.md".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(build(&index, &filter).unwrap_err().code(), "notFound");
+}
+
+#[test]
+fn missing_notes_and_orphans() {
+    let (_dir, index) = index();
+    let filter = GraphFilter {
+        missing: true,
+        hide_orphans: true,
+        ..Default::default()
+    };
+    let graph = build(&index, &filter).unwrap();
+    let ghost = &graph.nodes[0];
+    assert_eq!((ghost.id.as_str(), ghost.exists), ("Ghost", false));
+    assert!(edges(&graph).contains(&("a.md", "Ghost", 1)));
+    // The missing image is not a note, and the note without links is hidden
+    let ids: Vec<&str> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+    assert_eq!(
+        ids,
+        ["Ghost", "a.md", "archive/old.md", "b.md", "c.md", "d.md"]
+    );
+}
+
+#[test]
+fn exports_escape_quotes_and_ampersands() {
+    let id = "R&D/\"Plan\".md";
+    let graph = Graph {
+        nodes: vec![GraphNode {
+            id: id.to_string(),
+            name: "\"Plan\"".to_string(),
+            folder: "R&D".to_string(),
+            tags: vec!["a&b".to_string()],
+            exists: false,
+        }],
+        edges: vec![GraphEdge {
+            source: id.to_string(),
+            target: "x.md".to_string(),
+            count: 1,
+        }],
+    };
+
+    let graphml = export(&graph, GraphFormat::Graphml).unwrap();
+    for line in [
+        r#"    <node id="R&amp;D/&quot;Plan&quot;.md">"#,
+        r#"      <data key="name">&quot;Plan&quot;</data>"#,
+        r#"      <data key="folder">R&amp;D</data>"#,
+        r#"      <data key="tags">a&amp;b</data>"#,
+        r#"    <edge source="R&amp;D/&quot;Plan&quot;.md" target="x.md">"#,
+    ] {
+        assert!(graphml.lines().any(|l| l == line), "{}\n{}", line, graphml);
+    }
+
+    let dot = export(&graph, GraphFormat::Dot).unwrap();
+    for line in [
+        r#"  "R&D/\"Plan\".md" [label="\"Plan\"", folder="R&D", tags="a&b", style=dashed];"#,
+        r#"  "R&D/\"Plan\".md" -> "x.md" [weight=1];"#,
+    ] {
+        assert!(dot.lines().any(|l| l == line), "{}\n{}", line, dot);
+    }
+}
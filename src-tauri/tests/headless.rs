@@ -0,0 +1,49 @@
+//! Vault queries without an open vault, as `slate-cli` runs them: indexes
+//! built from disk must answer like the ones the app keeps.
+
+use slate_lib::vault::backlinks;
+use slate_lib::vault::links::LinkIndex;
+use slate_lib::vault::search::{self, query, SearchIndex};
+
+mod common;
+
+#[test]
+fn search_ranks_notes_built_from_disk() {
+    let (_dir, vault) = common::vault(&[
+        (
+            "gardening.md",
+            "Tomatoes need sun. More tomatoes, more sun.",
+        ),
+        ("cooking.md", "A sauce of tomatoes."),
+        ("travel.md", "Trains and boats."),
+        (".hidden/tomatoes.md", "tomatoes"),
+    ]);
+    let index = SearchIndex::build(vault.root());
+    let hits = search::search(&index, &vault, &query::parse("tomato").unwrap(), None);
+
+    let paths: Vec<&str> = hits
+        .iter()
+        .map(|hit| hit.file.relative_path.as_str())
+        .collect();
+    assert_eq!(paths, ["gardening.md", "cooking.md"]);
+    assert!(!hits[0].snippets.is_empty());
+}
+
+#[test]
+fn backlinks_come_with_their_paragraphs() {
+    let (_dir, vault) = common::vault(&[
+        ("Target.md", "The note everyone links to."),
+        ("a.md", "First.\n\nSee [[Target]] for more."),
+        ("b/c.md", "[Target](../Target.md)"),
+        ("d.md", "No links here."),
+    ]);
+    let index = LinkIndex::build(vault.root());
+    let found = backlinks::backlinks(&index, &vault, "Target.md");
+
+    let paths: Vec<&str> = found
+        .iter()
+        .map(|note| note.file.relative_path.as_str())
+        .collect();
+    assert_eq!(paths, ["a.md", "b/c.md"]);
+    assert_eq!(found[0].mentions[0].text, "See [[Target]] for more.");
+}
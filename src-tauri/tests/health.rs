@@ -0,0 +1,68 @@
+//! Vault health, checked against the fixture vault: orphans, dead ends,
+//! same-name wikilinks, and embeds that find an attachment or nothing.
+
+use slate_lib::vault::health::{check, HealthReport, LinkProblem};
+use slate_lib::vault::links::LinkIndex;
+use std::path::{Path, PathBuf};
+
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vault")
+}
+
+fn report() -> HealthReport {
+    let root = fixture();
+    check(&LinkIndex::build(&root), &root)
+}
+
+/// Each problem as (file, line, column, target).
+fn positions(problems: &[LinkProblem]) -> Vec<(&str, usize, usize, &str)> {
+    problems
+        .iter()
+        .map(|p| (p.file.as_str(), p.line, p.column, p.target.as_str()))
+        .collect()
+}
+
+#[test]
+fn orphans_and_dead_ends() {
+    let report = report();
+    assert!(!report.is_healthy());
+    assert_eq!(report.orphans, ["Zebra.md", "attachments/caption.md"]);
+    assert_eq!(
+        report.dead_ends,
+        [
+            "Zebra.md",
+            "attachments/caption.md",
+            "notes/alpha.md",
+            "notes/deep/gamma.md"
+        ]
+    );
+}
+
+#[test]
+fn same_name_wikilinks_are_ambiguous_unless_given_a_path() {
+    let report = report();
+    assert_eq!(
+        positions(&report.ambiguous_links),
+        [("notes/beta.md", 3, 9, "alpha")]
+    );
+    // The note it leads to comes first
+    assert_eq!(
+        report.ambiguous_links[0].candidates,
+        ["alpha.md", "notes/alpha.md"]
+    );
+}
+
+#[test]
+fn embeds_of_attachments_are_not_missing() {
+    let report = report();
+    assert_eq!(
+        positions(&report.broken_links),
+        [("alpha.md", 3, 15, "Missing")]
+    );
+    // diagram.txt is found from the root and next to caption.md
+    assert_eq!(
+        positions(&report.missing_embeds),
+        [("alpha.md", 6, 1, "chart.png")]
+    );
+    assert!(report.missing_embeds[0].candidates.is_empty());
+}
@@ -0,0 +1,142 @@
+//! Links: what counts as a link in a note, and which note or file each one
+//! leads to when several could.
+
+use slate_lib::vault::links::{parse, Anchor, Link, LinkSyntax, Resolver};
+use std::fs;
+use std::path::Path;
+
+fn targets(text: &str) -> Vec<String> {
+    parse(text).into_iter().map(|link| link.target).collect()
+}
+
+fn resolver(paths: &[&str]) -> Resolver {
+    let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+    Resolver::new(&paths)
+}
+
+/// The one link in `text`.
+fn only(text: &str) -> Link {
+    let mut links = parse(text);
+    assert_eq!(links.len(), 1, "{:?}", links);
+    links.remove(0)
+}
+
+#[test]
+fn wikilinks_carry_anchor_alias_and_embed() {
+    let links = parse("See [[Page]], [[folder/Page#Heading|shown]] and ![[diagram.png]]");
+    assert_eq!(links.len(), 3);
+
+    assert_eq!(links[0].syntax, LinkSyntax::Wikilink);
+    assert_eq!(
+        (links[0].target.as_str(), links[0].anchor.clone()),
+        ("Page", None)
+    );
+    assert_eq!((links[0].line, links[0].column, links[0].start), (1, 5, 4));
+
+    assert_eq!(links[1].target, "folder/Page");
+    assert_eq!(
+        links[1].anchor,
+        Some(Anchor::Heading("Heading".to_string()))
+    );
+    assert_eq!(links[1].alias.as_deref(), Some("shown"));
+
+    assert!(links[2].embed && !links[0].embed);
+    assert_eq!(links[2].target, "diagram.png");
+    // The embed's range takes in the `!`
+    assert_eq!(
+        &"See [[Page]], [[folder/Page#Heading|shown]] and ![[diagram.png]]"
+            [links[2].start..links[2].end],
+        "![[diagram.png]]"
+    );
+}
+
+#[test]
+fn same_note_and_malformed_wikilinks() {
+    let link = only("[[#^abc123]]");
+    assert_eq!(link.target, "");
+    assert_eq!(link.anchor, Some(Anchor::Block("abc123".to_string())));
+
+    for text in [
+        "[[#]]",
+        "[[|alias]]",
+        "[[Page|]]",
+        "[[Pa]ge]]",
+        "[[Page\n]]",
+    ] {
+        assert!(parse(text).is_empty(), "{:?}", text);
+    }
+}
+
+#[test]
+fn markdown_links_are_decoded_and_external_ones_skipped() {
+    let links = parse(
+        "[text](notes/a%20b.md#part) ![img](<pics/cat 1.png>) [web](https://x.org) [mail](mailto:a@b) [t](x.md \"title\")",
+    );
+    assert_eq!(links.len(), 3);
+
+    assert_eq!(links[0].syntax, LinkSyntax::Markdown);
+    assert_eq!(links[0].target, "notes/a b.md");
+    assert_eq!(links[0].anchor, Some(Anchor::Heading("part".to_string())));
+    assert_eq!(links[0].alias.as_deref(), Some("text"));
+
+    assert!(links[1].embed);
+    assert_eq!(links[1].target, "pics/cat 1.png");
+    assert_eq!(links[2].target, "x.md");
+}
+
+#[test]
+fn code_is_never_linked() {
+    let text = "```\n[[in fence]]\n```\n`[[inline]]` and ``[[double `tick`]]`` [[real]]\n~~~\n```\n[[still code]]\n~~~\n";
+    let links = parse(text);
+    assert_eq!(targets(text), ["real"]);
+    assert_eq!(links[0].line, 4);
+}
+
+#[test]
+fn wikilinks_prefer_exact_names_then_the_first_by_path() {
+    let resolver = resolver(&["a/Note.md", "b/note.md", "x/Other.md"]);
+    assert_eq!(resolver.resolve_note("Note").unwrap(), "a/Note.md");
+    // An exact match beats an earlier case-insensitive one
+    assert_eq!(resolver.resolve_note("note").unwrap(), "b/note.md");
+    assert_eq!(resolver.resolve_note("NOTE").unwrap(), "a/Note.md");
+    // Paths only count once no name matches
+    assert_eq!(resolver.resolve_note("b/Note").unwrap(), "b/note.md");
+    assert_eq!(resolver.resolve_note("x/other").unwrap(), "x/Other.md");
+    assert_eq!(resolver.resolve_note("Missing"), None);
+}
+
+#[test]
+fn markdown_links_try_the_source_folder_then_the_root() {
+    let resolver = resolver(&["a.md", "notes/a.md", "notes/b.md"]);
+    let link = only("[x](a.md)");
+    assert_eq!(
+        resolver.resolve_to_note("notes/b.md", &link),
+        Some("notes/a.md")
+    );
+    assert_eq!(resolver.resolve_to_note("other/c.md", &link), Some("a.md"));
+
+    let up = only("[x](../a.md)");
+    assert_eq!(resolver.resolve_to_note("notes/b.md", &up), Some("a.md"));
+    let rooted = only("[x](/notes/a)");
+    assert_eq!(
+        resolver.resolve_to_note("b.md", &rooted),
+        Some("notes/a.md")
+    );
+}
+
+#[test]
+fn attachments_resolve_only_if_they_exist() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("pics")).unwrap();
+    fs::write(dir.path().join("pics/photo.png"), b"png").unwrap();
+    let root: &Path = dir.path();
+    let resolver = resolver(&["notes/b.md"]);
+
+    let found = only("![](../pics/photo.png)");
+    assert_eq!(
+        resolver.resolve(root, "notes/b.md", &found).as_deref(),
+        Some("pics/photo.png")
+    );
+    let missing = only("![[pics/other.png]]");
+    assert_eq!(resolver.resolve(root, "notes/b.md", &missing), None);
+}
@@ -0,0 +1,79 @@
+//! Vault locations: user input is expanded against an explicit home
+//! directory and the environment, while handles coming back over IPC are
+//! taken literally.
+
+use slate_lib::vault::{expand_path, VaultHandle};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HOME: &str = "/home/me";
+
+fn expand(input: &str) -> PathBuf {
+    expand_path(input, Some(Path::new(HOME))).unwrap()
+}
+
+fn expand_err(input: &str) -> &'static str {
+    expand_path(input, Some(Path::new(HOME)))
+        .unwrap_err()
+        .code()
+}
+
+#[test]
+fn tilde_and_relative_paths_are_anchored_at_home() {
+    assert_eq!(expand("~"), Path::new(HOME));
+    assert_eq!(expand("~/Notes"), Path::new("/home/me/Notes"));
+    assert_eq!(expand("Notes"), Path::new("/home/me/Notes"));
+    assert_eq!(expand("  /srv/notes  "), Path::new("/srv/notes"));
+}
+
+#[test]
+fn without_a_home_only_absolute_paths_expand() {
+    assert_eq!(
+        expand_path("/srv/notes", None).unwrap(),
+        Path::new("/srv/notes")
+    );
+    for input in ["~", "~/Notes", "Notes"] {
+        let err = expand_path(input, None).unwrap_err();
+        assert_eq!(err.code(), "invalidPath", "{:?}", input);
+    }
+}
+
+#[test]
+fn variables_expand_in_both_forms() {
+    std::env::set_var("SLATE_TEST_VAULTS", "/srv/vaults");
+    assert_eq!(
+        expand("$SLATE_TEST_VAULTS/notes"),
+        Path::new("/srv/vaults/notes")
+    );
+    assert_eq!(
+        expand("${SLATE_TEST_VAULTS}-old"),
+        Path::new("/srv/vaults-old")
+    );
+    // A `$` that starts no variable name is kept
+    assert_eq!(expand("/srv/a$/b"), Path::new("/srv/a$/b"));
+}
+
+#[test]
+fn unset_and_unterminated_variables_are_errors() {
+    std::env::remove_var("SLATE_TEST_UNSET");
+    assert_eq!(expand_err("$SLATE_TEST_UNSET/notes"), "invalidPath");
+    assert_eq!(expand_err("${SLATE_TEST_UNSET}"), "invalidPath");
+    assert_eq!(expand_err("/srv/${SLATE_TEST_VAULTS"), "invalidPath");
+    assert_eq!(expand_err("   "), "invalidPath");
+}
+
+#[test]
+fn handles_round_trip_without_expansion() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("$notes");
+    fs::create_dir(&root).unwrap();
+    let handle = VaultHandle::from_path(&root).unwrap();
+
+    let json = serde_json::to_value(&handle).unwrap();
+    let back: VaultHandle = serde_json::from_value(json).unwrap();
+    assert_eq!(back, handle);
+    assert!(back.root().ends_with("$notes"));
+
+    let relative = serde_json::from_value::<VaultHandle>("notes".into());
+    assert!(relative.is_err());
+}
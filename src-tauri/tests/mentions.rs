@@ -0,0 +1,92 @@
+//! Unlinked mentions: a note's name or aliases written out word for word in
+//! other notes, outside links and code, and never in the note itself.
+
+use slate_lib::vault::backlinks::unlinked_mentions;
+use slate_lib::vault::search::SearchIndex;
+
+mod common;
+
+/// Each note mentioning `path`, with the text of every mention in it.
+fn mentions(notes: &[(&str, &str)], path: &str, aliases: &[&str]) -> Vec<(String, Vec<String>)> {
+    let (_dir, vault) = common::vault(notes);
+    let search = SearchIndex::build(vault.root());
+    let aliases: Vec<String> = aliases.iter().map(|alias| alias.to_string()).collect();
+    unlinked_mentions(&search, &vault, path, &aliases)
+        .into_iter()
+        .map(|note| {
+            let found = note
+                .mentions
+                .iter()
+                .flat_map(|snippet| {
+                    // ASCII text, so UTF-16 units are bytes
+                    snippet
+                        .highlights
+                        .iter()
+                        .map(|&(start, end)| snippet.text[start..end].to_string())
+                })
+                .collect();
+            (note.file.relative_path, found)
+        })
+        .collect()
+}
+
+fn found(path: &str, mentions: &[&str]) -> (String, Vec<String>) {
+    let mentions = mentions.iter().map(|m| m.to_string()).collect();
+    (path.to_string(), mentions)
+}
+
+#[test]
+fn multi_word_names_match_word_for_word() {
+    let notes = [
+        ("Big Plan.md", ""),
+        ("a.md", "The big plan is ready.\n\nA plan that is Big."),
+        ("b.md", "Bigger plans, big planning."),
+        ("c.md", "Not so BIG PLAN after all."),
+    ];
+    assert_eq!(
+        mentions(&notes, "Big Plan.md", &[]),
+        [found("a.md", &["big plan"]), found("c.md", &["BIG PLAN"])]
+    );
+}
+
+#[test]
+fn aliases_count_as_names() {
+    let notes = [
+        ("Project.md", "---\naliases: [The Thing]\n---\n"),
+        ("a.md", "Working on the thing today."),
+        ("b.md", "Project notes, and the thing."),
+        ("c.md", "A thing, the other thing."),
+    ];
+    assert_eq!(
+        mentions(&notes, "Project.md", &["The Thing"]),
+        [
+            found("a.md", &["the thing"]),
+            found("b.md", &["Project", "the thing"])
+        ]
+    );
+}
+
+#[test]
+fn links_and_code_are_not_mentions() {
+    let notes = [
+        ("Plan.md", ""),
+        (
+            "a.md",
+            "[[Plan]] and `plan`\n```\nplan\n```\n[the plan](Plan.md) and [[Other|plan]]",
+        ),
+        ("b.md", "[[Plan]] then plan again"),
+    ];
+    assert_eq!(mentions(&notes, "Plan.md", &[]), [found("b.md", &["plan"])]);
+}
+
+#[test]
+fn a_note_does_not_mention_itself() {
+    let notes = [
+        ("Plan.md", "This plan is mine."),
+        ("notes/Plan.md", "Another plan."),
+    ];
+    assert_eq!(
+        mentions(&notes, "Plan.md", &[]),
+        [found("notes/Plan.md", &["plan"])]
+    );
+}
@@ -0,0 +1,283 @@
+//! Three-way merge: hand-picked cases, plus property tests that apply
+//! generated edit scripts to a base text and merge the results.
+
+use proptest::prelude::*;
+use slate_lib::vault::merge::{merge, merge_unrelated, MergeOutcome, MergeRegion};
+
+fn clean(outcome: MergeOutcome) -> String {
+    match outcome {
+        MergeOutcome::Clean { content } => content,
+        conflicted => panic!("expected a clean merge, got {:?}", conflicted),
+    }
+}
+
+#[test]
+fn edits_to_different_lines_merge() {
+    let base = "one\ntwo\nthree\n";
+    let ours = "ONE\ntwo\nthree\n";
+    let theirs = "one\ntwo\nTHREE\n";
+    assert_eq!(clean(merge(base, ours, theirs)), "ONE\ntwo\nTHREE\n");
+}
+
+#[test]
+fn edits_to_different_words_of_a_line_merge() {
+    let base = "The quick brown fox\n";
+    let ours = "The slow brown fox\n";
+    let theirs = "The quick brown cat\n";
+    assert_eq!(clean(merge(base, ours, theirs)), "The slow brown cat\n");
+}
+
+#[test]
+fn identical_edits_merge() {
+    let base = "a\nb\n";
+    let both = "a\nB\nc\n";
+    assert_eq!(clean(merge(base, both, both)), both);
+}
+
+#[test]
+fn last_line_without_newline_merges() {
+    let base = "a\nb";
+    let ours = "A\nb";
+    let theirs = "a\nb!";
+    assert_eq!(clean(merge(base, ours, theirs)), "A\nb!");
+}
+
+#[test]
+fn overlapping_edits_conflict() {
+    let base = "keep\nchange me\nkeep\n";
+    let ours = "keep\nmine\nkeep\n";
+    let theirs = "keep\ntheirs\nkeep\n";
+
+    let MergeOutcome::Conflicted { content, regions } = merge(base, ours, theirs) else {
+        panic!("expected a conflict");
+    };
+    assert_eq!(
+        regions,
+        vec![
+            MergeRegion::Resolved {
+                text: "keep\n".into()
+            },
+            MergeRegion::Conflict {
+                base: "change me\n".into(),
+                ours: "mine\n".into(),
+                theirs: "theirs\n".into(),
+            },
+            MergeRegion::Resolved {
+                text: "keep\n".into()
+            },
+        ]
+    );
+    assert_eq!(
+        content,
+        "keep\n<<<<<<< ours\nmine\n=======\ntheirs\n>>>>>>> theirs\nkeep\n"
+    );
+}
+
+#[test]
+fn delete_against_edit_conflicts() {
+    let base = "a\nb\nc\n";
+    let ours = "a\nc\n";
+    let theirs = "a\nb changed\nc\n";
+    assert!(!merge(base, ours, theirs).is_clean());
+}
+
+#[test]
+fn unrelated_versions_keep_lines_only_one_side_has() {
+    let ours = "intro\nmine\nmiddle\nend\n";
+    let theirs = "intro\nmiddle\nend\nadded on the phone\n";
+    assert_eq!(
+        clean(merge_unrelated(ours, theirs)),
+        "intro\nmine\nmiddle\nend\nadded on the phone\n"
+    );
+}
+
+#[test]
+fn unrelated_versions_of_the_same_line_conflict() {
+    let outcome = merge_unrelated("a\nmine\nb\n", "a\ntheirs\nb\n");
+    assert!(!outcome.is_clean());
+    assert_eq!(
+        outcome.content(),
+        "a\n<<<<<<< ours\nmine\n=======\ntheirs\n>>>>>>> theirs\nb\n"
+    );
+}
+
+#[test]
+fn identical_unrelated_versions_merge_to_themselves() {
+    let text = "same\ntext\n";
+    assert_eq!(clean(merge_unrelated(text, text)), text);
+}
+
+/// What happens to one base line in an edit script.
+#[derive(Clone, Debug)]
+enum Edit {
+    Keep,
+    Delete,
+    Replace(usize),
+    InsertAfter(usize),
+}
+
+fn edit() -> impl Strategy<Value = Edit> {
+    prop_oneof![
+        4 => Just(Edit::Keep),
+        1 => Just(Edit::Delete),
+        1 => (1..3usize).prop_map(Edit::Replace),
+        1 => (1..3usize).prop_map(Edit::InsertAfter),
+    ]
+}
+
+/// Applies `edits` to `lines`. New lines are tagged with the side that
+/// wrote them (`side` of the base line's index), so they never coincide with
+/// base lines or the other side's lines.
+fn apply_by(lines: &[String], edits: &[Edit], side: impl Fn(usize) -> &'static str) -> Vec<String> {
+    let mut out = Vec::new();
+    for (i, (line, edit)) in lines.iter().zip(edits).enumerate() {
+        let side = side(i);
+        let new = |n: usize| (0..n).map(move |k| format!("{} {}.{}\n", side, i, k));
+        match edit {
+            Edit::Keep => out.push(line.clone()),
+            Edit::Delete => {}
+            Edit::Replace(n) => out.extend(new(*n)),
+            Edit::InsertAfter(n) => {
+                out.push(line.clone());
+                out.extend(new(*n));
+            }
+        }
+    }
+    out
+}
+
+fn apply(lines: &[String], edits: &[Edit], side: &'static str) -> Vec<String> {
+    apply_by(lines, edits, |_| side)
+}
+
+fn base_lines(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("line {}\n", i)).collect()
+}
+
+/// A base text and an edit script for it.
+fn edited(max_lines: usize) -> impl Strategy<Value = (Vec<String>, Vec<Edit>)> {
+    (1..max_lines).prop_flat_map(|n| (Just(base_lines(n)), prop::collection::vec(edit(), n..=n)))
+}
+
+proptest! {
+    #[test]
+    fn unchanged_sides_merge_to_base((base, _) in edited(30)) {
+        let base = base.concat();
+        prop_assert_eq!(clean(merge(&base, &base, &base)), base);
+    }
+
+    #[test]
+    fn one_sided_edits_are_taken((base, edits) in edited(30)) {
+        let changed = apply(&base, &edits, "ours").concat();
+        let base = base.concat();
+        prop_assert_eq!(clean(merge(&base, &changed, &base)), changed.clone());
+        prop_assert_eq!(clean(merge(&base, &base, &changed)), changed);
+    }
+
+    #[test]
+    fn identical_edits_are_taken_once((base, edits) in edited(30)) {
+        let changed = apply(&base, &edits, "both").concat();
+        let base = base.concat();
+        prop_assert_eq!(clean(merge(&base, &changed, &changed)), changed);
+    }
+
+    /// Edits on either side of a line both sides keep never conflict, and
+    /// the result has both.
+    #[test]
+    fn disjoint_edits_merge_cleanly(
+        (base, ours_edits, theirs_edits) in (3..40usize).prop_flat_map(|n| {
+            let mid = n / 2;
+            (
+                Just(base_lines(n)),
+                prop::collection::vec(edit(), mid..=mid),
+                prop::collection::vec(edit(), n - mid - 1..=n - mid - 1),
+            )
+        })
+    ) {
+        let mid = ours_edits.len();
+        let ours_script: Vec<Edit> = ours_edits
+            .iter()
+            .cloned()
+            .chain(std::iter::repeat_n(Edit::Keep, base.len() - mid))
+            .collect();
+        let theirs_script: Vec<Edit> = std::iter::repeat_n(Edit::Keep, mid + 1)
+            .chain(theirs_edits.iter().cloned())
+            .collect();
+        let both_script: Vec<Edit> = ours_edits
+            .iter()
+            .cloned()
+            .chain(std::iter::once(Edit::Keep))
+            .chain(theirs_edits.iter().cloned())
+            .collect();
+
+        let ours = apply(&base, &ours_script, "ours").concat();
+        let theirs = apply(&base, &theirs_script, "theirs").concat();
+        let expected = apply_by(&base, &both_script, |i| if i < mid { "ours" } else { "theirs" })
+            .concat();
+
+        prop_assert_eq!(clean(merge(&base.concat(), &ours, &theirs)), expected);
+    }
+
+    /// Swapping the sides swaps the result and nothing else.
+    #[test]
+    fn merge_is_symmetric(
+        (base, ours_edits, theirs_edits) in (1..30usize).prop_flat_map(|n| (
+            Just(base_lines(n)),
+            prop::collection::vec(edit(), n..=n),
+            prop::collection::vec(edit(), n..=n),
+        ))
+    ) {
+        let ours = apply(&base, &ours_edits, "ours").concat();
+        let theirs = apply(&base, &theirs_edits, "theirs").concat();
+        let base = base.concat();
+
+        match (merge(&base, &ours, &theirs), merge(&base, &theirs, &ours)) {
+            (MergeOutcome::Clean { content: a }, MergeOutcome::Clean { content: b }) => {
+                prop_assert_eq!(a, b)
+            }
+            (
+                MergeOutcome::Conflicted { regions: a, .. },
+                MergeOutcome::Conflicted { regions: b, .. },
+            ) => {
+                let swapped: Vec<MergeRegion> = b
+                    .into_iter()
+                    .map(|region| match region {
+                        MergeRegion::Conflict { base, ours, theirs } => MergeRegion::Conflict {
+                            base,
+                            ours: theirs,
+                            theirs: ours,
+                        },
+                        resolved => resolved,
+                    })
+                    .collect();
+                prop_assert_eq!(a, swapped)
+            }
+            (a, b) => prop_assert!(false, "{:?} vs {:?}", a, b),
+        }
+    }
+
+    /// Conflicts are reported with the exact text each side has there.
+    #[test]
+    fn conflicts_carry_each_sides_text(
+        (base, ours_edits, theirs_edits) in (1..30usize).prop_flat_map(|n| (
+            Just(base_lines(n)),
+            prop::collection::vec(edit(), n..=n),
+            prop::collection::vec(edit(), n..=n),
+        ))
+    ) {
+        let ours = apply(&base, &ours_edits, "ours").concat();
+        let theirs = apply(&base, &theirs_edits, "theirs").concat();
+        let base = base.concat();
+
+        if let MergeOutcome::Conflicted { content, regions } = merge(&base, &ours, &theirs) {
+            for region in &regions {
+                if let MergeRegion::Conflict { base: b, ours: o, theirs: t } = region {
+                    prop_assert!(base.contains(b.as_str()));
+                    prop_assert!(ours.contains(o.as_str()));
+                    prop_assert!(theirs.contains(t.as_str()));
+                    prop_assert!(content.contains(o.as_str()) && content.contains(t.as_str()));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Note I/O: a save is checked against the version the editor loaded, and
+//! a note that cannot be read is never mistaken for a deleted one; writes
+//! keep line endings and permissions and leave nothing behind when they fail.
+
+use slate_lib::vault::note::{self, BaseVersion};
+use std::fs;
+use std::path::Path;
+
+fn base(hash: &str) -> BaseVersion {
+    BaseVersion {
+        hash: Some(hash.to_string()),
+        mtime: None,
+    }
+}
+
+#[test]
+fn unchanged_note_passes_and_changed_note_is_returned() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.md");
+    fs::write(&path, "one\r\n").unwrap();
+    let loaded = note::read(&path).unwrap();
+
+    assert!(note::check_base(&path, &base(&loaded.hash), "two")
+        .unwrap()
+        .is_ok());
+
+    fs::write(&path, "changed elsewhere\n").unwrap();
+    let theirs = note::check_base(&path, &base(&loaded.hash), "two")
+        .unwrap()
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(theirs.content, "changed elsewhere\n");
+
+    // Already holding what would be written, up to line endings
+    fs::write(&path, "two\r\n").unwrap();
+    assert!(note::check_base(&path, &base(&loaded.hash), "two\n")
+        .unwrap()
+        .is_ok());
+}
+
+#[test]
+fn deleted_note_is_a_conflict_with_nothing_on_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("gone.md");
+    let theirs = note::check_base(&path, &base("abc"), "text")
+        .unwrap()
+        .unwrap_err();
+    assert!(theirs.is_none());
+}
+
+#[test]
+fn unreadable_note_is_an_error_not_a_deletion() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("binary.md");
+    fs::write(&binary, [0xff, 0xfe]).unwrap();
+    let err = note::check_base(&binary, &base("abc"), "text").unwrap_err();
+    assert_eq!(err.code(), "invalidEncoding");
+
+    let folder = dir.path().join("folder.md");
+    fs::create_dir(&folder).unwrap();
+    let err = note::check_base(&folder, &base("abc"), "text").unwrap_err();
+    assert_eq!(err.code(), "io");
+}
+
+/// Files in `dir`, sorted.
+fn listing(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn crlf_notes_stay_crlf_and_read_back_normalized() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.md");
+    fs::write(&path, "a\r\nb\r\n").unwrap();
+
+    let mut committed = None;
+    let entry =
+        note::write_atomic(&path, "a\nb\nc\n", |entry| committed = Some(entry.clone())).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"a\r\nb\r\nc\r\n");
+    assert_eq!(committed, Some(entry.clone()));
+
+    let read = note::read(&path).unwrap();
+    assert_eq!(read.content, "a\nb\nc\n");
+    assert_eq!((read.hash, read.mtime), (entry.hash, entry.mtime));
+    assert_eq!(entry.size, 9);
+}
+
+#[test]
+fn mixed_endings_follow_the_dominant_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.md");
+
+    fs::write(&path, "a\r\nb\r\nc\n").unwrap();
+    note::write_atomic(&path, "x\r\ny\n", |_| {}).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"x\r\ny\r\n");
+
+    fs::write(&path, "a\nb\nc\r\n").unwrap();
+    note::write_atomic(&path, "x\r\ny\n", |_| {}).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"x\ny\n");
+
+    // New notes, in new folders, get LF
+    let new = dir.path().join("new/deep/b.md");
+    note::write_atomic(&new, "x\r\ny\r\n", |_| {}).unwrap();
+    assert_eq!(fs::read(&new).unwrap(), b"x\ny\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn permissions_are_kept() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.md");
+    fs::write(&path, "a").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+    note::write_atomic(&path, "b", |_| {}).unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+}
+
+#[test]
+fn failed_replace_leaves_no_temp_file() {
+    let dir = tempfile::tempdir().unwrap();
+    // A folder with something in it cannot be replaced by a file
+    let path = dir.path().join("folder.md");
+    fs::create_dir(&path).unwrap();
+    fs::write(path.join("inside.md"), "x").unwrap();
+
+    let err = note::write_atomic(&path, "text", |_| {}).unwrap_err();
+    assert_eq!(err.code(), "io");
+    assert_eq!(listing(dir.path()), ["folder.md"]);
+    assert_eq!(listing(&path), ["inside.md"]);
+}
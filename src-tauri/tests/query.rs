@@ -0,0 +1,203 @@
+//! Property queries: where a query that does not parse points the user, and
+//! which notes a query picks, in what order, from frontmatter, inline
+//! fields, tags and links.
+
+use slate_lib::vault::frontmatter::PropertyValue;
+use slate_lib::vault::links::LinkIndex;
+use slate_lib::vault::properties::PropertyIndex;
+use slate_lib::vault::query::{block_body, parse};
+use slate_lib::vault::VaultIndex;
+use std::fs;
+
+mod common;
+
+/// The indexes an open vault would have for `notes`.
+struct Indexes {
+    _dir: tempfile::TempDir,
+    index: VaultIndex,
+    properties: PropertyIndex,
+    links: LinkIndex,
+}
+
+fn indexes(notes: &[(&str, &str)]) -> Indexes {
+    let (dir, vault) = common::vault(notes);
+    let mut index = VaultIndex::default();
+    index.reconcile(vault.root());
+    let mut properties = PropertyIndex::default();
+    for (path, entry) in index.iter() {
+        let text = fs::read_to_string(vault.root().join(path)).unwrap();
+        properties.insert(path.clone(), entry.hash.clone(), &text);
+    }
+    let links = LinkIndex::build(vault.root());
+    Indexes {
+        _dir: dir,
+        index,
+        properties,
+        links,
+    }
+}
+
+fn projects() -> Indexes {
+    indexes(&[
+        (
+            "projects/a.md",
+            "---\nstatus: done\ndue: 2026-01-05\n---\n#project\nstatus:: active\n",
+        ),
+        (
+            "projects/b.md",
+            "#project/alpha\nstatus:: active\ndue:: 2026-01-02\n",
+        ),
+        ("projects/c.md", "#project\n"),
+        (
+            "other/d.md",
+            "---\ntags: [project]\nstatus: active\n---\nSee [[a]]\n",
+        ),
+        ("e.md", "[[a]] and #misc"),
+    ])
+}
+
+fn rows(vault: &Indexes, src: &str) -> Vec<(String, Vec<PropertyValue>)> {
+    parse(src)
+        .unwrap()
+        .run(&vault.index, &vault.properties, &vault.links, None)
+        .unwrap()
+}
+
+fn paths(vault: &Indexes, src: &str) -> Vec<String> {
+    rows(vault, src).into_iter().map(|(path, _)| path).collect()
+}
+
+/// The message and UTF-16 span of the error `src` fails to parse with.
+fn error(src: &str) -> (String, usize, usize) {
+    let err = parse(src).unwrap_err();
+    (err.message, err.start, err.end)
+}
+
+#[test]
+fn parse_errors_point_at_the_offending_token() {
+    assert_eq!(
+        error("TABLE a b"),
+        (
+            "Expected a clause: FROM, WHERE, SORT or LIMIT".to_string(),
+            8,
+            9
+        )
+    );
+    assert_eq!(
+        error("LIST FROM"),
+        ("Expected #tag, \"folder\" or [[note]]".to_string(), 9, 9)
+    );
+    assert_eq!(
+        error("LIST WHERE \"open"),
+        ("Unclosed quote".to_string(), 11, 16)
+    );
+    assert_eq!(
+        error("LIST WHERE contains(a)"),
+        ("contains takes 2 arguments".to_string(), 11, 19)
+    );
+    assert_eq!(
+        error("LIST LIMIT -1"),
+        ("Expected a whole number after LIMIT".to_string(), 11, 12)
+    );
+    // Spans count UTF-16 units: 🔥 takes two
+    assert_eq!(
+        error("LIST WHERE \"🔥\" = nope(x)"),
+        ("Unknown function: nope".to_string(), 18, 22)
+    );
+}
+
+#[test]
+fn from_takes_nested_tags_folders_and_links() {
+    let vault = projects();
+    assert_eq!(
+        paths(&vault, "LIST FROM #project"),
+        [
+            "other/d.md",
+            "projects/a.md",
+            "projects/b.md",
+            "projects/c.md"
+        ]
+    );
+    assert_eq!(
+        paths(&vault, "LIST FROM \"projects/\" AND -#project/alpha"),
+        ["projects/a.md", "projects/c.md"]
+    );
+    // A folder source also takes the note at that path
+    assert_eq!(
+        paths(&vault, "LIST FROM \"e\" OR (#project & \"other\")"),
+        ["e.md", "other/d.md"]
+    );
+    assert_eq!(paths(&vault, "LIST FROM [[a]]"), ["e.md", "other/d.md"]);
+    assert!(paths(&vault, "LIST FROM [[missing]]").is_empty());
+}
+
+#[test]
+fn frontmatter_wins_over_inline_fields() {
+    let vault = projects();
+    assert_eq!(
+        paths(&vault, "LIST WHERE status = \"active\""),
+        ["other/d.md", "projects/b.md"]
+    );
+    assert_eq!(
+        rows(&vault, "TABLE status FROM \"projects\"")
+            .into_iter()
+            .map(|(_, values)| values)
+            .collect::<Vec<_>>(),
+        [
+            vec![PropertyValue::Text("done".to_string())],
+            vec![PropertyValue::Text("active".to_string())],
+            vec![PropertyValue::Null],
+        ]
+    );
+}
+
+#[test]
+fn missing_values_sort_last_either_way() {
+    let vault = projects();
+    assert_eq!(
+        paths(&vault, "TABLE due FROM #project SORT due"),
+        [
+            "projects/b.md",
+            "projects/a.md",
+            "other/d.md",
+            "projects/c.md"
+        ]
+    );
+    assert_eq!(
+        paths(&vault, "TABLE due FROM #project SORT due DESC LIMIT 3"),
+        ["projects/a.md", "projects/b.md", "other/d.md"]
+    );
+}
+
+#[test]
+fn a_query_block_runs_from_the_note_it_is_in() {
+    assert_eq!(
+        block_body("```query\nLIST FROM [[]]\n```"),
+        "LIST FROM [[]]"
+    );
+    assert_eq!(
+        block_body("~~~dataview\nTABLE due\nSORT due\n~~~\n"),
+        "TABLE due\nSORT due"
+    );
+    assert_eq!(block_body("  LIST  "), "LIST");
+    assert_eq!(block_body("```query\n```"), "");
+
+    let vault = projects();
+    let query = parse(block_body("```query\nLIST FROM [[]]\n```")).unwrap();
+    let found = query
+        .run(
+            &vault.index,
+            &vault.properties,
+            &vault.links,
+            Some("projects/a.md"),
+        )
+        .unwrap();
+    let found: Vec<&str> = found.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(found, ["e.md", "other/d.md"]);
+
+    // Outside a note there is nothing for [[]] to mean
+    let err = query
+        .run(&vault.index, &vault.properties, &vault.links, None)
+        .unwrap_err();
+    assert_eq!(err.code(), "invalidInput");
+}
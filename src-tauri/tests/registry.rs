@@ -0,0 +1,163 @@
+//! Vault registry: a vault stays open while any window holds it, each window
+//! remembers its own vault, and the config survives a restart.
+
+use slate_lib::vault::{VaultConfig, VaultHandle, VaultRegistry};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+
+struct Setup {
+    dir: tempfile::TempDir,
+    a: VaultHandle,
+    b: VaultHandle,
+}
+
+impl Setup {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let handle = |name: &str| {
+            let path = dir.path().join(name);
+            std::fs::create_dir(&path).unwrap();
+            VaultHandle::from_path(&path).unwrap()
+        };
+        let (a, b) = (handle("a"), handle("b"));
+        Setup { dir, a, b }
+    }
+
+    fn config(&self) -> PathBuf {
+        self.dir.path().join("config/vault.json")
+    }
+
+    fn registry(&self) -> VaultRegistry {
+        VaultRegistry::with_config(&self.config())
+    }
+}
+
+fn open(registry: &VaultRegistry) -> Vec<String> {
+    registry
+        .list()
+        .into_iter()
+        .filter(|info| info.is_open)
+        .map(|info| info.name)
+        .collect()
+}
+
+#[test]
+fn a_vault_stays_open_until_its_last_window_closes_it() {
+    let setup = Setup::new();
+    let registry = setup.registry();
+    registry.open("main", setup.a.clone()).unwrap();
+    registry.open("second", setup.a.clone()).unwrap();
+    assert_eq!(open(&registry), ["a"]);
+
+    assert!(registry.close("main", setup.a.root()));
+    assert!(registry.with_open(&setup.a, |_| ()).is_some());
+    // A window cannot close a vault it does not hold
+    assert!(!registry.close("main", setup.a.root()));
+    assert!(!registry.close("other", setup.b.root()));
+
+    assert!(registry.close("second", setup.a.root()));
+    assert!(registry.with_open(&setup.a, |_| ()).is_none());
+    // Closing keeps the vault in the known list
+    assert_eq!(registry.list().len(), 1);
+    assert!(open(&registry).is_empty());
+}
+
+#[test]
+fn forgetting_drops_the_vault_everywhere() {
+    let setup = Setup::new();
+    let registry = setup.registry();
+    registry.open("main", setup.a.clone()).unwrap();
+    registry.open("second", setup.b.clone()).unwrap();
+
+    registry.forget(setup.a.root()).unwrap();
+    assert!(registry.with_open(&setup.a, |_| ()).is_none());
+    let names: Vec<String> = registry.list().into_iter().map(|info| info.name).collect();
+    assert_eq!(names, ["b"]);
+    // main falls back to the vault used last anywhere
+    assert_eq!(registry.last_vault("main"), Some(setup.b.clone()));
+
+    // Forgetting works on a path that no longer exists
+    let gone = setup.b.root().to_path_buf();
+    std::fs::remove_dir(&gone).unwrap();
+    registry.forget(&gone).unwrap();
+    assert!(registry.list().is_empty());
+    assert_eq!(registry.last_vault("second"), None);
+}
+
+#[test]
+fn each_window_remembers_its_own_vault() {
+    let setup = Setup::new();
+    let registry = setup.registry();
+    registry.open("main", setup.a.clone()).unwrap();
+    registry.open("second", setup.b.clone()).unwrap();
+
+    assert_eq!(registry.last_vault("main"), Some(setup.a.clone()));
+    assert_eq!(registry.last_vault("second"), Some(setup.b.clone()));
+    // A new window starts from the vault opened last
+    assert_eq!(registry.last_vault("third"), Some(setup.b.clone()));
+}
+
+#[test]
+fn switching_vaults_cancels_the_previous_scan() {
+    let setup = Setup::new();
+    let registry = setup.registry();
+    registry.open("main", setup.a.clone()).unwrap();
+    let scan = registry.begin_scan(&setup.a);
+
+    // Another window opening the same vault leaves the scan alone
+    registry.open("second", setup.a.clone()).unwrap();
+    assert!(!scan.load(Ordering::Relaxed));
+
+    registry.open("main", setup.b.clone()).unwrap();
+    assert!(scan.load(Ordering::Relaxed));
+    assert!(!registry.cancel_scan(setup.a.root()));
+
+    // A newer scan of the same vault replaces the old one
+    let first = registry.begin_scan(&setup.b);
+    let second = registry.begin_scan(&setup.b);
+    assert!(first.load(Ordering::Relaxed));
+    registry.end_scan(&setup.b, &first);
+    assert!(registry.cancel_scan(setup.b.root()));
+    assert!(second.load(Ordering::Relaxed));
+}
+
+#[test]
+fn config_survives_a_restart() {
+    let setup = Setup::new();
+    let registry = setup.registry();
+    registry.open("main", setup.a.clone()).unwrap();
+    registry.open("second", setup.b.clone()).unwrap();
+    drop(registry);
+
+    let config = VaultConfig::load_from(&setup.config());
+    assert_eq!(
+        config.known_vaults,
+        [setup.a.root().to_path_buf(), setup.b.root().to_path_buf()]
+    );
+    assert_eq!(config.last_vault.as_deref(), Some(setup.b.root()));
+
+    // Nothing is open after a restart, but every window finds its vault
+    let registry = setup.registry();
+    assert_eq!(registry.list().len(), 2);
+    assert!(open(&registry).is_empty());
+    assert_eq!(registry.last_vault("main"), Some(setup.a.clone()));
+    assert_eq!(registry.last_vault("second"), Some(setup.b.clone()));
+}
+
+#[test]
+fn configs_from_before_the_registry_still_load() {
+    let setup = Setup::new();
+    let config = VaultConfig {
+        last_vault: Some(setup.a.root().to_path_buf()),
+        ..Default::default()
+    };
+    config.save_to(&setup.config()).unwrap();
+
+    let registry = setup.registry();
+    assert_eq!(registry.list()[0].name, "a");
+    assert_eq!(registry.last_vault("main"), Some(setup.a.clone()));
+
+    // A corrupt config is ignored
+    std::fs::write(setup.config(), "{ not json").unwrap();
+    assert!(setup.registry().list().is_empty());
+}
@@ -0,0 +1,174 @@
+//! Renaming notes: links that would break are rewritten in the style they
+//! were written in, and nothing moves unless every rewrite can be made.
+
+use slate_lib::vault::links::LinkIndex;
+use slate_lib::vault::rename::{sources, verify, Rename};
+use slate_lib::vault::VaultHandle;
+use std::fs;
+use std::path::Path;
+
+mod common;
+
+struct Vault {
+    _dir: tempfile::TempDir,
+    handle: VaultHandle,
+    paths: Vec<String>,
+}
+
+impl Vault {
+    fn new(notes: &[(&str, &str)]) -> Self {
+        let (dir, handle) = common::vault(notes);
+        let mut paths: Vec<String> = notes.iter().map(|(p, _)| p.to_string()).collect();
+        paths.sort();
+        Vault {
+            _dir: dir,
+            handle,
+            paths,
+        }
+    }
+
+    fn root(&self) -> &Path {
+        self.handle.root()
+    }
+
+    /// `source` after moving `from` to `to`.
+    fn rewrite(&self, from: &str, to: &str, source: &str) -> String {
+        let text = fs::read_to_string(self.root().join(source)).unwrap();
+        Rename::new(self.root(), &self.paths, from, to)
+            .rewrite(source, &text)
+            .0
+    }
+}
+
+#[test]
+fn wikilinks_keep_their_anchor_and_alias() {
+    let vault = Vault::new(&[
+        ("Old.md", ""),
+        (
+            "a.md",
+            "See [[Old#Heading|the old one]] and ![[Old#^block]].",
+        ),
+    ]);
+    assert_eq!(
+        vault.rewrite("Old.md", "New.md", "a.md"),
+        "See [[New#Heading|the old one]] and ![[New#^block]]."
+    );
+}
+
+#[test]
+fn wikilinks_written_by_path_stay_paths() {
+    let vault = Vault::new(&[("folder/Old.md", ""), ("a.md", "[[folder/Old]]")]);
+    assert_eq!(
+        vault.rewrite("folder/Old.md", "other/Old.md", "a.md"),
+        "[[other/Old]]"
+    );
+}
+
+#[test]
+fn links_that_still_resolve_are_left_alone() {
+    let vault = Vault::new(&[("Old.md", ""), ("a.md", "[[Old]]")]);
+    let text = "[[Old]]";
+    let (rewritten, edits) =
+        Rename::new(vault.root(), &vault.paths, "Old.md", "archive/Old.md").rewrite("a.md", text);
+    assert_eq!(rewritten, text);
+    assert!(edits.is_empty());
+}
+
+#[test]
+fn links_the_new_name_would_take_over_are_written_by_path() {
+    let vault = Vault::new(&[
+        ("a/bar.md", ""),
+        ("x/foo.md", ""),
+        ("n.md", "[[foo]] and [[bar]]"),
+        ("m.md", "[[x/foo]]"),
+    ]);
+    // Notes linking to x/foo.md are looked at too, since foo.md would win
+    // `[[foo]]` from it
+    let index = LinkIndex::build(vault.root());
+    assert_eq!(
+        sources(&index, "a/bar.md", "foo.md"),
+        ["m.md", "n.md", "a/bar.md"]
+    );
+    assert_eq!(
+        vault.rewrite("a/bar.md", "foo.md", "n.md"),
+        "[[x/foo]] and [[foo]]"
+    );
+    assert_eq!(vault.rewrite("a/bar.md", "foo.md", "m.md"), "[[x/foo]]");
+}
+
+#[test]
+fn markdown_links_stay_relative_and_keep_anchors() {
+    let vault = Vault::new(&[
+        ("Old.md", ""),
+        (
+            "notes/a.md",
+            "[x](../Old.md#part) and [y](../Old.md \"title\")",
+        ),
+    ]);
+    assert_eq!(
+        vault.rewrite("Old.md", "archive/Old Note.md", "notes/a.md"),
+        "[x](../archive/Old%20Note.md#part) and [y](../archive/Old%20Note.md \"title\")"
+    );
+}
+
+#[test]
+fn markdown_links_from_the_root_stay_rooted_and_are_percent_encoded() {
+    let vault = Vault::new(&[("Old.md", ""), ("notes/a.md", "[x](/Old.md)")]);
+    assert_eq!(
+        vault.rewrite("Old.md", "archive/Old (v2).md", "notes/a.md"),
+        "[x](/archive/Old%20%28v2%29.md)"
+    );
+}
+
+#[test]
+fn angle_bracket_destinations_are_not_encoded() {
+    let vault = Vault::new(&[("Old.md", ""), ("a.md", "[x](<Old.md>)")]);
+    assert_eq!(
+        vault.rewrite("Old.md", "archive/Old Note.md", "a.md"),
+        "[x](<archive/Old Note.md>)"
+    );
+}
+
+#[test]
+fn moved_note_keeps_its_own_relative_links_working() {
+    let vault = Vault::new(&[
+        ("sibling.md", ""),
+        ("notes/Old.md", "[s](../sibling.md) and [[sibling]]"),
+    ]);
+    let text = fs::read_to_string(vault.root().join("notes/Old.md")).unwrap();
+    let (rewritten, edits) = Rename::new(
+        vault.root(),
+        &vault.paths,
+        "notes/Old.md",
+        "notes/deep/Old.md",
+    )
+    .rewrite("notes/Old.md", &text);
+
+    assert_eq!(rewritten, "[s](../../sibling.md) and [[sibling]]");
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].before, "[s](../sibling.md)");
+}
+
+#[test]
+fn plan_is_refused_if_a_note_changed_since_it_was_read() {
+    let vault = Vault::new(&[("Old.md", ""), ("a.md", "[[Old]]"), ("b.md", "[[Old]]")]);
+    let sources = ["a.md".to_string(), "b.md".to_string()];
+    let plan = Rename::new(vault.root(), &vault.paths, "Old.md", "New.md").plan(&sources);
+    assert_eq!(plan.notes.len(), 2);
+    assert!(verify(vault.root(), &plan.notes, &plan.failed).is_ok());
+
+    fs::write(vault.root().join("b.md"), "[[Old]] edited elsewhere").unwrap();
+    let err = verify(vault.root(), &plan.notes, &plan.failed).unwrap_err();
+    assert_eq!(err.code(), "invalidInput");
+    assert!(err.message().contains("b.md"));
+}
+
+#[test]
+fn plan_is_refused_if_a_note_cannot_be_read() {
+    let vault = Vault::new(&[("Old.md", ""), ("a.md", "[[Old]]")]);
+    fs::write(vault.root().join("a.md"), [b'[', b'[', 0xff, b']', b']']).unwrap();
+    let sources = ["a.md".to_string()];
+    let plan = Rename::new(vault.root(), &vault.paths, "Old.md", "New.md").plan(&sources);
+    assert_eq!(plan.failed, ["a.md"]);
+    assert!(verify(vault.root(), &plan.notes, &plan.failed).is_err());
+}
@@ -0,0 +1,172 @@
+//! Vault scanning: which files count as notes, and in what order, against
+//! the fixture vault on disk and the same tree held in memory.
+
+use slate_lib::vault::fs::MemoryFs;
+use slate_lib::vault::{FileEntry, Vault, VaultHandle, VaultIndex};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+/// Notes in `tests/fixtures/vault`, sorted by relative path. Everything
+/// else there is hidden or not markdown.
+const NOTES: [&str; 6] = [
+    "Zebra.md",
+    "alpha.md",
+    "attachments/caption.md",
+    "notes/alpha.md",
+    "notes/beta.md",
+    "notes/deep/gamma.md",
+];
+
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vault")
+}
+
+fn paths(entries: &[FileEntry]) -> Vec<&str> {
+    entries.iter().map(|e| e.relative_path.as_str()).collect()
+}
+
+#[test]
+fn fixture_scan_skips_hidden_and_non_markdown_files() {
+    let root = fixture();
+    let entries = Vault::on_disk(&root).scan();
+
+    assert_eq!(paths(&entries), NOTES);
+    let gamma = &entries[5];
+    assert_eq!(gamma.name, "gamma.md");
+    assert_eq!(Path::new(&gamma.path), root.join("notes/deep/gamma.md"));
+}
+
+#[test]
+fn memory_tree_scans_like_the_fixture() {
+    let mut files = Vec::new();
+    for note in NOTES {
+        files.push((Path::new("/vault").join(note), "# Note"));
+    }
+    for other in [
+        ".obsidian/plugins.md",
+        ".obsidian/workspace.json",
+        "notes/.drafts/idea.md",
+        "notes/.secret.md",
+        "attachments/diagram.txt",
+        "readme.txt",
+    ] {
+        files.push((Path::new("/vault").join(other), "other"));
+    }
+    let vault = Vault::new("/vault", MemoryFs::with_files(&files));
+
+    assert_eq!(paths(&vault.scan()), NOTES);
+}
+
+#[test]
+fn scan_sorts_by_relative_path_bytes() {
+    let fs = MemoryFs::with_files(&[
+        ("/vault/b.md", ""),
+        ("/vault/a/z.md", ""),
+        ("/vault/B.md", ""),
+        ("/vault/a.md", ""),
+        ("/vault/a b.md", ""),
+    ]);
+    let vault = Vault::new("/vault", fs);
+
+    assert_eq!(
+        paths(&vault.scan()),
+        ["B.md", "a b.md", "a.md", "a/z.md", "b.md"]
+    );
+}
+
+#[test]
+fn conflicted_copies_are_found_without_the_real_disk() {
+    // Nothing exists at /vault on disk; originals are looked up in memory
+    let fs = MemoryFs::with_files(&[
+        ("/vault/Note.md", "mine"),
+        ("/vault/Note 2.md", "theirs"),
+        ("/vault/Chapter 2.md", ""),
+    ]);
+    let vault = Vault::new("/vault", fs);
+
+    let entries = vault.scan();
+    assert_eq!(paths(&entries), ["Chapter 2.md", "Note 2.md", "Note.md"]);
+    assert!(entries[0].conflict.is_none());
+    let copy = entries[1].conflict.as_ref().unwrap();
+    assert_eq!(copy.original.relative_path, "Note.md");
+
+    let delta = VaultIndex::default().reconcile_with(&vault);
+    let copy = delta.added[1].conflict.as_ref().unwrap();
+    assert_eq!(copy.original.relative_path, "Note.md");
+}
+
+#[test]
+fn hidden_root_is_still_scanned() {
+    let fs = MemoryFs::with_files(&[
+        ("/home/me/.notes/today.md", ""),
+        ("/home/me/.notes/.trash/old.md", ""),
+    ]);
+    let vault = Vault::new("/home/me/.notes", fs);
+
+    assert_eq!(paths(&vault.scan()), ["today.md"]);
+    assert!(!vault.is_hidden(Path::new("/home/me/.notes/today.md")));
+    assert!(vault.is_hidden(Path::new("/home/me/.notes/.trash/old.md")));
+    assert!(vault.is_hidden(Path::new("/home/me/other.md")));
+}
+
+#[test]
+fn reconcile_reports_changes_on_any_filesystem() {
+    let fs = MemoryFs::with_files(&[
+        ("/vault/a.md", "one"),
+        ("/vault/b.md", "two"),
+        ("/vault/c.md", "three"),
+    ]);
+    let vault = Vault::new("/vault", fs);
+    let mut index = VaultIndex::default();
+
+    let delta = index.reconcile_with(&vault);
+    assert_eq!(paths(&delta.added), ["a.md", "b.md", "c.md"]);
+
+    vault.fs().write(Path::new("/vault/a.md"), b"one, edited");
+    vault.fs().remove(Path::new("/vault/b.md"));
+    // Touched, but the content is the same
+    vault.fs().write(Path::new("/vault/c.md"), b"three");
+    vault
+        .fs()
+        .write(Path::new("/vault/.slate/index.md"), b"ignored");
+
+    let delta = index.reconcile_with(&vault);
+    assert!(delta.added.is_empty());
+    assert_eq!(paths(&delta.modified), ["a.md"]);
+    assert_eq!(delta.removed, ["b.md"]);
+}
+
+#[test]
+fn reconcile_indexes_the_files_already_walked_until_cancelled() {
+    let fs = MemoryFs::with_files(&[("/vault/a.md", "one"), ("/vault/b.md", "two")]);
+    let vault = Vault::new("/vault", fs);
+    let walked: Vec<PathBuf> = vault.markdown_files().collect();
+    // Added after the walk, so not indexed: nothing walks the vault again
+    vault.fs().write(Path::new("/vault/c.md"), b"three");
+
+    let mut index = VaultIndex::default();
+    let delta = index
+        .reconcile_paths(&vault, walked.clone(), &AtomicBool::new(false))
+        .unwrap();
+    assert_eq!(paths(&delta.added), ["a.md", "b.md"]);
+    assert_eq!(paths(&index.entries(vault.root())), ["a.md", "b.md"]);
+
+    let mut index = VaultIndex::default();
+    let cancelled = index.reconcile_paths(&vault, walked, &AtomicBool::new(true));
+    assert!(cancelled.is_none());
+}
+
+#[test]
+fn resolve_takes_home_from_the_caller() {
+    let home = tempfile::tempdir().unwrap();
+    fs::create_dir(home.path().join("Vault")).unwrap();
+    let expected = VaultHandle::from_path(&home.path().join("Vault")).unwrap();
+
+    for input in ["~/Vault", "Vault"] {
+        let handle = VaultHandle::resolve(input, Some(home.path())).unwrap();
+        assert_eq!(handle, expected, "{:?}", input);
+    }
+    let err = VaultHandle::resolve("~/Vault", None).unwrap_err();
+    assert_eq!(err.code(), "invalidPath");
+}
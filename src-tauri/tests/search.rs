@@ -0,0 +1,101 @@
+//! Search queries: parse errors point at the offending part of the query in
+//! UTF-16 units, and scoped operators see the same headings, code blocks,
+//! tasks and tags as the rest of the app.
+
+use slate_lib::vault::search::query::{parse, Note};
+
+fn error(query: &str) -> (String, usize, usize) {
+    let err = parse(query).unwrap_err();
+    (err.message, err.start, err.end)
+}
+
+fn matches(query: &str, text: &str) -> bool {
+    let note = Note {
+        path: "notes/today.md",
+        text,
+    };
+    parse(query).unwrap().matches(&note)
+}
+
+#[test]
+fn error_spans_count_utf16_units() {
+    // 🔥 is two UTF-16 units, ï one
+    let (message, start, end) = error("🔥 naïve (meeting");
+    assert_eq!(message, "Unclosed '('");
+    assert_eq!((start, end), (9, 10));
+
+    let (_, start, end) = error("café \"unclosed");
+    assert_eq!((start, end), (5, 14));
+}
+
+#[test]
+fn unbalanced_groups_are_errors() {
+    assert_eq!(error("a )"), ("Unmatched ')'".to_string(), 2, 3));
+    assert_eq!(error("(a (b)"), ("Unclosed '('".to_string(), 0, 1));
+    assert_eq!(error("a ()"), ("Empty group".to_string(), 2, 4));
+}
+
+#[test]
+fn or_needs_something_on_both_sides() {
+    assert_eq!(error("meeting OR"), ("Nothing after OR".to_string(), 8, 10));
+    assert_eq!(error("OR meeting"), ("Nothing before OR".to_string(), 0, 2));
+    assert_eq!(error("(a OR)"), ("Nothing after OR".to_string(), 3, 5));
+    assert!(matches("meeting OR standup", "daily standup"));
+}
+
+#[test]
+fn bare_negation_is_an_error() {
+    for query in ["-", "a -", "a - b", "(a -)"] {
+        let (message, _, _) = error(query);
+        assert_eq!(message, "Nothing to negate after '-'", "{:?}", query);
+    }
+    assert!(matches("-archive", "today"));
+}
+
+#[test]
+fn operators_without_a_value_are_errors() {
+    assert_eq!(
+        error("(path:)"),
+        ("Missing value after path:".to_string(), 1, 6)
+    );
+    assert_eq!(
+        error("file:\"\""),
+        ("Missing value after file:".to_string(), 5, 7)
+    );
+    assert_eq!(error("path: x").0, "Missing value after path:");
+    assert_eq!(error("tag:#").0, "Missing tag name after tag:");
+}
+
+#[test]
+fn sections_ignore_headings_in_any_code_block() {
+    let text = "# One\nalpha\n~~~~\n```\n# fake\n```\n~~~~\nbeta\n# Two\ngamma\n";
+    assert!(matches("section:(alpha beta)", text));
+    assert!(!matches("section:(alpha gamma)", text));
+}
+
+#[test]
+fn tasks_are_read_like_the_task_list() {
+    let text = "- [ ] call Alice\n- [x] email Bob\n- [-] skip Carol\n-[ ] not a task Dan\n";
+    assert!(matches("task-todo:alice", text));
+    assert!(matches("task-done:bob", text));
+    assert!(matches("task-done:carol", text));
+    assert!(!matches("task:dan", text));
+}
+
+#[test]
+fn tasks_in_code_blocks_do_not_count() {
+    let text = "```\n- [ ] call Alice\n```\n- [ ] call Bob\n";
+    assert!(!matches("task:alice", text));
+    assert!(matches("task:bob", text));
+}
+
+#[test]
+fn tags_are_read_like_the_tag_list() {
+    let text = "---\ntags: [project/alpha]\n---\n`#code` and #123\n```\n#fenced\n```\n";
+    assert!(matches("tag:project", text));
+    assert!(matches("tag:#project/alpha", text));
+    assert!(!matches("tag:code", text));
+    assert!(!matches("tag:123", text));
+    assert!(!matches("tag:fenced", text));
+    assert!(!matches("tag:proj", text));
+}
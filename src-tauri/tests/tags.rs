@@ -0,0 +1,132 @@
+//! Tags: what counts as a tag, how nested tags add up in the tree, and
+//! renaming a tag with the ones nested under it without touching code.
+
+use slate_lib::vault::links::LinkIndex;
+use slate_lib::vault::tags::{parse, rename_names, retag, tree, TagNode};
+use std::fs;
+
+fn names(text: &str) -> Vec<String> {
+    parse(text).into_iter().map(|tag| tag.name).collect()
+}
+
+/// Each node as (tag, count, total), depth first.
+fn flatten(nodes: &[TagNode], out: &mut Vec<(String, usize, usize)>) {
+    for node in nodes {
+        out.push((node.tag.clone(), node.count, node.total));
+        flatten(&node.children, out);
+    }
+}
+
+#[test]
+fn tags_start_a_word_and_are_not_all_digits() {
+    assert_eq!(
+        names("#tag a#not #1 # Heading #nested/tag/ (#paren) [#br],#comma #ünï"),
+        ["tag", "nested/tag", "paren", "br", "comma", "ünï"]
+    );
+
+    // Same-note links and anchors are links
+    assert_eq!(
+        names("[[#Heading]] [jump](#intro) [[Page#Part|#alias]] #real"),
+        ["real"]
+    );
+
+    let text = "---\ntitle: \"#nope\"\n---\nSee `#code` and #yes";
+    let tags = parse(text);
+    assert_eq!(tags.len(), 1);
+    assert_eq!(&text[tags[0].start..tags[0].end], "#yes");
+}
+
+#[test]
+fn tree_counts_notes_under_each_parent() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.md"), "#project/alpha #Project").unwrap();
+    fs::write(
+        dir.path().join("b.md"),
+        "---\ntags: [project/beta]\n---\n#project/alpha",
+    )
+    .unwrap();
+    fs::write(dir.path().join("c.md"), "#solo").unwrap();
+
+    let nodes = tree(&LinkIndex::build(dir.path()));
+    let mut flat = Vec::new();
+    flatten(&nodes, &mut flat);
+    let expected = [
+        ("project", 1, 2),
+        ("project/alpha", 2, 2),
+        ("project/beta", 1, 1),
+        ("solo", 1, 1),
+    ];
+    let expected: Vec<(String, usize, usize)> = expected
+        .into_iter()
+        .map(|(tag, count, total)| (tag.to_string(), count, total))
+        .collect();
+    assert_eq!(flat, expected);
+    assert_eq!(nodes[0].children[0].name, "alpha");
+}
+
+#[test]
+fn renames_carry_nested_tags_along() {
+    assert_eq!(
+        retag(
+            "#project and #project/alpha and #projects and #Project/Beta\n",
+            "project",
+            "work"
+        )
+        .unwrap(),
+        "#work and #work/alpha and #projects and #work/Beta\n"
+    );
+}
+
+#[test]
+fn tags_in_code_are_left_alone() {
+    assert_eq!(
+        retag(
+            "`#project` and\n```\n#project\n```\n#project\n",
+            "project",
+            "work"
+        )
+        .unwrap(),
+        "`#project` and\n```\n#project\n```\n#work\n"
+    );
+}
+
+#[test]
+fn frontmatter_tags_merge_without_duplicates() {
+    assert_eq!(
+        retag(
+            "---\ntags: [work, project/alpha, Project]\n---\nBody #project\n",
+            "project",
+            "work"
+        )
+        .unwrap(),
+        "---\ntags: [work, work/alpha]\n---\nBody #work\n"
+    );
+    assert_eq!(
+        retag(
+            "---\ntags:\n  - project\n  - work\n---\n",
+            "project",
+            "work"
+        )
+        .unwrap(),
+        "---\ntags:\n  - work\n---\n"
+    );
+}
+
+#[test]
+fn a_tag_cannot_move_under_itself() {
+    let err = rename_names("project", "project/sub").unwrap_err();
+    assert_eq!(err.code(), "invalidInput");
+
+    assert_eq!(
+        rename_names(" #project ", "#projects").unwrap(),
+        ("project".to_string(), "projects".to_string())
+    );
+    // Only the case changes
+    assert_eq!(
+        rename_names("Project", "project").unwrap(),
+        ("Project".to_string(), "project".to_string())
+    );
+    for (from, to) in [("a", "a"), ("a", "1"), ("a", "b c"), ("", "b")] {
+        assert!(rename_names(from, to).is_err(), "{:?} -> {:?}", from, to);
+    }
+}
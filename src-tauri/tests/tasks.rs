@@ -0,0 +1,96 @@
+//! Tasks: what a note's `- [ ]` items parse to, IDs that survive edits
+//! around a task, and toggling a task found by its ID.
+
+use slate_lib::vault::tasks::{parse, toggle, Priority, Task};
+
+fn texts(tasks: &[Task]) -> Vec<&str> {
+    tasks.iter().map(|t| t.text.as_str()).collect()
+}
+
+#[test]
+fn tasks_nest_under_their_headings_and_parents() {
+    let text =
+        "# Project\n## Week\n- [ ] parent\n    - [x] child\n- [ ] sibling\n## Later\n- [ ] later\n";
+    let tasks = parse("p.md", text);
+    assert_eq!(texts(&tasks), ["parent", "child", "sibling", "later"]);
+
+    let (parent, child, sibling, later) = (&tasks[0], &tasks[1], &tasks[2], &tasks[3]);
+    assert_eq!(parent.headings, ["Project", "Week"]);
+    assert_eq!(later.headings, ["Project", "Later"]);
+    assert_eq!(parent.subtasks, [child.id.clone()]);
+    assert_eq!(child.parent.as_ref(), Some(&parent.id));
+    assert!(child.completed && !parent.completed);
+    assert_eq!(sibling.parent, None);
+    assert_eq!((parent.line, later.line), (3, 7));
+}
+
+#[test]
+fn tasks_in_code_and_frontmatter_are_skipped() {
+    let text =
+        "---\n- [ ] in frontmatter\n---\n```\n- [ ] in code\n```\n- [ ] real\n-[ ] not a task\n";
+    let tasks = parse("p.md", text);
+    assert_eq!(texts(&tasks), ["real"]);
+    assert_eq!(tasks[0].line, 7);
+}
+
+#[test]
+fn dates_and_priority_come_from_emoji_or_fields() {
+    let tasks = parse(
+        "p.md",
+        "- [ ] pay rent 📅 2026-11-01 ⏫ #home\n\
+         - [ ] file taxes [due:: 2026-04-15] [priority:: low]\n\
+         - [ ] call due:: 2026-05-01 scheduled:: soon\n",
+    );
+    assert_eq!(tasks[0].due.as_deref(), Some("2026-11-01"));
+    assert_eq!(tasks[0].priority, Some(Priority::High));
+    assert_eq!(tasks[0].tags, ["home"]);
+
+    assert_eq!(tasks[1].due.as_deref(), Some("2026-04-15"));
+    assert_eq!(tasks[1].priority, Some(Priority::Low));
+
+    assert_eq!(tasks[2].due.as_deref(), Some("2026-05-01"));
+    assert_eq!(tasks[2].scheduled, None);
+    assert_eq!(tasks[2].priority, None);
+}
+
+#[test]
+fn ids_survive_moves_and_checking() {
+    let before = parse("p.md", "- [ ] a\n- [ ] b\n- [ ] b\n");
+    let after = parse("p.md", "# New heading\n- [ ] b\n- [x] a\n- [ ] b\n");
+
+    assert_eq!(before[0].id, after[1].id);
+    // Tasks with the same text keep their order among themselves
+    assert_eq!(before[1].id, after[0].id);
+    assert_eq!(before[2].id, after[2].id);
+    assert_ne!(before[1].id, before[2].id);
+
+    let elsewhere = parse("q.md", "- [ ] a\n");
+    assert_ne!(before[0].id, elsewhere[0].id);
+}
+
+#[test]
+fn toggle_flips_only_the_task_with_that_id() {
+    let text = "- [ ] a\r\n- [-] b\r\n- [ ] a\r\n";
+    let tasks = parse("p.md", text);
+
+    assert_eq!(
+        toggle("p.md", text, &tasks[2].id).unwrap(),
+        "- [ ] a\r\n- [-] b\r\n- [x] a\r\n"
+    );
+    // Any status other than a space unchecks
+    assert_eq!(
+        toggle("p.md", text, &tasks[1].id).unwrap(),
+        "- [ ] a\r\n- [ ] b\r\n- [ ] a\r\n"
+    );
+
+    let toggled = toggle("p.md", text, &tasks[0].id).unwrap();
+    assert_eq!(parse("p.md", &toggled)[0].id, tasks[0].id);
+    assert!(parse("p.md", &toggled)[0].completed);
+}
+
+#[test]
+fn toggling_a_task_that_is_gone_fails() {
+    let tasks = parse("p.md", "- [ ] a\n");
+    let err = toggle("p.md", "- [ ] renamed\n", &tasks[0].id).unwrap_err();
+    assert_eq!(err.code(), "invalidInput");
+}
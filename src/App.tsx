@@ -1,13 +1,13 @@
 import { onMount, onCleanup, Show, For } from 'solid-js';
+import { confirm } from '@tauri-apps/plugin-dialog';
 
-import { readTextFile, writeTextFile, exists } from '@tauri-apps/plugin-fs';
 import WysiwygEditor from './components/WysiwygEditor';
 import FileFinder from './components/FileFinder';
-import { scanVault, type FileEntry } from './services/fileService';
+import { scanVaultStreaming, openVault, getLastVault, onIndexDelta, applyIndexDelta, onVaultChange, readNote, writeNote, mergeNote, resolveConflict, isWriteConflict, isSlateError, errorMessage, type ConflictResolution, type FileEntry } from './services/fileService';
 import { createAppStore, AppStoreProvider } from './store/appStore';
 
-// Vault directory relative to home
-const VAULT_ROOT_RELATIVE = 'Dropbox/Vault';
+// Vault opened when no vault has been used before
+const DEFAULT_VAULT = '~/Dropbox/Vault';
 
 const APP_START = performance.now();
 console.log('[App] Module loaded at:', APP_START.toFixed(0), 'ms');
@@ -18,21 +18,124 @@ function App() {
 
   let saveTimeout: number | undefined;
 
+  // Write the editor content, refusing to clobber changes made on disk since
+  // the note was loaded. Pass force to overwrite them anyway.
+  const saveNote = async (file: FileEntry, content: string, force = false): Promise<void> => {
+    const vault = state.vault.root;
+    if (!vault) return;
+
+    try {
+      const version = await writeNote(vault, file.relativePath, content, force ? null : state.editor.version);
+      if (state.vault.currentFile?.path !== file.path) return;
+      setState('editor', 'version', version);
+      setState('editor', 'baseContent', content);
+      setState('editor', 'conflict', null);
+      setState('editor', 'merge', null);
+      if (state.editor.content === content) setState('editor', 'isDirty', false);
+    } catch (err) {
+      if (isWriteConflict(err)) {
+        // Fold the changes on disk in; only ask the user if they overlap
+        const theirs = err.theirs;
+        const merged = theirs
+          ? await mergeNote(vault, file.relativePath, state.editor.baseContent, content).catch(() => null)
+          : null;
+        if (theirs && merged?.status === 'clean' && state.editor.content === content) {
+          console.log('[App] Merged changes made on disk into', file.relativePath);
+          setState('editor', 'version', merged.theirs);
+          setState('editor', 'baseContent', theirs.content);
+          setState('editor', 'content', merged.content);
+          setState('editor', 'key', k => k + 1);
+          await saveNote(file, merged.content);
+          return;
+        }
+        setState('editor', 'conflict', err);
+        setState('editor', 'merge', merged);
+        return;
+      }
+      throw errorMessage(err);
+    }
+  };
+
   // Save current file immediately (used before switching)
   const saveCurrentFile = async (): Promise<void> => {
     const file = state.vault.currentFile;
-    if (!file || !state.editor.isDirty) return;
+    if (!file || !state.editor.isDirty || state.editor.conflict) return;
 
     if (saveTimeout) clearTimeout(saveTimeout);
 
     try {
-      await writeTextFile(file.path, state.editor.content);
-      setState('editor', 'isDirty', false);
+      await saveNote(file, state.editor.content);
     } catch (err) {
       console.error('Failed to save file:', err);
     }
   };
 
+  // Conflict resolution: overwrite the disk with the editor content
+  const keepMine = async () => {
+    const file = state.vault.currentFile;
+    if (!file) return;
+    try {
+      await saveNote(file, state.editor.content, true);
+    } catch (err) {
+      setState('ui', 'error', `Save failed: ${err}`);
+    }
+  };
+
+  // Conflict resolution: discard the editor content for what is on disk
+  const loadTheirs = () => {
+    const theirs = state.editor.conflict?.theirs;
+    if (!theirs) return;
+    setState('editor', 'content', theirs.content);
+    setState('editor', 'version', { mtime: theirs.mtime, hash: theirs.hash });
+    setState('editor', 'baseContent', theirs.content);
+    setState('editor', 'conflict', null);
+    setState('editor', 'merge', null);
+    setState('editor', 'isDirty', false);
+    setState('editor', 'key', k => k + 1);
+  };
+
+  // Conflict resolution: save both versions with conflict markers to fix up by hand
+  const keepBoth = async () => {
+    const file = state.vault.currentFile;
+    const merged = state.editor.merge;
+    const theirs = state.editor.conflict?.theirs;
+    if (!file || !merged || !theirs) return;
+    setState('editor', 'content', merged.content);
+    setState('editor', 'version', merged.theirs);
+    setState('editor', 'baseContent', theirs.content);
+    setState('editor', 'key', k => k + 1);
+    try {
+      await saveNote(file, merged.content);
+    } catch (err) {
+      setState('ui', 'error', `Save failed: ${err}`);
+    }
+  };
+
+  // Settle the open conflicted copy and switch to its original
+  const settleConflictCopy = async (resolution: ConflictResolution) => {
+    const copy = state.vault.currentFile;
+    const vault = state.vault.root;
+    if (!copy?.conflict || !vault) return;
+
+    // "Chapter 2" next to "Chapter" may be an ordinary note; ask before merging it away
+    const confirmed = copy.conflict.service === 'icloud'
+      && await confirm(`Treat ${copy.relativePath} as an iCloud conflicted copy of ${copy.conflict.original.relativePath}? It will be deleted.`, { kind: 'warning' });
+    if (copy.conflict.service === 'icloud' && !confirmed) return;
+
+    await saveCurrentFile();
+    try {
+      const { original, clean } = await resolveConflict(vault, copy.relativePath, resolution, confirmed);
+      setState('vault', 'files', files => files.filter(f => f.path !== copy.path));
+      setState('editor', 'isDirty', false);
+      await loadFile(original);
+      if (!clean) {
+        setState('ui', 'error', 'Some changes overlapped; look for conflict markers in the note');
+      }
+    } catch (err) {
+      setState('ui', 'error', `Failed to resolve conflict: ${errorMessage(err)}`);
+    }
+  };
+
   // Load a file into the editor
   const loadFile = async (file: FileEntry): Promise<void> => {
     // Save current file first if dirty
@@ -41,22 +144,23 @@ function App() {
     setState('editor', 'isLoaded', false);
     setState('ui', 'error', null);
 
+    const vault = state.vault.root;
+    if (!vault) return;
+
     try {
-      const fileExists = await exists(file.path);
-      if (fileExists) {
-        const text = await readTextFile(file.path);
-        setState('editor', 'content', text);
-      } else {
-        setState('ui', 'error', `File not found: ${file.relativePath}`);
-        return;
-      }
+      const note = await readNote(vault, file.relativePath);
+      setState('editor', 'content', note.content);
+      setState('editor', 'version', { mtime: note.mtime, hash: note.hash });
+      setState('editor', 'baseContent', note.content);
+      setState('editor', 'conflict', null);
+      setState('editor', 'merge', null);
 
       setState('vault', 'currentFile', file);
       setState('editor', 'isDirty', false);
       setState('editor', 'key', k => k + 1);
       setState('editor', 'isLoaded', true);
     } catch (err) {
-      setState('ui', 'error', `Failed to load file: ${err}`);
+      setState('ui', 'error', `Failed to load file: ${errorMessage(err)}`);
       console.error('Failed to load file:', err);
     }
   };
@@ -79,6 +183,13 @@ function App() {
     loadFile(file);
   };
 
+  // Recently opened notes, most recent first, for the finder's ranking
+  const recentPaths = () => {
+    const current = state.vault.currentFile;
+    const visited = state.history.entries.slice(0, state.history.index + 1).reverse();
+    return (current ? [current, ...visited] : visited).map(file => file.relativePath);
+  };
+
   // Navigation: Go Back
   const canGoBack = () => state.history.index >= 0;
   const goBack = () => {
@@ -121,13 +232,65 @@ function App() {
     console.log('[App] Starting vault scan at:', (performance.now() - APP_START).toFixed(0), 'ms');
     setState('vault', 'isScanning', true);
     try {
-      const vaultFiles = await scanVault(VAULT_ROOT_RELATIVE);
+      const { root: vault } = await openVault((await getLastVault()) ?? DEFAULT_VAULT);
+      setState('vault', 'root', vault);
+
+      // The scan answers from the index; changes found afterwards arrive here
+      const unlisten = await onIndexDelta((delta) => {
+        if (delta.vault !== state.vault.root) return;
+        setState('vault', 'files', files => applyIndexDelta(files, delta));
+      });
+      onCleanup(unlisten);
+
+      // Keep the file list and the open note in sync with edits made outside Slate
+      const unlistenChanges = await onVaultChange({
+        created: (v, file) => {
+          if (v !== state.vault.root) return;
+          setState('vault', 'files', files => applyIndexDelta(files, { vault: v, added: [file], modified: [], removed: [] }));
+        },
+        modified: async (v, file) => {
+          const current = state.vault.currentFile;
+          if (v !== state.vault.root || current?.path !== file.path || state.editor.isDirty) return;
+          // Our own saves come back as modifications; only reload real changes
+          const { content: text, mtime, hash } = await readNote(v, file.relativePath);
+          setState('editor', 'version', { mtime, hash });
+          setState('editor', 'baseContent', text);
+          if (text !== state.editor.content) {
+            setState('editor', 'content', text);
+            setState('editor', 'key', k => k + 1);
+          }
+        },
+        deleted: (v, file) => {
+          if (v !== state.vault.root) return;
+          setState('vault', 'files', files => files.filter(f => f.path !== file.path));
+          if (state.vault.currentFile?.path === file.path) {
+            setState('ui', 'error', `File was deleted: ${file.relativePath}`);
+          }
+        },
+        renamed: (v, from, to) => {
+          if (v !== state.vault.root) return;
+          setState('vault', 'files', files =>
+            applyIndexDelta(files, { vault: v, added: [to], modified: [], removed: [from.relativePath] }));
+          if (state.vault.currentFile?.path === from.path) {
+            setState('vault', 'currentFile', to);
+          }
+        },
+      });
+      onCleanup(unlistenChanges);
+
+      // Fill the file list as batches arrive so the finder is usable early
+      setState('vault', 'files', []);
+      const vaultFiles = await scanVaultStreaming(vault, (entries) => {
+        setState('vault', 'files', files => [...files, ...entries]);
+      });
       console.log('[App] Vault scan complete at:', (performance.now() - APP_START).toFixed(0), 'ms');
-      setState('vault', 'files', vaultFiles);
       setState('vault', 'isScanning', false);
 
       // Load INBOX.md by default, or first file if not found
-      if (vaultFiles.length > 0) {
+      if (!vaultFiles) {
+        // Cancelled by a vault switch; the new vault's scan takes over
+      } else if (vaultFiles.length > 0) {
+        setState('vault', 'files', vaultFiles);
         const inbox = vaultFiles.find(f => f.name.toLowerCase() === 'inbox.md');
         console.log('[App] Starting loadFile at:', (performance.now() - APP_START).toFixed(0), 'ms');
         await loadFile(inbox || vaultFiles[0]);
@@ -138,7 +301,9 @@ function App() {
       }
     } catch (err) {
       setState('vault', 'isScanning', false);
-      setState('ui', 'error', `Failed to scan vault: ${err}`);
+      setState('ui', 'error', isSlateError(err, 'vaultNotFound')
+        ? `Vault not found: ${err.path}`
+        : `Failed to scan vault: ${errorMessage(err)}`);
       console.error('Failed to scan vault:', err);
       setState('editor', 'isLoaded', true);
     }
@@ -180,18 +345,18 @@ function App() {
     setState('ui', 'error', null);
 
     const file = state.vault.currentFile;
-    if (!file) return;
+    if (!file || state.editor.conflict) return;
 
     // Debounced auto-save
     if (saveTimeout) clearTimeout(saveTimeout);
     saveTimeout = window.setTimeout(async () => {
       try {
         setState('editor', 'saveStatus', 'saving');
-        await writeTextFile(file.path, newContent);
-        setState('editor', 'isDirty', false);
-        setState('editor', 'saveStatus', 'saved');
+        await saveNote(file, newContent);
+        setState('editor', 'saveStatus', state.editor.conflict ? 'idle' : 'saved');
         setTimeout(() => setState('editor', 'saveStatus', 'idle'), 1500);
       } catch (err) {
+        setState('editor', 'saveStatus', 'idle');
         setState('ui', 'error', `Auto-save failed: ${err}`);
         console.error('Auto-save failed:', err);
       }
@@ -208,7 +373,8 @@ function App() {
       <div class="flex flex-col h-screen bg-[var(--color-bg-primary)]">
         {/* File Finder Modal */}
         <FileFinder
-          files={state.vault.files}
+          vault={state.vault.root}
+          recent={recentPaths()}
           isOpen={state.ui.finderOpen}
           onClose={() => setState('ui', 'finderOpen', false)}
           onSelect={handleFileSelect}
@@ -221,6 +387,54 @@ function App() {
           </div>
         </Show>
 
+        {/* Conflicted Copy Banner */}
+        <Show when={state.vault.currentFile?.conflict}>
+          {(conflict) => (
+            <div class="flex items-center gap-3 px-4 py-2 bg-amber-500/20 border-b border-amber-500/50 text-amber-500 text-sm">
+              <span class="flex-1">
+                {conflict().service === 'icloud'
+                  ? `This may be an iCloud conflicted copy of ${conflict().original.relativePath}.`
+                  : `This is a conflicted copy of ${conflict().original.relativePath}.`}
+              </span>
+              <button class="toolbar-button" onClick={() => settleConflictCopy('merge')} title="Merge this copy into the original">
+                Merge
+              </button>
+              <button class="toolbar-button" onClick={() => settleConflictCopy('keepOriginal')} title="Delete this copy">
+                Keep original
+              </button>
+              <button class="toolbar-button" onClick={() => settleConflictCopy('keepCopy')} title="Replace the original with this copy">
+                Keep copy
+              </button>
+            </div>
+          )}
+        </Show>
+
+        {/* Conflict Banner */}
+        <Show when={state.editor.conflict}>
+          {(conflict) => (
+            <div class="flex items-center gap-3 px-4 py-2 bg-amber-500/20 border-b border-amber-500/50 text-amber-500 text-sm">
+              <span class="flex-1">
+                {conflict().theirs
+                  ? `${conflict().path} changed on disk since you opened it. Your edits have not been saved.`
+                  : `${conflict().path} was deleted on disk. Your edits have not been saved.`}
+              </span>
+              <button class="toolbar-button" onClick={keepMine} title="Overwrite the file on disk with your version">
+                Keep mine
+              </button>
+              <Show when={state.editor.merge}>
+                <button class="toolbar-button" onClick={keepBoth} title="Save both versions with conflict markers">
+                  Keep both
+                </button>
+              </Show>
+              <Show when={conflict().theirs}>
+                <button class="toolbar-button" onClick={loadTheirs} title="Discard your edits and load the version on disk">
+                  Load theirs
+                </button>
+              </Show>
+            </div>
+          )}
+        </Show>
+
         {/* Toolbar */}
         <header class="flex items-center justify-between px-4 py-2 bg-[var(--color-bg-secondary)] border-b border-[var(--color-border)]">
           <div class="flex items-center gap-3">
@@ -269,6 +483,8 @@ function App() {
               {(_key) => <WysiwygEditor
                 content={state.editor.content}
                 onContentChange={handleContentChange}
+                vault={state.vault.root!}
+                path={state.vault.currentFile?.relativePath}
                 files={state.vault.files}
                 onNavigate={handleFileSelect}
               />}
@@ -1,60 +1,54 @@
-import { createSignal, createMemo, createEffect, For, onMount, onCleanup, Show } from 'solid-js';
-import type { FileEntry } from '../services/fileService';
+import { createSignal, createEffect, For, onMount, onCleanup, Show } from 'solid-js';
+import { fuzzyFind, type FileEntry, type FuzzyMatch } from '../services/fileService';
 
 interface FileFinderProps {
-    files: FileEntry[];
+    vault: string | null;
+    recent: string[]; // Recently opened notes' relative paths, most recent first
     isOpen: boolean;
     onClose: () => void;
     onSelect: (file: FileEntry) => void;
 }
 
+const MAX_RESULTS = 50;
+
 /**
- * Simple fuzzy match scoring - returns -1 for no match, higher is better.
+ * Splits `text` into runs, marking the ones at the matched UTF-16 offsets.
  */
-function fuzzyMatch(query: string, target: string): number {
-    const lowerQuery = query.toLowerCase();
-    const lowerTarget = target.toLowerCase();
-
-    if (!query) return 1; // Empty query matches everything
-
-    // Exact substring match gets high score
-    if (lowerTarget.includes(lowerQuery)) {
-        return 100 + (query.length / target.length) * 50;
-    }
-
-    // Fuzzy character matching
-    let queryIdx = 0;
-    let score = 0;
-    let consecutive = 0;
-
-    for (let i = 0; i < target.length && queryIdx < query.length; i++) {
-        if (lowerTarget[i] === lowerQuery[queryIdx]) {
-            queryIdx++;
-            consecutive++;
-            score += consecutive * 2; // Bonus for consecutive matches
+function highlightRuns(text: string, indices: number[]): { text: string; matched: boolean }[] {
+    const matched = new Set(indices);
+    const runs: { text: string; matched: boolean }[] = [];
+    for (let i = 0; i < text.length; i++) {
+        const isMatch = matched.has(i);
+        const last = runs[runs.length - 1];
+        if (last && last.matched === isMatch) {
+            last.text += text[i];
         } else {
-            consecutive = 0;
+            runs.push({ text: text[i], matched: isMatch });
         }
     }
-
-    // All query characters must be found
-    return queryIdx === query.length ? score : -1;
+    return runs;
 }
 
 function FileFinder(props: FileFinderProps) {
     const [query, setQuery] = createSignal('');
     const [selectedIndex, setSelectedIndex] = createSignal(0);
+    const [matches, setMatches] = createSignal<FuzzyMatch[]>([]);
     let inputRef!: HTMLInputElement;
 
-    // Filter and sort files by match score
-    const filteredFiles = createMemo(() => {
+    // Match in the backend; a slower, older request never overwrites a newer one
+    let latest = 0;
+    createEffect(() => {
+        const vault = props.vault;
         const q = query();
-        const scored = props.files
-            .map(file => ({ file, score: fuzzyMatch(q, file.relativePath) }))
-            .filter(item => item.score >= 0)
-            .sort((a, b) => b.score - a.score);
-        return scored.map(item => item.file);
+        if (!props.isOpen || !vault) return;
+        const request = ++latest;
+        fuzzyFind(vault, q, MAX_RESULTS, props.recent)
+            .then(found => {
+                if (request === latest) setMatches(found);
+            })
+            .catch(err => console.error('Fuzzy find failed:', err));
     });
+    const filteredFiles = () => matches().map(match => match.file);
 
     // Reset selection when query or files change
     const resetSelection = () => setSelectedIndex(0);
@@ -70,6 +64,7 @@ function FileFinder(props: FileFinderProps) {
             // Reset query when closed
             setQuery('');
             setSelectedIndex(0);
+            setMatches([]);
         }
     });
 
@@ -137,18 +132,29 @@ function FileFinder(props: FileFinderProps) {
 
                     {/* File list */}
                     <div class="max-h-80 overflow-y-auto">
-                        <For each={filteredFiles()}>
-                            {(file, index) => (
+                        <For each={matches()}>
+                            {(match, index) => (
                                 <div
                                     class={`px-4 py-2 cursor-pointer flex items-center gap-2 ${index() === selectedIndex()
                                         ? 'bg-[var(--color-accent)]/20 text-[var(--color-text-primary)]'
                                         : 'text-[var(--color-text-secondary)] hover:bg-[var(--color-bg-primary)]'
                                         }`}
-                                    onClick={() => props.onSelect(file)}
+                                    onClick={() => props.onSelect(match.file)}
                                     onMouseEnter={() => setSelectedIndex(index())}
                                 >
-                                    <span class="text-[var(--color-text-muted)]">📄</span>
-                                    <span class="truncate">{file.relativePath}</span>
+                                    <span class="text-[var(--color-text-muted)]" title={match.file.conflict ? `Conflicted copy of ${match.file.conflict.original.relativePath}` : undefined}>
+                                        {match.file.conflict ? '⚠️' : '📄'}
+                                    </span>
+                                    <span class="truncate">
+                                        <For each={highlightRuns(match.alias ?? match.file.relativePath, match.indices)}>
+                                            {(run) => run.matched
+                                                ? <span class="text-[var(--color-accent)] font-semibold">{run.text}</span>
+                                                : run.text}
+                                        </For>
+                                    </span>
+                                    <Show when={match.alias}>
+                                        <span class="truncate text-xs text-[var(--color-text-muted)]">{match.file.relativePath}</span>
+                                    </Show>
                                 </div>
                             )}
                         </For>
@@ -56,6 +56,8 @@ refractor.register(toml);
 interface WysiwygEditorProps {
   content: string;
   onContentChange: (markdown: string) => void;
+  vault: string;
+  path: string | undefined; // Relative path of the note being edited
   files: FileEntry[];
   onNavigate: (file: FileEntry) => void;
 }
@@ -156,7 +158,7 @@ function WysiwygEditor(props: WysiwygEditorProps) {
         .create();
 
       // Set up wikilink navigation and autocomplete context
-      setWikilinkNavigationContext(props.files, props.onNavigate);
+      setWikilinkNavigationContext(props.vault, props.path, props.onNavigate);
       setAutocompleteContext(props.files);
 
       // Set up listener after editor is created
@@ -1,11 +1,12 @@
 import { $view } from '@milkdown/kit/utils';
 import { wikilinkNode } from './wikilinkPlugin';
-import type { FileEntry } from '../services/fileService';
+import { errorMessage, type FileEntry, type VaultHandle } from '../services/fileService';
 import { resolveWikilink, getWikilinkDisplayText } from '../services/wikilinkService';
 
 // Navigation context - will be set externally
 let navigationContext: {
-    files: FileEntry[];
+    vault: VaultHandle;
+    from: string | undefined; // Relative path of the note being edited
     onNavigate: (file: FileEntry) => void;
 } | null = null;
 
@@ -18,31 +19,23 @@ const activeWikilinks = new Set<{
 
 /**
  * Set the navigation context for wikilinks.
- * Must be called after editor is created with current files list.
+ * Must be called after editor is created with the open vault and note.
  */
 export function setWikilinkNavigationContext(
-    files: FileEntry[],
+    vault: VaultHandle,
+    from: string | undefined,
     onNavigate: (file: FileEntry) => void
 ) {
-    navigationContext = { files, onNavigate };
+    navigationContext = { vault, from, onNavigate };
     // Update all existing wikilinks with new context
     refreshAllWikilinks();
 }
 
 /**
- * Update just the files list (without changing the navigation callback).
+ * Re-resolve all active wikilink elements, e.g. after notes were added or
+ * removed.
  */
-export function updateWikilinkFiles(files: FileEntry[]) {
-    if (navigationContext) {
-        navigationContext.files = files;
-        refreshAllWikilinks();
-    }
-}
-
-/**
- * Refresh all active wikilink elements.
- */
-function refreshAllWikilinks() {
+export function refreshAllWikilinks() {
     activeWikilinks.forEach(({ updateState }) => {
         updateState();
     });
@@ -69,14 +62,23 @@ export const wikilinkView = $view(wikilinkNode, () => {
         span.textContent = getDisplayText();
         span.style.cursor = 'pointer';
 
-        // Check if target exists and update styling
+        // Check if target exists and update styling. Resolution is async, so
+        // an answer for a target that has since been edited is dropped
         const updateExistsState = () => {
             if (navigationContext) {
-                const resolved = resolveWikilink(target, navigationContext.files);
-                span.classList.toggle('wikilink--broken', !resolved.exists);
-                span.title = resolved.exists
-                    ? `Click to open: ${resolved.file?.relativePath} (double-click to edit)`
-                    : `Not found: ${target} (double-click to edit)`;
+                const requested = target;
+                resolveWikilink(navigationContext.vault, requested, navigationContext.from)
+                    .then((resolved) => {
+                        if (requested !== target) return;
+                        span.classList.toggle('wikilink--broken', !resolved.exists);
+                        span.title = resolved.exists
+                            ? `Click to open: ${resolved.file?.relativePath} (double-click to edit)`
+                            : `Not found: ${target} (double-click to edit)`;
+                    })
+                    .catch((err) => {
+                        span.classList.add('wikilink--broken');
+                        span.title = `${target} (${errorMessage(err)})`;
+                    });
             } else {
                 // No context yet - show as potentially broken
                 span.classList.add('wikilink--broken');
@@ -93,11 +95,12 @@ export const wikilinkView = $view(wikilinkNode, () => {
         // Click handling: delay single-click to detect double-click
         let clickTimeout: number | null = null;
 
-        const doNavigate = () => {
+        const doNavigate = async () => {
             if (navigationContext) {
-                const resolved = resolveWikilink(target, navigationContext.files);
+                const { onNavigate } = navigationContext;
+                const resolved = await resolveWikilink(navigationContext.vault, target, navigationContext.from);
                 if (resolved.exists && resolved.file) {
-                    navigationContext.onNavigate(resolved.file);
+                    onNavigate(resolved.file);
                 } else {
                     console.log(`[wikilink] Target not found: ${target}`);
                 }
@@ -1,24 +1,125 @@
-import { invoke } from '@tauri-apps/api/core';
-import { homeDir, join } from '@tauri-apps/api/path';
+import { invoke, Channel } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 export interface FileEntry {
     name: string;
     path: string;         // Full absolute path
     relativePath: string; // Path relative to vault root (for display)
+    conflict?: ConflictCopy; // Set if this is a sync client's conflicted copy of another note
+}
+
+export interface ConflictCopy {
+    service: 'dropbox' | 'syncthing' | 'icloud';
+    original: FileEntry; // May no longer exist
+}
+
+/**
+ * Why a backend command failed. `code` is stable, so the UI can react to a
+ * kind of failure (e.g. offer to create a missing vault) without parsing
+ * `message`, which is meant for people.
+ */
+export type SlateError =
+    | { code: 'vaultNotFound'; path: string; message: string }
+    | { code: 'notADirectory'; path: string; message: string }
+    | { code: 'vaultNotOpen'; path: string; message: string }
+    | { code: 'indexNotReady'; message: string } // Retry once the vault's indexes are built
+    | { code: 'notFound'; path: string; message: string }
+    | { code: 'alreadyExists'; path: string; message: string }
+    | { code: 'permissionDenied'; path: string; message: string }
+    | { code: 'invalidPath'; path: string; message: string }
+    | { code: 'invalidEncoding'; path: string; message: string }
+    | { code: 'invalidInput'; message: string }
+    | { code: 'query'; message: string; start: number; end: number }
+    | { code: 'conflict'; path: string; ours: string; theirs: NoteContent | null; message: string }
+    | { code: 'io'; path?: string; ioKind: string; message: string } // ioKind is Rust's io::ErrorKind, e.g. 'StorageFull'
+    | { code: 'internal'; message: string };
+
+export type SlateErrorCode = SlateError['code'];
+
+export function isSlateError<C extends SlateErrorCode>(
+    err: unknown,
+    code?: C,
+): err is Extract<SlateError, { code: C }> {
+    if (typeof err !== 'object' || err === null || typeof (err as SlateError).code !== 'string') {
+        return false;
+    }
+    return code === undefined || (err as SlateError).code === code;
+}
+
+/**
+ * Human readable text of a rejected command (or any other thrown value).
+ */
+export function errorMessage(err: unknown): string {
+    return isSlateError(err) ? err.message : String(err);
+}
+
+/**
+ * Canonical vault root as returned by the backend. Pass it back to any
+ * vault command; the backend re-validates it on every call.
+ */
+export type VaultHandle = string;
+
+/**
+ * Resolves a vault location into a canonical handle.
+ * @param path Absolute path, `~/...`, `$VAR/...`, or a path relative to home (e.g., 'Dropbox/Vault')
+ */
+export async function resolveVault(path: string): Promise<VaultHandle> {
+    return await invoke<VaultHandle>('resolve_vault', { path });
+}
+
+export interface VaultInfo {
+    root: VaultHandle;
+    name: string;
+    available: boolean; // False if the directory is gone (e.g. unplugged drive)
+    isOpen: boolean;
+}
+
+/**
+ * Lists every vault the user has opened and not forgotten.
+ */
+export async function listVaults(): Promise<VaultInfo[]> {
+    return await invoke<VaultInfo[]>('list_vaults');
+}
+
+/**
+ * Opens a vault for this window and remembers it for next launch.
+ * @param path Any path accepted by resolveVault
+ */
+export async function openVault(path: string): Promise<VaultInfo> {
+    return await invoke<VaultInfo>('open_vault', { path });
+}
+
+/**
+ * Closes a vault (works even if its directory is gone).
+ */
+export async function closeVault(root: string): Promise<void> {
+    await invoke('close_vault', { root });
+}
+
+/**
+ * Removes a vault from the known list (works even if its directory is gone).
+ */
+export async function forgetVault(root: string): Promise<void> {
+    await invoke('forget_vault', { root });
+}
+
+/**
+ * Returns the vault this window used last, or null if none or it is gone.
+ */
+export async function getLastVault(): Promise<VaultHandle | null> {
+    return await invoke<VaultHandle | null>('get_last_vault');
 }
 
 /**
  * Scans the vault directory for all markdown files using Rust backend.
- * @param vaultRelativePath Path to vault relative to home directory (e.g., 'Dropbox/Vault')
+ * @param vault Vault handle from resolveVault/getLastVault
  * @returns Array of FileEntry objects sorted by relative path
  */
-export async function scanVault(vaultRelativePath: string): Promise<FileEntry[]> {
-    console.log(`[scan] Starting vault scan: ${vaultRelativePath}`);
+export async function scanVault(vault: VaultHandle): Promise<FileEntry[]> {
+    console.log(`[scan] Starting vault scan: ${vault}`);
     const startTime = performance.now();
 
-    const results = await invoke<FileEntry[]>('scan_vault', {
-        vaultRelativePath,
-    });
+    const results = await invoke<FileEntry[]>('scan_vault', { vault });
 
     const elapsed = (performance.now() - startTime).toFixed(0);
     console.log(`[scan] Done: ${results.length} files in ${elapsed}ms`);
@@ -26,10 +127,558 @@ export async function scanVault(vaultRelativePath: string): Promise<FileEntry[]>
     return results;
 }
 
+export type ScanEvent =
+    | { event: 'batch'; data: { entries: FileEntry[] } }
+    | { event: 'progress'; data: { scanned: number } }
+    | { event: 'finished'; data: { total: number; elapsedMs: number; fromIndex: boolean } }
+    | { event: 'cancelled'; data: { scanned: number } };
+
+/**
+ * Scans the vault, reporting files in batches as the backend finds them.
+ * @param onBatch Called with each batch of newly found files (unsorted)
+ * @returns All files sorted by relative path, or null if the scan was cancelled
+ */
+export function scanVaultStreaming(
+    vault: VaultHandle,
+    onBatch: (entries: FileEntry[]) => void,
+): Promise<FileEntry[] | null> {
+    console.log(`[scan] Starting streaming vault scan: ${vault}`);
+
+    return new Promise((resolve, reject) => {
+        const files: FileEntry[] = [];
+        const onEvent = new Channel<ScanEvent>();
+        onEvent.onmessage = (message) => {
+            switch (message.event) {
+                case 'batch':
+                    files.push(...message.data.entries);
+                    onBatch(message.data.entries);
+                    break;
+                case 'progress':
+                    break;
+                case 'finished':
+                    console.log(`[scan] Done: ${message.data.total} files in ${message.data.elapsedMs}ms` +
+                        (message.data.fromIndex ? ' (from index)' : ''));
+                    resolve(files.sort((a, b) => a.relativePath.localeCompare(b.relativePath)));
+                    break;
+                case 'cancelled':
+                    console.log(`[scan] Cancelled after ${message.data.scanned} files`);
+                    resolve(null);
+                    break;
+            }
+        };
+        invoke('scan_vault_streaming', { vault, onEvent }).catch(reject);
+    });
+}
+
+/**
+ * Cancels an in-flight streaming scan. Returns false if none was running.
+ */
+export async function cancelScan(vault: VaultHandle): Promise<boolean> {
+    return await invoke<boolean>('cancel_scan', { vault });
+}
+
 /**
- * Gets the full path for a vault-relative path.
+ * On-disk version of a note, as returned after a write.
  */
-export async function getVaultPath(vaultRelativePath: string): Promise<string> {
-    const home = await homeDir();
-    return await join(home, vaultRelativePath);
+export interface NoteVersion {
+    mtime: number; // Milliseconds since the Unix epoch
+    hash: string;  // BLAKE3 hash of the bytes on disk
+}
+
+export interface NoteContent extends NoteVersion {
+    content: string; // Line endings normalized to \n
+}
+
+/**
+ * Reads a note through the backend.
+ * @param path Path relative to the vault root
+ */
+export async function readNote(vault: VaultHandle, path: string): Promise<NoteContent> {
+    return await invoke<NoteContent>('read_note', { vault, path });
+}
+
+/**
+ * Why writeNote refused to save: the note changed on disk since `base` was
+ * loaded. Nothing was written.
+ */
+export type WriteConflict = Extract<SlateError, { code: 'conflict' }>;
+
+export function isWriteConflict(err: unknown): err is WriteConflict {
+    return isSlateError(err, 'conflict');
+}
+
+/**
+ * Writes a note atomically through the backend. The file's line endings and
+ * permissions are preserved.
+ * @param path Path relative to the vault root
+ * @param base Version the editor loaded; the save is rejected with a
+ *             WriteConflict if the file no longer matches it. Omit to
+ *             overwrite unconditionally.
+ */
+export async function writeNote(
+    vault: VaultHandle,
+    path: string,
+    content: string,
+    base?: NoteVersion | null,
+): Promise<NoteVersion> {
+    return await invoke<NoteVersion>('write_note', { vault, path, content, base: base ?? null });
+}
+
+/**
+ * Result of a three-way merge. Conflicted merges carry the text with
+ * git-style conflict markers, and the regions that make it up.
+ */
+export type MergeRegion =
+    | { kind: 'resolved'; text: string }
+    | { kind: 'conflict'; base: string; ours: string; theirs: string };
+
+export type NoteMerge = (
+    | { status: 'clean'; content: string }
+    | { status: 'conflicted'; content: string; regions: MergeRegion[] }
+) & {
+    theirs: NoteVersion; // Disk version merged against; save the result with it as base
+};
+
+/**
+ * Three-way merges the editor's text into the note as it is now on disk.
+ * @param base Text the editor loaded
+ * @param ours Text in the editor now
+ */
+export async function mergeNote(vault: VaultHandle, path: string, base: string, ours: string): Promise<NoteMerge> {
+    return await invoke<NoteMerge>('merge_note', { vault, path, base, ours });
+}
+
+/**
+ * How to settle a conflicted copy: merge it into the original (overlapping
+ * changes are kept with conflict markers), discard it, or replace the
+ * original with it.
+ */
+export type ConflictResolution = 'merge' | 'keepOriginal' | 'keepCopy';
+
+export interface ConflictResolved {
+    original: FileEntry;
+    version: NoteVersion;
+    clean: boolean; // False if the merge left conflict markers in the original
+}
+
+/**
+ * Settles a conflicted copy and deletes it.
+ * @param path Path of the copy, relative to the vault root
+ */
+export async function resolveConflict(
+    vault: VaultHandle,
+    path: string,
+    resolution: ConflictResolution,
+    confirmed = false, // Required for iCloud copies, which may be ordinary notes
+): Promise<ConflictResolved> {
+    return await invoke<ConflictResolved>('resolve_conflict', { vault, path, resolution, confirmed });
+}
+
+export interface SearchSnippet {
+    line: number; // 1-based
+    text: string;
+    highlights: [number, number][]; // [start, end) offsets into text
+}
+
+export interface SearchHit {
+    file: FileEntry;
+    score: number;
+    snippets: SearchSnippet[];
+}
+
+/**
+ * A query that did not parse. `start`/`end` locate the offending part of the
+ * query in UTF-16 code units, ready for String.slice.
+ */
+export type QueryError = Extract<SlateError, { code: 'query' }>;
+
+export function isQueryError(err: unknown): err is QueryError {
+    return isSlateError(err, 'query');
+}
+
+/**
+ * Searches note contents, best matches first. Words are stemmed and must
+ * all match; "quoted phrases" match together, `word*` matches by prefix and
+ * /regex/ matches raw text. `OR`, `-negation` and (groups) combine terms,
+ * and path:, file:, tag:, line:(...), section:(...), task:(...),
+ * task-todo:(...) and task-done:(...) narrow where they match. Rejects with
+ * an 'indexNotReady' SlateError while the search index is still being built.
+ */
+export async function searchVault(vault: VaultHandle, query: string, limit?: number): Promise<SearchHit[]> {
+    return await invoke<SearchHit[]>('search_vault', { vault, query, limit: limit ?? null });
+}
+
+export interface FuzzyMatch {
+    file: FileEntry;
+    score: number;
+    alias?: string; // Set if the note matched by alias rather than path
+    indices: number[]; // Matched UTF-16 offsets in file.relativePath, or in alias
+}
+
+/**
+ * Fuzzy-matches note paths and aliases for the quick switcher, best first.
+ * Smart-case: case-insensitive unless the query has an uppercase letter.
+ * @param recent Recently opened notes' relative paths, most recent first;
+ *               they rank higher, as do recently modified notes
+ */
+export async function fuzzyFind(
+    vault: VaultHandle,
+    query: string,
+    limit?: number,
+    recent?: string[],
+): Promise<FuzzyMatch[]> {
+    return await invoke<FuzzyMatch[]>('fuzzy_find', {
+        vault,
+        query,
+        limit: limit ?? null,
+        recent: recent ?? null,
+    });
+}
+
+/** The part of a note a link points into. */
+export type LinkAnchor = { heading: string } | { block: string };
+
+/** Where a wikilink target leads. */
+export interface LinkResolution {
+    target: string;
+    file: FileEntry | null;
+    exists: boolean;
+    anchor: LinkAnchor | null;
+}
+
+/**
+ * Resolves what is inside a wikilink's brackets (`Page`, `folder/Page`,
+ * `Page#Heading`, `Page#^block`) to a note or other file.
+ * @param from Relative path of the note the link is in
+ */
+export async function resolveLink(vault: VaultHandle, target: string, from?: string): Promise<LinkResolution> {
+    return await invoke<LinkResolution>('resolve_link', { vault, target, from: from ?? null });
+}
+
+/** A link in a note and where it leads. */
+export interface OutgoingLink {
+    syntax: 'wikilink' | 'markdown';
+    embed: boolean;
+    target: string; // Empty for links within the same note
+    anchor: LinkAnchor | null;
+    alias: string | null;
+    line: number; // 1-based
+    column: number; // 1-based, in characters
+    start: number; // Byte offsets of the whole link in the note
+    end: number;
+    resolved: string | null; // Relative path it leads to; null if broken
+}
+
+/**
+ * Outgoing links of one note, or of every note if `path` is omitted, keyed
+ * by relative path.
+ */
+export async function getOutgoingLinks(vault: VaultHandle, path?: string): Promise<Record<string, OutgoingLink[]>> {
+    return await invoke<Record<string, OutgoingLink[]>>('get_outgoing_links', { vault, path: path ?? null });
+}
+
+/**
+ * A note referring to another. Each mention is the paragraph around one or
+ * more references, highlighted.
+ */
+export interface NoteMentions {
+    file: FileEntry;
+    mentions: SearchSnippet[];
+}
+
+/** Notes linking to the note at `path`, sorted by path. */
+export async function getBacklinks(vault: VaultHandle, path: string): Promise<NoteMentions[]> {
+    return await invoke<NoteMentions[]>('get_backlinks', { vault, path });
+}
+
+/**
+ * Notes mentioning the note at `path` by name in plain text, outside links
+ * and code, sorted by path.
+ */
+export async function getUnlinkedMentions(vault: VaultHandle, path: string): Promise<NoteMentions[]> {
+    return await invoke<NoteMentions[]>('get_unlinked_mentions', { vault, path });
+}
+
+export interface GraphNode {
+    id: string; // Relative path; for a missing note, the link target as written
+    name: string;
+    folder: string; // Empty at the vault root
+    tags: string[];
+    exists: boolean;
+}
+
+export interface GraphEdge {
+    source: string;
+    target: string;
+    count: number; // Links from source to target
+}
+
+export interface Graph {
+    nodes: GraphNode[];
+    edges: GraphEdge[];
+}
+
+/** Which part of the graph to return; everything when omitted. */
+export interface GraphFilter {
+    folders?: string[]; // Only notes in these folders (and below)
+    excludeFolders?: string[];
+    tags?: string[]; // Only notes with one of these tags, nested tags included
+    hideEmbeds?: boolean;
+    missing?: boolean; // Include link targets that do not exist
+    hideOrphans?: boolean;
+    center?: string; // Local graph around this note...
+    depth?: number; // ...up to this many links away (default 1)
+}
+
+/** The vault's link graph from the link index. */
+export async function getGraph(vault: VaultHandle, filter?: GraphFilter): Promise<Graph> {
+    return await invoke<Graph>('get_graph', { vault, filter: filter ?? null });
+}
+
+/** The link graph as JSON, GraphML or DOT text, for use in other tools. */
+export async function exportGraph(vault: VaultHandle, format: 'json' | 'graphml' | 'dot', filter?: GraphFilter): Promise<string> {
+    return await invoke<string>('export_graph', { vault, format, filter: filter ?? null });
+}
+
+/** A link with something wrong with it. */
+export interface LinkProblem {
+    file: string;
+    line: number; // 1-based
+    column: number; // 1-based, in characters
+    target: string; // As written
+    candidates: string[]; // Ambiguous links: every note it could mean, the one it leads to first
+}
+
+export interface HealthReport {
+    orphans: string[]; // Notes nothing links to
+    deadEnds: string[]; // Notes linking to no other note
+    brokenLinks: LinkProblem[];
+    ambiguousLinks: LinkProblem[]; // Wikilinks by a name several notes have
+    missingEmbeds: LinkProblem[];
+}
+
+/** Orphans, dead ends, broken and ambiguous links and missing embeds. */
+export async function vaultHealth(vault: VaultHandle): Promise<HealthReport> {
+    return await invoke<HealthReport>('vault_health', { vault });
+}
+
+/** A link rewritten by a rename. */
+export interface LinkEdit {
+    line: number; // 1-based
+    before: string;
+    after: string;
+}
+
+export interface RenameReport {
+    from: string;
+    to: string;
+    applied: boolean; // False for a dry run
+    notes: { path: string; edits: LinkEdit[] }[]; // Paths after the rename
+    failed: string[]; // Notes whose links could not be rewritten
+}
+
+/**
+ * Moves a note and rewrites the links to it across the vault, keeping
+ * aliases, anchors and relative paths. With `dryRun`, only reports the
+ * edits it would make. The move and the rewritten notes also arrive as
+ * vault change events.
+ */
+export async function renameNote(vault: VaultHandle, from: string, to: string, dryRun = false): Promise<RenameReport> {
+    return await invoke<RenameReport>('rename_note', { vault, from, to, dryRun });
+}
+
+/** A frontmatter value. Dates are `YYYY-MM-DD`, optionally with a time. */
+export type PropertyValue =
+    | { text: string }
+    | { number: number }
+    | { boolean: boolean }
+    | { date: string }
+    | { list: PropertyValue[] }
+    | 'null';
+
+/** A frontmatter key and its value. */
+export interface Property {
+    key: string;
+    value: PropertyValue;
+}
+
+/** Frontmatter properties (YAML or TOML) of a note, in written order. */
+export async function getProperties(vault: VaultHandle, path: string): Promise<Property[]> {
+    return await invoke<Property[]>('get_properties', { vault, path });
+}
+
+/**
+ * Sets one frontmatter property, or removes it when `value` is null. The
+ * rest of the frontmatter is left as written. The change also arrives as a
+ * vault change event.
+ */
+export async function setProperty(
+    vault: VaultHandle,
+    path: string,
+    key: string,
+    value: PropertyValue | null,
+): Promise<NoteVersion> {
+    return await invoke<NoteVersion>('set_property', { vault, path, key, value });
+}
+
+/** A tag and the tags nested under it. */
+export interface TagNode {
+    name: string; // Last part: `alpha` for `project/alpha`
+    tag: string; // Whole tag, without `#`
+    count: number; // Notes with exactly this tag
+    total: number; // Notes with this tag or one nested under it
+    children: TagNode[];
+}
+
+/** Every tag in the vault, from note text and frontmatter, as a tree. */
+export async function listTags(vault: VaultHandle): Promise<TagNode[]> {
+    return await invoke<TagNode[]>('list_tags', { vault });
+}
+
+/** Notes with a tag, or (unless `nested` is false) a tag nested under it. */
+export async function notesWithTag(vault: VaultHandle, tag: string, nested = true): Promise<FileEntry[]> {
+    return await invoke<FileEntry[]>('notes_with_tag', { vault, tag, nested });
+}
+
+/** What a tag rename did, or would do. */
+export interface TagRenameReport {
+    from: string;
+    to: string;
+    applied: boolean; // False for a dry run
+    merged: boolean; // The new tag was already in use
+    notes: string[];
+    failed: string[];
+}
+
+/**
+ * Renames a tag, and the tags nested under it, in every note. With
+ * `dryRun`, only reports the notes it would change. Rewritten notes also
+ * arrive as vault change events.
+ */
+export async function renameTag(vault: VaultHandle, from: string, to: string, dryRun = false): Promise<TagRenameReport> {
+    return await invoke<TagRenameReport>('rename_tag', { vault, from, to, dryRun });
+}
+
+export type TaskPriority = 'highest' | 'high' | 'medium' | 'low' | 'lowest';
+
+/** A `- [ ]` task list item somewhere in the vault. */
+export interface Task {
+    id: string; // Stable across edits elsewhere in the note and toggling
+    path: string;
+    line: number; // 1-based
+    status: string; // Character inside the brackets
+    completed: boolean;
+    text: string; // After the checkbox, as written
+    headings: string[]; // Outermost first
+    due: string | null; // YYYY-MM-DD
+    scheduled: string | null;
+    start: string | null;
+    done: string | null;
+    priority: TaskPriority | null;
+    tags: string[];
+    parent: string | null; // ID of the task this is nested under
+    subtasks: string[]; // IDs
+}
+
+/** Which tasks queryTasks returns. Every field is optional. */
+export interface TaskQuery {
+    completed?: boolean;
+    folder?: string;
+    tags?: string[]; // Nested tags match too
+    text?: string;
+    dueAfter?: string; // Inclusive, YYYY-MM-DD
+    dueBefore?: string;
+    hasDue?: boolean;
+    minPriority?: TaskPriority;
+    sort?: 'due' | 'priority' | 'path';
+    limit?: number;
+}
+
+/** Tasks across the vault matching a query. */
+export async function queryTasks(vault: VaultHandle, query: TaskQuery = {}): Promise<Task[]> {
+    return await invoke<Task[]>('query_tasks', { vault, query });
+}
+
+/**
+ * Checks or unchecks a task in its note on disk, by ID. The edit also
+ * arrives as a vault change event.
+ */
+export async function toggleTask(vault: VaultHandle, id: string): Promise<Task> {
+    return await invoke<Task>('toggle_task', { vault, id });
+}
+
+/** One note's row of a query result. */
+export interface QueryRow {
+    file: FileEntry;
+    values: PropertyValue[]; // One per column
+}
+
+/** The result of a TABLE or LIST query. */
+export interface QueryResult {
+    kind: 'table' | 'list';
+    columns: string[]; // Not counting the note column
+    showFile: boolean; // False for TABLE WITHOUT ID
+    rows: QueryRow[];
+}
+
+/**
+ * Runs a Dataview-style query, such as
+ * `TABLE due, status FROM #project WHERE status != "done" SORT due`, over
+ * frontmatter properties and inline `key:: value` fields. `query` may be a
+ * whole fenced block; `path` is the note it is in, for `FROM [[]]`. A query
+ * that does not parse fails with a QueryError.
+ */
+export async function runQuery(vault: VaultHandle, query: string, path?: string): Promise<QueryResult> {
+    return await invoke<QueryResult>('run_query', { vault, query, path: path ?? null });
+}
+
+/**
+ * Files that changed on disk since the index was last saved. Emitted after
+ * scanVault answers from the persistent index.
+ */
+export interface IndexDelta {
+    vault: VaultHandle;
+    added: FileEntry[];
+    modified: FileEntry[];
+    removed: string[]; // Relative paths
+}
+
+export async function onIndexDelta(handler: (delta: IndexDelta) => void): Promise<UnlistenFn> {
+    return await listen<IndexDelta>('vault://index-delta', (event) => handler(event.payload));
+}
+
+/**
+ * Applies an index delta to a file list, keeping it sorted by relative path.
+ */
+export function applyIndexDelta(files: FileEntry[], delta: IndexDelta): FileEntry[] {
+    const removed = new Set(delta.removed);
+    const added = new Set(delta.added.map(f => f.relativePath));
+    return files
+        .filter(f => !removed.has(f.relativePath) && !added.has(f.relativePath))
+        .concat(delta.added)
+        .sort((a, b) => a.relativePath.localeCompare(b.relativePath));
+}
+
+/**
+ * Handlers for changes reported by the backend file watcher. Payloads carry
+ * the vault they belong to so stale events from a closed vault can be ignored.
+ */
+export interface VaultChangeHandlers {
+    created: (vault: VaultHandle, file: FileEntry) => void;
+    modified: (vault: VaultHandle, file: FileEntry) => void;
+    deleted: (vault: VaultHandle, file: FileEntry) => void;
+    renamed: (vault: VaultHandle, from: FileEntry, to: FileEntry) => void;
+}
+
+export async function onVaultChange(handlers: VaultChangeHandlers): Promise<UnlistenFn> {
+    type FileEvent = { vault: VaultHandle; file: FileEntry };
+    type RenameEvent = { vault: VaultHandle; from: FileEntry; to: FileEntry };
+
+    const unlisteners = await Promise.all([
+        listen<FileEvent>('vault://created', (e) => handlers.created(e.payload.vault, e.payload.file)),
+        listen<FileEvent>('vault://modified', (e) => handlers.modified(e.payload.vault, e.payload.file)),
+        listen<FileEvent>('vault://deleted', (e) => handlers.deleted(e.payload.vault, e.payload.file)),
+        listen<RenameEvent>('vault://renamed', (e) => handlers.renamed(e.payload.vault, e.payload.from, e.payload.to)),
+    ]);
+    return () => unlisteners.forEach(unlisten => unlisten());
 }
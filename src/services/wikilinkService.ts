@@ -1,4 +1,4 @@
-import type { FileEntry } from './fileService';
+import { resolveLink, type FileEntry, type VaultHandle } from './fileService';
 
 export interface ResolvedWikilink {
     target: string;
@@ -7,64 +7,27 @@ export interface ResolvedWikilink {
 }
 
 /**
- * Resolves a wikilink target to a file in the vault.
- * 
+ * Resolves a wikilink target to a file in the vault, using the backend's
+ * link index.
+ *
  * Resolution order:
  * 1. Exact match on filename (without .md extension)
  * 2. Case-insensitive match on filename
- * 3. Match on basename if target includes path separator
+ * 3. Match on relative path if target includes path separator
+ *
+ * A `#Heading` or `#^block` suffix is ignored for resolution.
+ * @param from Relative path of the note the link is in
  */
-export function resolveWikilink(
+export async function resolveWikilink(
+    vault: VaultHandle,
     target: string,
-    files: FileEntry[]
-): ResolvedWikilink {
-    if (!target || files.length === 0) {
+    from?: string,
+): Promise<ResolvedWikilink> {
+    if (!target) {
         return { target, file: null, exists: false };
     }
-
-    const normalizedTarget = target.trim();
-
-    // Try exact match on filename (without extension)
-    let match = files.find((f) => {
-        const nameWithoutExt = f.name.replace(/\.md$/, '');
-        return nameWithoutExt === normalizedTarget;
-    });
-
-    if (match) {
-        return { target, file: match, exists: true };
-    }
-
-    // Try case-insensitive match
-    const lowerTarget = normalizedTarget.toLowerCase();
-    match = files.find((f) => {
-        const nameWithoutExt = f.name.replace(/\.md$/, '').toLowerCase();
-        return nameWithoutExt === lowerTarget;
-    });
-
-    if (match) {
-        return { target, file: match, exists: true };
-    }
-
-    // Try matching on relative path (for targets like "folder/Page")
-    if (normalizedTarget.includes('/')) {
-        const targetWithExt = normalizedTarget.endsWith('.md')
-            ? normalizedTarget
-            : `${normalizedTarget}.md`;
-
-        match = files.find((f) => f.relativePath === targetWithExt);
-
-        if (!match) {
-            // Case-insensitive path match
-            const lowerPath = targetWithExt.toLowerCase();
-            match = files.find((f) => f.relativePath.toLowerCase() === lowerPath);
-        }
-
-        if (match) {
-            return { target, file: match, exists: true };
-        }
-    }
-
-    return { target, file: null, exists: false };
+    const { file, exists } = await resolveLink(vault, target, from);
+    return { target, file, exists };
 }
 
 /**
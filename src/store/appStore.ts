@@ -1,6 +1,6 @@
 import { createContext, useContext } from 'solid-js';
 import { createStore, SetStoreFunction } from 'solid-js/store';
-import type { FileEntry } from '../services/fileService';
+import type { FileEntry, NoteMerge, NoteVersion, WriteConflict } from '../services/fileService';
 
 // ============================================================================
 // Type Definitions
@@ -9,6 +9,7 @@ import type { FileEntry } from '../services/fileService';
 export interface AppState {
     // File management
     vault: {
+        root: string | null; // Canonical vault handle
         files: FileEntry[];
         currentFile: FileEntry | null;
         isScanning: boolean;
@@ -21,6 +22,10 @@ export interface AppState {
         isDirty: boolean;
         saveStatus: 'saved' | 'saving' | 'idle';
         key: number; // Increment to force editor re-render
+        version: NoteVersion | null; // On-disk version the content was loaded from
+        baseContent: string; // Text of that version, the base for merges
+        conflict: WriteConflict | null; // Save refused because the file changed on disk
+        merge: NoteMerge | null; // Attempted merge of a conflict, if any
     };
 
     // Navigation history
@@ -43,6 +48,7 @@ export interface AppState {
 
 export const createInitialState = (): AppState => ({
     vault: {
+        root: null,
         files: [],
         currentFile: null,
         isScanning: true,
@@ -53,6 +59,10 @@ export const createInitialState = (): AppState => ({
         isDirty: false,
         saveStatus: 'idle',
         key: 0,
+        version: null,
+        baseContent: '',
+        conflict: null,
+        merge: null,
     },
     history: {
         entries: [],